@@ -1,133 +1,143 @@
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::get_associated_token_address_with_program_id;
-use crate::state::{DexType, PathStep};
-use super::types::{ProgramIds, FixedAddresses, get_fixed_addresses, pda_seeds};
-use std::collections::HashMap;
+use crate::state::{LogLevel, StepView};
+use crate::account_resolver::AccountTable;
+use super::types::{ProgramIds, FixedAddresses, get_fixed_addresses};
 
 /// 账户推导引擎（V2 协议）
 ///
 /// 目标：在“最小必需客户端账户（indices + 全局表）”基础上，链上统一推导“可确定”的账户，
-/// 包括用户 ATAs、Token/Token-2022 程序选择、部分固定地址与 PDA，降低客户端负担并提升一致性。
+/// 包括用户 ATAs、Token/Token-2022 程序选择与固定地址，降低客户端负担并提升一致性。
+/// DEX 侧 PDA 依赖本步实际 CPI 目标程序与客户端 bump，由 `dex_router::swaps` / `account_resolver::validation` 就地推导并校验。
 ///
 /// 流程要点：
 /// 1) initialize() 装载固定地址与系统程序；
 /// 2) derive_for_path():
 ///    - 基于 remaining_accounts 自动识别每个 mint 的 token program（Token/Token-2022）；
-///    - 为路径涉及的所有 mint 推导用户 ATAs 并缓存；
-///    - 按 DEX 类型推导必要 PDA/固定账户（如 CPMM authority、Pump 系列 PDA 等）。
+///    - 为路径涉及的所有 mint 推导用户 ATAs 并缓存。
 /// 3) 执行阶段：从缓存读取用户 ATAs/固定地址，配合 AccountResolver 解析出的 DEX 最小集 + 动态补充账户组装 CPI。
+///
 /// 注意：本模块不负责将账户加入 remaining_accounts，也不做强制校验，仅做推导与缓存（用于定位/日志）。
 ///
 /// 每个 DEX 的“链上推导 vs 客户端传入”：
 /// - Raydium CPMM
-///   链上推导：authority（固定地址）、用户 ATAs、每个 mint 的 token program 选择（用于派生 ATA）。
+///   链上推导：用户 ATAs、每个 mint 的 token program 选择（用于派生 ATA）；authority 取固定地址。
 ///   客户端传入（indices）：amm_config、pool_state、token0_vault、token1_vault、input_mint、output_mint、observation_state；
 ///   说明：CPI metas 中 token_program 重复位来自外部传入（合约入口的 token_program）。
 ///
 /// - Raydium CLMM
 ///   链上推导：用户 ATAs、每个 mint 的 token program 选择（用于派生 ATA）。
 ///   客户端传入（indices 基础 11 项）：clmm_program、amm_config、pool_state、input_vault、output_vault、
-///     observation_state、token_program、token_program_2022、memo_program、input_vault_mint、output_vault_mint；
-///   客户端追加（不计入 indices）：tick_array_extension、tick arrays（动态）；
-///   合约在 CPI 前按 owner==clmm_program 动态注入上述追加账户到 metas（顺序沿用全局表）。
+///   observation_state、token_program、token_program_2022、memo_program、input_vault_mint、output_vault_mint；
+///   客户端传入（indices 尾部，本步专属）：可选 tick_array_extension + 1..=4 个 tick arrays（按 swap 方向）；
+///   合约校验其归属本步 pool_state 后按原顺序注入 CPI metas。
 ///
 /// - PumpFun（Bonding Curve）
-///   链上推导：bonding_curve PDA（mint）、associated_bonding_curve（bonding_curve+mint）、
-///     （可选）creator_vault、（可选）volume accumulators（global/user，买入时）。
+///   swaps 就地推导：global、event_authority、creator_vault、（买入时）volume accumulators（global/user）；
+///   bonding_curve PDA 在 validation 中校验。
 ///   客户端传入（indices）：bonding_curve(pool_id)、mint、creator；
 ///   客户端追加（全局表）：program、global、fee_recipient、event_authority、rent、associated_bonding_curve 等。
 ///
 /// - PumpSwap AMM
-///   链上推导：global_config PDA、pool/user 双边 ATAs、fee_recipient_ata、creator_vault_authority PDA 及其 ATA、
-///     每个 mint 的 token program 选择（用于派生 ATA）。
+///   链上推导：用户 ATAs、每个 mint 的 token program 选择；swaps 就地推导 global_config、event_authority、
+///   creator_vault_authority PDA 及其 ATA。
 ///   客户端传入（indices）：pool_state、base_mint、quote_mint、coin_creator；
 ///   客户端追加（全局表）：program、global_config、fee_recipient、fee_recipient_ata、event_authority、amm_program、
-///     creator_vault_ata 等。
+///   creator_vault_ata 等。
 ///
-/// 缓存策略（单次指令内存级）：
-/// - user_token_accounts: mint -> user_ata；
-/// - token_programs: mint -> token_program_id（mint.owner 自动识别后缓存）；
-/// - 各 DEX 推导缓存（如 Pump 系列 PDA）与 fixed_addresses；
+/// 缓存策略（单次指令内存级，无 String 键、无逐次堆分配）：
+/// - mints: 小向量 `MintEntry { mint, token_program, user_ata }`，容量按路径 mint 数预留，线性查找（≤ 20 项）；
+/// - fixed_addresses/program_ids 按值保存；
 /// - 执行时从缓存取 Pubkey，再在 remaining_accounts 中查找 AccountInfo 参与 CPI。
-
+///
 /// 完整的账户推导引擎
 pub struct DerivedAccounts {
-    // 基础缓存
-    pub user_token_accounts: HashMap<Pubkey, Pubkey>,  // mint -> user_ata
-    pub token_programs: HashMap<Pubkey, Pubkey>,       // mint -> token_program_id
-    
-    // DEX特定账户缓存
-    pub raydium_accounts: HashMap<String, Pubkey>,
-    pub pumpfun_accounts: HashMap<String, Pubkey>, 
-    pub pumpswap_accounts: HashMap<String, Pubkey>,
-    
-    // 系统程序和固定地址
-    pub system_programs: HashMap<String, Pubkey>,
+    // 基础缓存：mint -> (token_program, user_ata)
+    pub mints: Vec<MintEntry>,
+
+    // 固定地址与程序ID
     pub fixed_addresses: Option<FixedAddresses>,
+    pub program_ids: Option<ProgramIds>,
+
+    // 本次指令的日志级别（执行期各模块据此决定是否输出）
+    pub log_level: LogLevel,
+}
+
+/// 单个 mint 的缓存项
+#[derive(Clone, Copy, Debug)]
+pub struct MintEntry {
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    /// 尚未推导用户 ATA 时为 None（仅完成 token program 识别）
+    pub user_ata: Option<Pubkey>,
+}
+
+impl Default for DerivedAccounts {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DerivedAccounts {
     pub fn new() -> Self {
         Self {
-            user_token_accounts: HashMap::new(),
-            token_programs: HashMap::new(),
-            raydium_accounts: HashMap::new(),
-            pumpfun_accounts: HashMap::new(),
-            pumpswap_accounts: HashMap::new(),
-            system_programs: HashMap::new(),
+            mints: Vec::new(),
             fixed_addresses: None,
+            program_ids: None,
+            log_level: LogLevel::default(),
         }
     }
 
     /// 初始化系统
-    pub fn initialize(&mut self, program_ids: &ProgramIds) -> Result<()> {
+    pub fn initialize(&mut self, program_ids: &ProgramIds, log_level: LogLevel) -> Result<()> {
         self.fixed_addresses = Some(get_fixed_addresses()?);
-        
-        self.system_programs.insert("token_program".to_string(), program_ids.token_program);
-        self.system_programs.insert("token_2022_program".to_string(), program_ids.token_2022_program);
-        self.system_programs.insert("associated_token_program".to_string(), program_ids.associated_token_program);
-        self.system_programs.insert("system_program".to_string(), program_ids.system_program);
-        
+        self.program_ids = Some(program_ids.clone());
+        self.log_level = log_level;
         Ok(())
     }
 
+    #[inline]
+    fn mint_entry(&self, mint: &Pubkey) -> Option<&MintEntry> {
+        self.mints.iter().find(|e| e.mint == *mint)
+    }
+
     // ================================================================
     // 基础工具函数
     // ================================================================
 
     /// 推导用户ATA账户
     pub fn derive_user_ata(&mut self, user: &Pubkey, mint: &Pubkey, program_ids: &ProgramIds) -> Result<Pubkey> {
-        if let Some(existing) = self.user_token_accounts.get(mint) {
-            return Ok(*existing);
+        if let Some(existing) = self.mint_entry(mint).and_then(|e| e.user_ata) {
+            return Ok(existing);
         }
 
         let token_program = self.get_token_program_for_mint(mint, program_ids);
         let ata = get_associated_token_address_with_program_id(user, mint, &token_program);
 
-        self.user_token_accounts.insert(*mint, ata);
-        self.token_programs.insert(*mint, token_program);
+        match self.mints.iter_mut().find(|e| e.mint == *mint) {
+            Some(entry) => entry.user_ata = Some(ata),
+            None => self.mints.push(MintEntry { mint: *mint, token_program, user_ata: Some(ata) }),
+        }
         Ok(ata)
     }
 
     /// 获取 token program（带缓存；未命中时默认使用 Token Program）
-    pub fn get_token_program_for_mint(&mut self, mint: &Pubkey, program_ids: &ProgramIds) -> Pubkey {
-        if let Some(cached) = self.token_programs.get(mint) {
-            return *cached;
-        }
-        
-        program_ids.token_program
+    pub fn get_token_program_for_mint(&self, mint: &Pubkey, program_ids: &ProgramIds) -> Pubkey {
+        self.mint_entry(mint)
+            .map(|e| e.token_program)
+            .unwrap_or(program_ids.token_program)
     }
 
-    /// 从 remaining_accounts 检测并缓存某 mint 的 token program（Token 或 Token-2022）
+    /// 从全局账户表检测并缓存某 mint 的 token program（Token 或 Token-2022）
     pub fn detect_and_cache_token_program_for_mint(
         &mut self,
         mint: &Pubkey,
         program_ids: &ProgramIds,
-        remaining_accounts: &[AccountInfo],
+        table: &AccountTable,
     ) {
         // 若已缓存则跳过
-        if self.token_programs.get(mint).is_some() { return; }
-        if let Some(ai) = remaining_accounts.iter().find(|ai| ai.key() == *mint) {
+        if self.mint_entry(mint).is_some() { return; }
+        if let Some(ai) = table.find(mint) {
             let owner = ai.owner;
             let detected = if owner == &program_ids.token_program {
                 program_ids.token_program
@@ -137,244 +147,33 @@ impl DerivedAccounts {
                 // 未识别，退回默认 Token Program
                 program_ids.token_program
             };
-            self.token_programs.insert(*mint, detected);
-            msg!("[TokenDetect] mint={} program_id={} (cached)", mint, detected);
-        }
-    }
-
-
-    /// 推导Raydium CPMM authority (固定地址)
-    pub fn derive_raydium_cpmm_authority(&mut self) -> Result<Pubkey> {
-        let key = "cpmm_authority".to_string();
-        if let Some(existing) = self.raydium_accounts.get(&key) {
-            return Ok(*existing);
-        }
-
-        let fixed_addrs = self.fixed_addresses.as_ref()
-            .ok_or_else(|| error!(crate::errors::ArbitrageError::AccountNotFound))?;
-        
-        let authority = fixed_addrs.raydium_cpmm_authority;
-        self.raydium_accounts.insert(key, authority);
-        Ok(authority)
-    }
-
-    /// 推导PumpFun bonding curve PDA
-    pub fn derive_pumpfun_bonding_curve(&mut self, mint: &Pubkey, program_ids: &ProgramIds) -> Result<Pubkey> {
-        let key = format!("bonding_curve_{}", mint);
-        if let Some(existing) = self.pumpfun_accounts.get(&key) {
-            return Ok(*existing);
-        }
-
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPFUN_BONDING_CURVE, mint.as_ref()],
-            &program_ids.pumpfun,
-        );
-
-        self.pumpfun_accounts.insert(key, pda);
-        Ok(pda)
-    }
-
-    /// 推导PumpFun associated bonding curve (bonding curve的代币账户)
-    pub fn derive_pumpfun_associated_bonding_curve(
-        &mut self,
-        bonding_curve: &Pubkey,
-        mint: &Pubkey,
-        program_ids: &ProgramIds,
-    ) -> Result<Pubkey> {
-        let key = format!("associated_bonding_curve_{}_{}", bonding_curve, mint);
-        if let Some(existing) = self.pumpfun_accounts.get(&key) {
-            return Ok(*existing);
-        }
-
-        let token_program = self.get_token_program_for_mint(mint, program_ids);
-        let ata = get_associated_token_address_with_program_id(bonding_curve, mint, &token_program);
-
-        self.pumpfun_accounts.insert(key, ata);
-        Ok(ata)
-    }
-
-    /// 推导PumpFun creator vault PDA
-    pub fn derive_pumpfun_creator_vault(&mut self, creator: &Pubkey, program_ids: &ProgramIds) -> Result<Pubkey> {
-        let key = format!("creator_vault_{}", creator);
-        if let Some(existing) = self.pumpfun_accounts.get(&key) {
-            return Ok(*existing);
-        }
-
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPFUN_CREATOR_VAULT, creator.as_ref()],
-            &program_ids.pumpfun,
-        );
-
-        self.pumpfun_accounts.insert(key, pda);
-        Ok(pda)
-    }
-
-    /// 推导PumpFun global volume accumulator PDA (仅买入时需要)
-    pub fn derive_pumpfun_global_volume_accumulator(&mut self, program_ids: &ProgramIds) -> Result<Pubkey> {
-        let key = "global_volume_accumulator".to_string();
-        if let Some(existing) = self.pumpfun_accounts.get(&key) {
-            return Ok(*existing);
-        }
-
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPFUN_GLOBAL_VOLUME_ACCUMULATOR],
-            &program_ids.pumpfun,
-        );
-
-        self.pumpfun_accounts.insert(key, pda);
-        Ok(pda)
-    }
-
-    /// 推导PumpFun user volume accumulator PDA (仅买入时需要)
-    pub fn derive_pumpfun_user_volume_accumulator(&mut self, user: &Pubkey, program_ids: &ProgramIds) -> Result<Pubkey> {
-        let key = format!("user_volume_accumulator_{}", user);
-        if let Some(existing) = self.pumpfun_accounts.get(&key) {
-            return Ok(*existing);
-        }
-
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPFUN_USER_VOLUME_ACCUMULATOR, user.as_ref()],
-            &program_ids.pumpfun,
-        );
-
-        self.pumpfun_accounts.insert(key, pda);
-        Ok(pda)
-    }
-
-
-    /// 推导PumpSwap global config PDA
-    pub fn derive_pumpswap_global_config(&mut self, program_ids: &ProgramIds) -> Result<Pubkey> {
-        let key = "global_config".to_string();
-        if let Some(existing) = self.pumpswap_accounts.get(&key) {
-            return Ok(*existing);
-        }
-
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPSWAP_GLOBAL_CONFIG],
-            &program_ids.pumpswap,
-        );
-
-        self.pumpswap_accounts.insert(key, pda);
-        Ok(pda)
-    }
-
-    /// 推导pool的代币账户ATA
-    pub fn derive_pool_token_ata(&mut self, pool: &Pubkey, mint: &Pubkey, program_ids: &ProgramIds) -> Result<Pubkey> {
-        let key = format!("pool_ata_{}_{}", pool, mint);
-        if let Some(existing) = self.pumpswap_accounts.get(&key) {
-            return Ok(*existing);
+            self.mints.push(MintEntry { mint: *mint, token_program: detected, user_ata: None });
+            if self.log_level.verbose() {
+                msg!("[TokenDetect] mint={} program_id={} (cached)", mint, detected);
+            }
         }
-
-        let token_program = self.get_token_program_for_mint(mint, program_ids);
-        let ata = get_associated_token_address_with_program_id(pool, mint, &token_program);
-
-        self.pumpswap_accounts.insert(key, ata);
-        Ok(ata)
     }
 
-    /// 推导PumpSwap creator vault authority PDA (使用AMM程序)
-    pub fn derive_pumpswap_creator_vault_authority(&mut self, creator: &Pubkey) -> Result<Pubkey> {
-        let key = format!("creator_vault_authority_{}", creator);
-        if let Some(existing) = self.pumpswap_accounts.get(&key) {
-            return Ok(*existing);
-        }
-
-        let fixed_addrs = self.fixed_addresses.as_ref()
-            .ok_or_else(|| error!(crate::errors::ArbitrageError::AccountNotFound))?;
-
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPSWAP_CREATOR_VAULT, creator.as_ref()],
-            &fixed_addrs.pumpswap_amm_program,
-        );
 
-        self.pumpswap_accounts.insert(key, pda);
-        Ok(pda)
-    }
-
-    /// 推导creator vault ATA
-    pub fn derive_pumpswap_creator_vault_ata(
+    /// 为整个套利路径推导所有账户
+    ///
+    /// 仅识别 token program 并推导用户 ATAs；缓存按路径规模预留容量，避免循环内扩容。
+    pub fn derive_for_path(
         &mut self,
-        creator: &Pubkey,
-        quote_mint: &Pubkey,
+        path: &[StepView],
+        user: &Pubkey,
         program_ids: &ProgramIds,
-    ) -> Result<Pubkey> {
-        let key = format!("creator_vault_ata_{}_{}", creator, quote_mint);
-        if let Some(existing) = self.pumpswap_accounts.get(&key) {
-            return Ok(*existing);
-        }
-
-        let creator_vault_authority = self.derive_pumpswap_creator_vault_authority(creator)?;
-        let token_program = self.get_token_program_for_mint(quote_mint, program_ids);
-        let ata = get_associated_token_address_with_program_id(&creator_vault_authority, quote_mint, &token_program);
-
-        self.pumpswap_accounts.insert(key, ata);
-        Ok(ata)
-    }
-
-    /// 推导fee recipient ATA
-    pub fn derive_pumpswap_fee_recipient_ata(&mut self, mint: &Pubkey, program_ids: &ProgramIds) -> Result<Pubkey> {
-        let key = format!("fee_recipient_ata_{}", mint);
-        if let Some(existing) = self.pumpswap_accounts.get(&key) {
-            return Ok(*existing);
-        }
-
-        let fee_recipient = self.fixed_addresses.as_ref()
-            .ok_or_else(|| error!(crate::errors::ArbitrageError::AccountNotFound))?
-            .pumpswap_fee_recipient;
-
-        let token_program = self.get_token_program_for_mint(mint, program_ids);
-        let ata = get_associated_token_address_with_program_id(
-            &fee_recipient,
-            mint,
-            &token_program,
-        );
-
-        self.pumpswap_accounts.insert(key, ata);
-        Ok(ata)
-    }
-
-    /// 为整个套利路径推导所有账户
-    pub fn derive_for_path(&mut self, path: &[PathStep], user: &Pubkey, program_ids: &ProgramIds, remaining_accounts: &[AccountInfo]) -> Result<()> {
+        table: &AccountTable,
+    ) -> Result<()> {
+        self.mints.reserve(path.len() * 2);
         for step in path {
-            // 先尝试从 remaining_accounts 自动识别 token program（Token/Token-2022）
-            self.detect_and_cache_token_program_for_mint(&step.input_mint, program_ids, remaining_accounts);
-            self.detect_and_cache_token_program_for_mint(&step.output_mint, program_ids, remaining_accounts);
+            // 先尝试从全局账户表自动识别 token program（Token/Token-2022）
+            self.detect_and_cache_token_program_for_mint(&step.input_mint, program_ids, table);
+            self.detect_and_cache_token_program_for_mint(&step.output_mint, program_ids, table);
 
             // 再推导用户的输入输出代币账户（使用已缓存的正确 token program）
             self.derive_user_ata(user, &step.input_mint, program_ids)?;
             self.derive_user_ata(user, &step.output_mint, program_ids)?;
-            
-            // 根据DEX类型推导特定账户
-            match step.dex_type {
-                DexType::RaydiumCpmm => {
-                    self.derive_raydium_cpmm_authority()?;
-                }
-                DexType::RaydiumClmm => {
-                    // CLMM 主要依赖客户端提供的动态账户，这里仅完成用户 ATA 推导
-                }
-                DexType::PumpFunBondingCurve => {
-                    // 方向感知：若 output_mint 是 WSOL，则 token_mint= input_mint；否则 token_mint= output_mint
-                    let fixed_addrs = self.fixed_addresses.as_ref()
-                        .ok_or_else(|| error!(crate::errors::ArbitrageError::AccountNotFound))?;
-                    let token_mint = if step.output_mint == fixed_addrs.wrapped_sol_mint {
-                        step.input_mint
-                    } else {
-                        step.output_mint
-                    };
-
-                    self.derive_pumpfun_bonding_curve(&token_mint, program_ids)?;
-                    if let Some(bonding_curve) = &step.pool_id {
-                        self.derive_pumpfun_associated_bonding_curve(bonding_curve, &token_mint, program_ids)?;
-                    }
-                }
-                DexType::PumpSwap => {
-                    self.derive_pumpswap_global_config(program_ids)?;
-                    if let Some(pool_id) = &step.pool_id {
-                        self.derive_pool_token_ata(pool_id, &step.input_mint, program_ids)?;
-                        self.derive_pool_token_ata(pool_id, &step.output_mint, program_ids)?;
-                    }
-                }
-            }
         }
         Ok(())
     }
@@ -385,11 +184,16 @@ impl DerivedAccounts {
     
     /// 获取用户代币账户
     pub fn get_user_token_account(&self, mint: &Pubkey) -> Option<&Pubkey> {
-        self.user_token_accounts.get(mint)
+        self.mint_entry(mint).and_then(|e| e.user_ata.as_ref())
     }
 
     /// 获取固定地址
     pub fn get_fixed_addresses(&self) -> Option<&FixedAddresses> {
         self.fixed_addresses.as_ref()
     }
+
+    /// 获取程序ID配置（CPI 目标程序的白名单来源）
+    pub fn get_program_ids(&self) -> Option<&ProgramIds> {
+        self.program_ids.as_ref()
+    }
 }
\ No newline at end of file
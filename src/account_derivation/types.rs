@@ -1,5 +1,18 @@
 use anchor_lang::prelude::*;
 use std::str::FromStr;
+use crate::state::DexType;
+
+/// DEX 程序的已知部署地址：(mainnet, devnet)
+pub mod dex_program_ids {
+    pub const RAYDIUM_CPMM: (&str, &str) =
+        ("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C", "CPMDWBwJDtYax9qW7AyRuVC19Cc4L4Vcy4n2BHAbHkCW");
+    pub const RAYDIUM_CLMM: (&str, &str) =
+        ("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK", "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+    pub const PUMPFUN: (&str, &str) =
+        ("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P", "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+    pub const PUMPSWAP: (&str, &str) =
+        ("pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA", "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA");
+}
 
 #[derive(Clone)]
 pub struct ProgramIds {
@@ -25,22 +38,13 @@ impl Default for ProgramIds {
             else { Pubkey::from_str(default).unwrap() }
         }
         // mainnet 默认，允许 ENV 覆盖
-        let is_devnet = cfg!(feature = "devnet");
-        let (cpmm_def, clmm_def, pumpfun_def, pumpswap_def) = if is_devnet {
-            (
-                "CPMDWBwJDtYax9qW7AyRuVC19Cc4L4Vcy4n2BHAbHkCW",
-                "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK",
-                "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P",
-                "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA",
-            )
-        } else {
-            (
-                "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C",
-                "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK",
-                "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P",
-                "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA",
-            )
-        };
+        let network = |ids: (&'static str, &'static str)| if cfg!(feature = "devnet") { ids.1 } else { ids.0 };
+        let (cpmm_def, clmm_def, pumpfun_def, pumpswap_def) = (
+            network(dex_program_ids::RAYDIUM_CPMM),
+            network(dex_program_ids::RAYDIUM_CLMM),
+            network(dex_program_ids::PUMPFUN),
+            network(dex_program_ids::PUMPSWAP),
+        );
         Self {
             // Core DEX Programs（可被 ENV 覆盖）
             raydium_cpmm: env_pk("PROGRAM_ID_RAYDIUM_CPMM", cpmm_def),
@@ -58,6 +62,34 @@ impl Default for ProgramIds {
 }
 
 impl ProgramIds {
+    /// 本合约允许 CPI 的 DEX 程序白名单：配置值；`flex` 下另含各 DEX 的已知 mainnet/devnet 部署
+    pub fn is_dex_program(&self, program_id: &Pubkey) -> bool {
+        [DexType::RaydiumCpmm, DexType::RaydiumClmm, DexType::PumpFunBondingCurve, DexType::PumpSwap]
+            .into_iter()
+            .any(|dex_type| self.accepts_dex_program(dex_type, program_id))
+    }
+
+    /// 某 DEX 的配置程序 ID
+    pub fn dex_program(&self, dex_type: DexType) -> Pubkey {
+        match dex_type {
+            DexType::RaydiumCpmm => self.raydium_cpmm,
+            DexType::RaydiumClmm => self.raydium_clmm,
+            DexType::PumpFunBondingCurve => self.pumpfun,
+            DexType::PumpSwap => self.pumpswap,
+        }
+    }
+
+    /// `program_id` 是否可作为该 DEX 的程序：等于配置值，或（`flex`）为该 DEX 的已知 mainnet/devnet 部署
+    pub fn accepts_dex_program(&self, dex_type: DexType, program_id: &Pubkey) -> bool {
+        *program_id == self.dex_program(dex_type)
+            || (cfg!(feature = "flex") && known_dex_deployments(dex_type).contains(program_id))
+    }
+
+    /// 受支持的 token 程序（Token / Token-2022）
+    pub fn is_token_program(&self, program_id: &Pubkey) -> bool {
+        *program_id == self.token_program || *program_id == self.token_2022_program
+    }
+
     pub fn new(
         raydium_cpmm: Pubkey,
         raydium_clmm: Pubkey,
@@ -78,6 +110,17 @@ impl ProgramIds {
     }
 }
 
+/// 某 DEX 的已知部署（mainnet, devnet）
+pub fn known_dex_deployments(dex_type: DexType) -> [Pubkey; 2] {
+    let (mainnet, devnet) = match dex_type {
+        DexType::RaydiumCpmm => dex_program_ids::RAYDIUM_CPMM,
+        DexType::RaydiumClmm => dex_program_ids::RAYDIUM_CLMM,
+        DexType::PumpFunBondingCurve => dex_program_ids::PUMPFUN,
+        DexType::PumpSwap => dex_program_ids::PUMPSWAP,
+    };
+    [Pubkey::from_str(mainnet).unwrap(), Pubkey::from_str(devnet).unwrap()]
+}
+
 /// 固定账户地址常量 - 仅包含4个核心DEX的地址
 pub mod fixed_addresses {
     // ==============================================
@@ -119,6 +162,7 @@ pub mod fixed_addresses {
 pub mod instruction_discriminators {
     // Raydium CPMM
     pub const RAYDIUM_CPMM_SWAP_BASE_IN: &[u8; 8] = &[143, 190, 90, 218, 196, 30, 51, 222];
+    pub const RAYDIUM_CPMM_SWAP_BASE_OUT: &[u8; 8] = &[55, 217, 98, 86, 163, 74, 180, 173];
     
     // Raydium CLMM  
     pub const RAYDIUM_CLMM_SWAP_V2: &[u8; 8] = &[43, 4, 237, 11, 26, 201, 30, 98];
@@ -151,6 +195,100 @@ pub mod pda_seeds {
     pub const PUMPSWAP_EVENT_AUTHORITY: &[u8] = b"__event_authority";
     pub const PUMPSWAP_GLOBAL_VOLUME_ACCUMULATOR: &[u8] = b"global_volume_accumulator";
     pub const PUMPSWAP_USER_VOLUME_ACCUMULATOR: &[u8] = b"user_volume_accumulator";
+    
+    // Raydium CLMM PDA种子（tick array 另附 start_tick_index 的大端字节）
+    pub const RAYDIUM_CLMM_TICK_ARRAY: &[u8] = b"tick_array";
+    pub const RAYDIUM_CLMM_TICK_ARRAY_BITMAP_EXTENSION: &[u8] = b"pool_tick_array_bitmap_extension";
+}
+
+/// 外部 DEX 账户数据布局（字节偏移，含 8 字节 Anchor discriminator）
+pub mod pool_layouts {
+    // Raydium CPMM PoolState：discriminator(8) + amm_config(32) + pool_creator(32) + token_0_vault ...
+    pub const CPMM_POOL_AMM_CONFIG: usize = 8;
+    pub const CPMM_POOL_TOKEN_0_VAULT: usize = 72;
+    pub const CPMM_POOL_TOKEN_1_VAULT: usize = 104;
+    pub const CPMM_POOL_TOKEN_0_MINT: usize = 168;
+    pub const CPMM_POOL_TOKEN_1_MINT: usize = 200;
+    pub const CPMM_POOL_OBSERVATION_KEY: usize = 296;
+    pub const CPMM_POOL_MIN_LEN: usize = 328;
+    // ... auth_bump/status/decimals(5) + lp_supply(8) + protocol_fees_token_0/1 + fund_fees_token_0/1（vault 中不属于储备的部分）
+    pub const CPMM_POOL_PROTOCOL_FEES_TOKEN_0: usize = 341;
+    pub const CPMM_POOL_PROTOCOL_FEES_TOKEN_1: usize = 349;
+    pub const CPMM_POOL_FUND_FEES_TOKEN_0: usize = 357;
+    pub const CPMM_POOL_FUND_FEES_TOKEN_1: usize = 365;
+    pub const CPMM_POOL_QUOTE_MIN_LEN: usize = 373;
+    // Raydium CPMM AmmConfig：discriminator(8) + bump(1) + disable_create_pool(1) + index(2) + trade_fee_rate(u64, 1e6 分母)
+    pub const CPMM_CONFIG_TRADE_FEE_RATE: usize = 12;
+    pub const CPMM_CONFIG_MIN_LEN: usize = 20;
+
+    // Raydium CLMM PoolState（#[repr(C, packed)]）：discriminator(8) + bump(1) + amm_config(32) + owner(32) + token_mint_0 ...
+    pub const CLMM_POOL_AMM_CONFIG: usize = 9;
+    pub const CLMM_POOL_TOKEN_MINT_0: usize = 73;
+    pub const CLMM_POOL_TOKEN_MINT_1: usize = 105;
+    pub const CLMM_POOL_TOKEN_VAULT_0: usize = 137;
+    pub const CLMM_POOL_TOKEN_VAULT_1: usize = 169;
+    pub const CLMM_POOL_OBSERVATION_KEY: usize = 201;
+    pub const CLMM_POOL_MIN_LEN: usize = 233;
+    // ... mint_decimals_0/1(2) + tick_spacing(u16) + liquidity(u128) + sqrt_price_x64(u128) + tick_current(i32)
+    pub const CLMM_POOL_TICK_SPACING: usize = 235;
+    pub const CLMM_POOL_LIQUIDITY: usize = 237;
+    pub const CLMM_POOL_SQRT_PRICE_X64: usize = 253;
+    pub const CLMM_POOL_TICK_CURRENT: usize = 269;
+    // ... fee_growth/protocol_fees/swap 统计 + status(1) + padding(7) + 3×RewardInfo(169) + tick_array_bitmap([u64; 16])
+    pub const CLMM_POOL_STATUS: usize = 389;
+    pub const CLMM_POOL_TICK_ARRAY_BITMAP: usize = 904;
+    pub const CLMM_POOL_QUOTE_MIN_LEN: usize = 1032;
+    // Raydium CLMM AmmConfig：discriminator(8) + bump(1) + index(2) + owner(32) + protocol_fee_rate(u32) + trade_fee_rate(u32, 1e6 分母) + tick_spacing(u16)
+    pub const CLMM_CONFIG_TRADE_FEE_RATE: usize = 47;
+    pub const CLMM_CONFIG_TICK_SPACING: usize = 51;
+    pub const CLMM_CONFIG_MIN_LEN: usize = 53;
+    // Raydium CLMM TickArrayState（#[repr(C, packed)]）：discriminator(8) + pool_id(32) + start_tick_index(i32) + 60×TickState(168)
+    pub const CLMM_TICK_ARRAY_POOL_ID: usize = 8;
+    pub const CLMM_TICK_ARRAY_START_TICK_INDEX: usize = 40;
+    pub const CLMM_TICK_ARRAY_TICKS: usize = 44;
+    pub const CLMM_TICK_ARRAY_MIN_LEN: usize = 10124;
+    // TickState：tick(i32) + liquidity_net(i128) + liquidity_gross(u128) + fee/reward growth + padding
+    pub const CLMM_TICK_STATE_LEN: usize = 168;
+    pub const CLMM_TICK_STATE_LIQUIDITY_NET: usize = 4;
+    pub const CLMM_TICK_STATE_LIQUIDITY_GROSS: usize = 20;
+    // Raydium CLMM TickArrayBitmapExtension（#[repr(C, packed)]）：discriminator(8) + pool_id(32) + 正/负向各 14×[u64; 8]
+    pub const CLMM_BITMAP_EXTENSION_POOL_ID: usize = 8;
+    pub const CLMM_BITMAP_EXTENSION_POSITIVE: usize = 40;
+    pub const CLMM_BITMAP_EXTENSION_NEGATIVE: usize = 936;
+    pub const CLMM_BITMAP_EXTENSION_MIN_LEN: usize = 1832;
+
+    // PumpFun BondingCurve：discriminator(8) + 5×u64 reserves/supply(40) + complete(1) + creator(32)
+    pub const PUMPFUN_BONDING_CURVE_VIRTUAL_TOKEN_RESERVES: usize = 8;
+    pub const PUMPFUN_BONDING_CURVE_VIRTUAL_SOL_RESERVES: usize = 16;
+    pub const PUMPFUN_BONDING_CURVE_REAL_TOKEN_RESERVES: usize = 24;
+    pub const PUMPFUN_BONDING_CURVE_REAL_SOL_RESERVES: usize = 32;
+    pub const PUMPFUN_BONDING_CURVE_TOKEN_TOTAL_SUPPLY: usize = 40;
+    pub const PUMPFUN_BONDING_CURVE_COMPLETE: usize = 48;
+    pub const PUMPFUN_BONDING_CURVE_CREATOR: usize = 49;
+    pub const PUMPFUN_BONDING_CURVE_MIN_LEN: usize = 81;
+
+    // PumpFun Global：discriminator(8) + initialized(1) + authority/fee_recipient(64) + 4×u64 初始参数 + fee_basis_points ...
+    pub const PUMPFUN_GLOBAL_FEE_RECIPIENT: usize = 41;
+    pub const PUMPFUN_GLOBAL_FEE_BASIS_POINTS: usize = 105;
+    pub const PUMPFUN_GLOBAL_MIN_LEN: usize = 113;
+    // ... withdraw_authority(32) + enable_migrate(1) + pool_migration_fee(8) + creator_fee_basis_points（旧版本账户无此字段）
+    pub const PUMPFUN_GLOBAL_CREATOR_FEE_BASIS_POINTS: usize = 154;
+
+    // PumpSwap Pool：discriminator(8) + pool_bump(1) + index(2) + creator(32) + base_mint ...
+    pub const PUMPSWAP_POOL_BASE_MINT: usize = 43;
+    pub const PUMPSWAP_POOL_QUOTE_MINT: usize = 75;
+    pub const PUMPSWAP_POOL_LP_MINT: usize = 107;
+    pub const PUMPSWAP_POOL_BASE_TOKEN_ACCOUNT: usize = 139;
+    pub const PUMPSWAP_POOL_QUOTE_TOKEN_ACCOUNT: usize = 171;
+    pub const PUMPSWAP_POOL_LP_SUPPLY: usize = 203;
+    pub const PUMPSWAP_POOL_COIN_CREATOR: usize = 211;
+    pub const PUMPSWAP_POOL_MIN_LEN: usize = 243;
+
+    // PumpSwap GlobalConfig：discriminator(8) + admin(32) + lp/protocol fee bps + disable_flags(1) + 8×recipient(256) + coin_creator_fee bps
+    pub const PUMPSWAP_GLOBAL_LP_FEE_BASIS_POINTS: usize = 40;
+    pub const PUMPSWAP_GLOBAL_PROTOCOL_FEE_BASIS_POINTS: usize = 48;
+    pub const PUMPSWAP_GLOBAL_MIN_LEN: usize = 56;
+    pub const PUMPSWAP_GLOBAL_COIN_CREATOR_FEE_BASIS_POINTS: usize = 313;
 }
 
 /// 获取所有固定地址
@@ -214,58 +352,66 @@ pub struct FixedAddresses {
 /// PDA推导辅助函数
 pub mod pda_utils {
     use super::*;
-    
+    use crate::errors::ArbitrageError;
+
+    /// 推导 PDA：提供 bump 时用 `create_program_address` 单次复核（省去 bump 搜索的 CU），否则回退 `find_program_address`
+    pub fn derive_pda(seeds: &[&[u8]], bump: Option<u8>, program_id: &Pubkey) -> Result<Pubkey> {
+        match bump {
+            Some(bump) => {
+                let bump_seed = [bump];
+                let mut with_bump: [&[u8]; 3] = [&[]; 3];
+                require!(seeds.len() < with_bump.len(), ArbitrageError::InvalidPdaBump);
+                with_bump[..seeds.len()].copy_from_slice(seeds);
+                with_bump[seeds.len()] = &bump_seed;
+                Pubkey::create_program_address(&with_bump[..=seeds.len()], program_id)
+                    .map_err(|_| error!(ArbitrageError::InvalidPdaBump))
+            }
+            None => Ok(Pubkey::find_program_address(seeds, program_id).0),
+        }
+    }
+
     /// 推导PumpFun bonding curve PDA
-    pub fn derive_pumpfun_bonding_curve(mint: &Pubkey, program_id: &Pubkey) -> Result<Pubkey> {
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPFUN_BONDING_CURVE, mint.as_ref()],
-            program_id,
-        );
-        Ok(pda)
+    pub fn derive_pumpfun_bonding_curve(mint: &Pubkey, bump: Option<u8>, program_id: &Pubkey) -> Result<Pubkey> {
+        derive_pda(&[pda_seeds::PUMPFUN_BONDING_CURVE, mint.as_ref()], bump, program_id)
     }
-    
+
+    /// 推导PumpFun global PDA
+    pub fn derive_pumpfun_global(bump: Option<u8>, program_id: &Pubkey) -> Result<Pubkey> {
+        derive_pda(&[pda_seeds::PUMPFUN_GLOBAL], bump, program_id)
+    }
+
+    /// 推导PumpFun event authority PDA
+    pub fn derive_pumpfun_event_authority(bump: Option<u8>, program_id: &Pubkey) -> Result<Pubkey> {
+        derive_pda(&[pda_seeds::PUMPFUN_EVENT_AUTHORITY], bump, program_id)
+    }
+
     /// 推导PumpFun creator vault PDA
-    pub fn derive_pumpfun_creator_vault(creator: &Pubkey, program_id: &Pubkey) -> Result<Pubkey> {
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPFUN_CREATOR_VAULT, creator.as_ref()],
-            program_id,
-        );
-        Ok(pda)
+    pub fn derive_pumpfun_creator_vault(creator: &Pubkey, bump: Option<u8>, program_id: &Pubkey) -> Result<Pubkey> {
+        derive_pda(&[pda_seeds::PUMPFUN_CREATOR_VAULT, creator.as_ref()], bump, program_id)
     }
     
     /// 推导PumpFun global volume accumulator PDA
-    pub fn derive_pumpfun_global_volume_accumulator(program_id: &Pubkey) -> Result<Pubkey> {
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPFUN_GLOBAL_VOLUME_ACCUMULATOR],
-            program_id,
-        );
-        Ok(pda)
+    pub fn derive_pumpfun_global_volume_accumulator(bump: Option<u8>, program_id: &Pubkey) -> Result<Pubkey> {
+        derive_pda(&[pda_seeds::PUMPFUN_GLOBAL_VOLUME_ACCUMULATOR], bump, program_id)
     }
     
     /// 推导PumpFun user volume accumulator PDA
-    pub fn derive_pumpfun_user_volume_accumulator(user: &Pubkey, program_id: &Pubkey) -> Result<Pubkey> {
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPFUN_USER_VOLUME_ACCUMULATOR, user.as_ref()],
-            program_id,
-        );
-        Ok(pda)
+    pub fn derive_pumpfun_user_volume_accumulator(user: &Pubkey, bump: Option<u8>, program_id: &Pubkey) -> Result<Pubkey> {
+        derive_pda(&[pda_seeds::PUMPFUN_USER_VOLUME_ACCUMULATOR, user.as_ref()], bump, program_id)
     }
     
     /// 推导PumpSwap global config PDA
-    pub fn derive_pumpswap_global_config(program_id: &Pubkey) -> Result<Pubkey> {
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPSWAP_GLOBAL_CONFIG],
-            program_id,
-        );
-        Ok(pda)
+    pub fn derive_pumpswap_global_config(bump: Option<u8>, program_id: &Pubkey) -> Result<Pubkey> {
+        derive_pda(&[pda_seeds::PUMPSWAP_GLOBAL_CONFIG], bump, program_id)
+    }
+
+    /// 推导PumpSwap event authority PDA
+    pub fn derive_pumpswap_event_authority(bump: Option<u8>, program_id: &Pubkey) -> Result<Pubkey> {
+        derive_pda(&[pda_seeds::PUMPSWAP_EVENT_AUTHORITY], bump, program_id)
     }
     
     /// 推导PumpSwap creator vault PDA
-    pub fn derive_pumpswap_creator_vault(creator: &Pubkey, amm_program: &Pubkey) -> Result<Pubkey> {
-        let (pda, _) = Pubkey::find_program_address(
-            &[pda_seeds::PUMPSWAP_CREATOR_VAULT, creator.as_ref()],
-            amm_program,
-        );
-        Ok(pda)
+    pub fn derive_pumpswap_creator_vault(creator: &Pubkey, bump: Option<u8>, amm_program: &Pubkey) -> Result<Pubkey> {
+        derive_pda(&[pda_seeds::PUMPSWAP_CREATOR_VAULT, creator.as_ref()], bump, amm_program)
     }
 }
\ No newline at end of file
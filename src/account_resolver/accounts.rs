@@ -1,12 +1,15 @@
 use anchor_lang::prelude::*;
+use crate::state::PdaBumps;
 
 /// 账户结构定义（V2 协议 - 客户端最小集 + 合约推导项说明）
 /// 用途：
 /// - 表达各 DEX 在 V2 协议下客户端需要显式传入的最小账户集合；
 /// - 注明哪些账户在合约内可通过 PDA/固定地址/ATA 推导，避免重复传参；
 /// - 对齐客户端 indices 顺序常量，便于双端一致。
+///
 /// 说明：以下结构体仅包含“indices 最小集”。CPI 还需要的其它账户由客户端追加到全局表 remaining_accounts（不计入 indices），
 /// 合约仅计算期望值/选择正确 token program 并在全局表中定位/校验/记录日志，不负责“补账户”。
+///
 /// Raydium CPMM账户 - 仅包含客户端传递的7个账户（indices）
 /// 对应客户端 SmartAccountCollector.collect_raydium_cpmm() 的传递顺序
 #[derive(Clone)]
@@ -39,11 +42,11 @@ pub struct RaydiumClmmAccounts<'info> {
     pub memo_program: &'info AccountInfo<'info>,
     pub input_vault_mint: &'info AccountInfo<'info>,
     pub output_vault_mint: &'info AccountInfo<'info>,
+    // indices[11..]：可选 tick_array_extension 居首，其后为按 swap 方向排序的 tick arrays；
+    // 校验阶段核对归属本步 pool_state，CPI 时按原顺序追加（可写）
+    pub dynamic_accounts: Vec<&'info AccountInfo<'info>>,
 }
 
-// 说明：CLMM 的 tick arrays 与 tick_array_extension 不在 indices 最小集中，需由客户端追加到全局表；
-// 合约在执行前按 owner == clmm_program 从全局表动态注入到 CPI metas。
-
 /// PumpFun账户 - 仅包含客户端传递的3个账户（indices）  
 /// 对应客户端 SmartAccountCollector.collect_pumpfun() 的传递顺序
 #[derive(Clone)]
@@ -53,6 +56,8 @@ pub struct PumpfunAccounts<'info> {
     pub creator: &'info AccountInfo<'info>,            // 3. 创建者地址
     // 可选扩展：indices 可追加 fee_recipient（若提供则优先使用）
     pub fee_recipient_opt: Option<&'info AccountInfo<'info>>, 
+    // 客户端提供的 PDA bump（来自 mapping.bumps）
+    pub bumps: PdaBumps,
     
     // 注意：以下账户不在 indices 最小集中（需客户端追加到全局表，合约仅做期望值定位/校验/日志）：
     // - program、global_account、fee_recipient（可选由 indices 指定）、event_authority、rent
@@ -74,6 +79,8 @@ pub struct PumpswapAccounts<'info> {
     // 可选扩展：indices 可追加 fee_recipient 与 fee_recipient_ata（若提供则优先使用）
     pub fee_recipient_opt: Option<&'info AccountInfo<'info>>,
     pub fee_recipient_ata_opt: Option<&'info AccountInfo<'info>>,
+    // 客户端提供的 PDA bump（来自 mapping.bumps）
+    pub bumps: PdaBumps,
     
     // 注意：以下账户不在 indices 最小集中：
     // - global_config、event_authority、amm_program（客户端追加到全局表；其中 amm_program 需可执行校验）
@@ -1,5 +1,9 @@
 pub mod resolver;
 pub mod accounts;
+pub mod validation;
+pub mod table;
 
 pub use resolver::*;
-pub use accounts::*;
\ No newline at end of file
+pub use accounts::*;
+pub use validation::*;
+pub use table::*;
\ No newline at end of file
@@ -1,5 +1,5 @@
 use anchor_lang::prelude::*;
-use crate::state::{DexType, PathAccountMappingV2};
+use crate::state::{DexType, LogLevel, MappingView};
 use crate::errors::ArbitrageError;
 use super::accounts::*;
 use crate::dex_router::types::{get_expected_account_count, constants};
@@ -8,23 +8,26 @@ use std::collections::HashSet;
 
 pub struct AccountResolver<'info> {
     remaining_accounts: &'info [AccountInfo<'info>],
+    log_level: LogLevel,
 }
 
 impl<'info> AccountResolver<'info> {
     pub fn new(
         remaining_accounts: &'info [AccountInfo<'info>],
+        log_level: LogLevel,
     ) -> Self {
         Self {
             remaining_accounts,
+            log_level,
         }
     }
 
     /// 按 V2 indices 解析 Raydium CPMM 所需账户（客户端 indices 顺序与常量对齐）
     pub fn resolve_raydium_cpmm_by_indices(
         &self,
-        mapping: &PathAccountMappingV2,
+        mapping: MappingView,
     ) -> Result<RaydiumCpmmAccounts<'info>> {
-        let idxs = &mapping.indices;
+        let idxs = mapping.indices;
         if idxs.len() != 7 {
             return Err(ArbitrageError::InvalidAccountCount.into());
         }
@@ -39,15 +42,17 @@ impl<'info> AccountResolver<'info> {
         })
     }
 
-    /// 按 V2 indices 解析 Raydium CLMM 所需账户（基础 11 个账户，tick arrays 单独追加到 remaining）
+    /// 按 V2 indices 解析 Raydium CLMM 所需账户（基础 11 个账户 + 尾部 extension/tick arrays）
     pub fn resolve_raydium_clmm_by_indices(
         &self,
-        mapping: &PathAccountMappingV2,
+        mapping: MappingView,
     ) -> Result<RaydiumClmmAccounts<'info>> {
-        let idxs = &mapping.indices;
-        if idxs.len() != 11 {
+        let idxs = mapping.indices;
+        let base = constants::RAYDIUM_CLMM_BASE_ACCOUNT_COUNT as usize;
+        if idxs.len() <= base || idxs.len() > constants::RAYDIUM_CLMM_MAX_ACCOUNT_COUNT as usize {
             return Err(ArbitrageError::InvalidAccountCount.into());
         }
+        let dynamic_accounts = idxs[base..].iter().map(|&i| self.ai(i)).collect::<Result<Vec<_>>>()?;
         Ok(RaydiumClmmAccounts {
             clmm_program: self.ai(idxs[0])?,
             amm_config: self.ai(idxs[1])?,
@@ -60,15 +65,16 @@ impl<'info> AccountResolver<'info> {
             memo_program: self.ai(idxs[8])?,
             input_vault_mint: self.ai(idxs[9])?,
             output_vault_mint: self.ai(idxs[10])?,
+            dynamic_accounts,
         })
     }
 
     /// 按 V2 indices 解析 Pumpfun Bonding Curve 所需账户
     pub fn resolve_pumpfun_by_indices(
         &self,
-        mapping: &PathAccountMappingV2,
+        mapping: MappingView,
     ) -> Result<PumpfunAccounts<'info>> {
-        let idxs = &mapping.indices;
+        let idxs = mapping.indices;
         if idxs.len() < 3 || idxs.len() > 4 {
             msg!("[Resolver] PumpFun indices mismatch: expected 3..=4 got {}", idxs.len());
             msg!("[Resolver] indices={:?}", idxs);
@@ -80,15 +86,16 @@ impl<'info> AccountResolver<'info> {
             mint: self.ai(idxs[1])?,
             creator: self.ai(idxs[2])?,
             fee_recipient_opt,
+            bumps: mapping.bumps,
         })
     }
 
     /// 按 V2 indices 解析 Pumpswap 所需账户
     pub fn resolve_pumpswap_by_indices(
         &self,
-        mapping: &PathAccountMappingV2,
+        mapping: MappingView,
     ) -> Result<PumpswapAccounts<'info>> {
-        let idxs = &mapping.indices;
+        let idxs = mapping.indices;
         if idxs.len() < 4 || idxs.len() > 6 {
             msg!("[Resolver] PumpSwap indices mismatch: expected 4..=6 got {}", idxs.len());
             msg!("[Resolver] indices={:?}", idxs);
@@ -103,25 +110,28 @@ impl<'info> AccountResolver<'info> {
             coin_creator: self.ai(idxs[3])?,
             fee_recipient_opt,
             fee_recipient_ata_opt,
+            bumps: mapping.bumps,
         })
     }
 
     /// 校验 indices 数量与 DEX 期望一致，并输出关键日志（含 signer/writable 提示）
     ///
-    /// V2 协议：indices 仅覆盖“固定最小集”，CLMM 的 tick arrays/extension 等动态账户
-    /// 由客户端追加到全局账户表，并在 swaps 中按程序 owner 动态注入 CPI metas。
-    pub fn validate_indices_for_dex(&self, mapping: &PathAccountMappingV2) -> Result<()> {
+    /// V2 协议：indices 覆盖“固定最小集”；CLMM 另在尾部列出本步的 extension/tick arrays，
+    /// 仅这些账户被注入本步 CPI（同一路径多个 CLMM 池互不串用）。
+    pub fn validate_indices_for_dex(&self, mapping: MappingView) -> Result<()> {
         let actual_len_u8 = mapping.indices.len() as u8;
         let expected = get_expected_account_count(mapping.dex_type);
         let total = self.remaining_accounts.len();
 
-        // 数量校验（CLMM 基础 11 个）
+        // 数量校验（CLMM 基础 11 个 + 1..=5 个动态账户）
         match mapping.dex_type {
             DexType::RaydiumClmm => {
-                if actual_len_u8 != constants::RAYDIUM_CLMM_BASE_ACCOUNT_COUNT {
+                let max = constants::RAYDIUM_CLMM_MAX_ACCOUNT_COUNT;
+                if actual_len_u8 <= constants::RAYDIUM_CLMM_BASE_ACCOUNT_COUNT || actual_len_u8 > max {
                     msg!(
-                        "[Resolver] CLMM indices mismatch: expected {} got {}",
-                        constants::RAYDIUM_CLMM_BASE_ACCOUNT_COUNT,
+                        "[Resolver] CLMM indices mismatch: expected {}..={} got {}",
+                        constants::RAYDIUM_CLMM_BASE_ACCOUNT_COUNT + 1,
+                        max,
                         actual_len_u8
                     );
                     msg!("[Resolver] indices={:?}", mapping.indices);
@@ -173,13 +183,20 @@ impl<'info> AccountResolver<'info> {
             }
         }
 
-        // 角色顺序提示与 signer/writable 提示（仅日志）
+        // 角色顺序提示与 signer/writable 提示（仅日志，需 verbose）
+        if !self.log_level.verbose() {
+            return Ok(());
+        }
         let roles = expected_roles(mapping.dex_type);
-        let list_len = min(roles.len(), mapping.indices.len());
+        // CLMM 尾部动态账户（extension/tick arrays）无固定角色，统一记为 dynamic
+        let list_len = match mapping.dex_type {
+            DexType::RaydiumClmm => mapping.indices.len(),
+            _ => min(roles.len(), mapping.indices.len()),
+        };
         for j in 0..list_len {
+            let role = roles.get(j).copied().unwrap_or("dynamic");
             let idx = mapping.indices[j] as usize;
             let ai = &self.remaining_accounts[idx];
-            let role = roles[j];
             msg!(
                 "[Resolver] role={} idx={} key={} W={} S={}",
                 role,
@@ -0,0 +1,85 @@
+use anchor_lang::prelude::*;
+use crate::account_derivation::ProgramIds;
+use crate::errors::ArbitrageError;
+use std::collections::HashMap;
+
+/// SPL Token 账户基础长度（Token-2022 带扩展时更长，且第 165 字节为 AccountType）
+const TOKEN_ACCOUNT_LEN: usize = 165;
+/// Token-2022 AccountType::Account
+const TOKEN_2022_ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+/// 全局账户表索引（V2 协议）
+///
+/// 入口处对 remaining_accounts 一次性建索引，之后各步 O(1) 定位：
+/// - `by_key`: Pubkey -> 表内位置（重复 key 取首次出现）；
+/// - `token_accounts`: (owner, mint) -> 表内位置，仅收录 Token/Token-2022 拥有的 token 账户（不含 mint）。
+///
+/// 取代原先 `find_ai`/`find_ata`/`find_account_info` 的逐步线性扫描（`find_ata` 还需逐个借用并解析数据）。
+pub struct AccountTable<'info> {
+    accounts: &'info [AccountInfo<'info>],
+    by_key: HashMap<Pubkey, u8>,
+    token_accounts: HashMap<(Pubkey, Pubkey), u8>,
+}
+
+impl<'info> AccountTable<'info> {
+    pub fn new(accounts: &'info [AccountInfo<'info>], program_ids: &ProgramIds) -> Result<Self> {
+        // indices 为 u8，全局表最多 256 项
+        require!(accounts.len() <= u8::MAX as usize + 1, ArbitrageError::InvalidAccountCount);
+        let mut by_key = HashMap::with_capacity(accounts.len());
+        let mut token_accounts = HashMap::new();
+        for (i, ai) in accounts.iter().enumerate() {
+            let idx = i as u8;
+            by_key.entry(ai.key()).or_insert(idx);
+            if let Some(owner_mint) = token_account_owner_mint(ai, program_ids) {
+                token_accounts.entry(owner_mint).or_insert(idx);
+            }
+        }
+        Ok(Self { accounts, by_key, token_accounts })
+    }
+
+    /// 原始全局表（供需要遍历全表的场景）
+    #[inline]
+    pub fn accounts(&self) -> &'info [AccountInfo<'info>] {
+        self.accounts
+    }
+
+    #[inline]
+    pub fn index_of(&self, key: &Pubkey) -> Option<u8> {
+        self.by_key.get(key).copied()
+    }
+
+    #[inline]
+    pub fn find(&self, key: &Pubkey) -> Option<&'info AccountInfo<'info>> {
+        self.index_of(key).map(|idx| &self.accounts[idx as usize])
+    }
+
+    /// 按 key 定位，缺失返回 `AccountNotFound`
+    #[inline]
+    pub fn get(&self, key: &Pubkey) -> Result<&'info AccountInfo<'info>> {
+        self.find(key).ok_or(ArbitrageError::AccountNotFound.into())
+    }
+
+    /// 按 (owner, mint) 定位 token 账户（ATA 或 Pool 自定义 vault）
+    #[inline]
+    pub fn token_account(&self, owner: &Pubkey, mint: &Pubkey) -> Option<&'info AccountInfo<'info>> {
+        self.token_accounts
+            .get(&(*owner, *mint))
+            .map(|&idx| &self.accounts[idx as usize])
+    }
+}
+
+/// 若为 Token/Token-2022 token 账户，返回 (owner, mint)
+fn token_account_owner_mint(ai: &AccountInfo, program_ids: &ProgramIds) -> Option<(Pubkey, Pubkey)> {
+    if !program_ids.is_token_program(ai.owner) {
+        return None;
+    }
+    let data = ai.try_borrow_data().ok()?;
+    let is_account = data.len() == TOKEN_ACCOUNT_LEN
+        || (data.len() > TOKEN_ACCOUNT_LEN && data[TOKEN_ACCOUNT_LEN] == TOKEN_2022_ACCOUNT_TYPE_ACCOUNT);
+    if !is_account {
+        return None;
+    }
+    let mint = Pubkey::new_from_array(data[0..32].try_into().ok()?);
+    let owner = Pubkey::new_from_array(data[32..64].try_into().ok()?);
+    Some((owner, mint))
+}
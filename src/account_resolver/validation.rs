@@ -0,0 +1,455 @@
+use anchor_lang::prelude::*;
+use crate::account_derivation::types::{pda_utils, pool_layouts, ProgramIds};
+use crate::dex_router::types::{constants, DexAccounts};
+use crate::errors::ArbitrageError;
+use crate::state::DexType;
+use crate::external_programs::raydium_clmm;
+use crate::quote::clmm::tick_array_start_index;
+use anchor_lang::Discriminator;
+use super::accounts::*;
+
+/// 池归属与成员校验（V2 协议）
+///
+/// 目的：客户端账户表只是“声明”，链上在 CPI 前按 DEX 状态账户核对，错误的表以精确错误码失败，
+/// 而不是在外部程序内部以难以定位的错误回滚。
+/// - 归属：pool/config/observation 等数据账户的 owner 必须是 `ProgramIds` 中对应的 DEX 程序；
+/// - 成员：vault/mint/config/observation/creator 必须与反序列化出的池状态字段一致。
+///
+/// `flex` 特性：跨网络调试时，程序 ID 可取该 DEX 的另一已知网络部署（与 pool_state.owner 一致者）；
+/// 仍限于 `ProgramIds` 中的 mainnet/devnet ID 集合，不信任任意 owner；成员校验不放宽。
+pub fn validate_dex_accounts(accounts: &DexAccounts, program_ids: &ProgramIds) -> Result<()> {
+    match accounts {
+        DexAccounts::RaydiumCpmm(a) => validate_raydium_cpmm(a, program_ids),
+        DexAccounts::RaydiumClmm(a) => validate_raydium_clmm(a, program_ids),
+        DexAccounts::Pumpfun(a) => validate_pumpfun(a, program_ids),
+        DexAccounts::Pumpswap(a) => validate_pumpswap(a, program_ids),
+    }
+}
+
+/// CPMM：pool/config/observation 归属 + PoolState 中 amm_config/vault/mint/observation 对齐
+pub fn validate_raydium_cpmm(accounts: &RaydiumCpmmAccounts, program_ids: &ProgramIds) -> Result<()> {
+    let program = expected_program(program_ids, DexType::RaydiumCpmm, accounts.pool_state.owner);
+    require_owner(accounts.pool_state, &program, "CPMM.pool_state")?;
+    require_owner(accounts.amm_config, &program, "CPMM.amm_config")?;
+    require_owner(accounts.observation_state, &program, "CPMM.observation_state")?;
+
+    let data = accounts.pool_state.try_borrow_data()?;
+    require!(data.len() >= pool_layouts::CPMM_POOL_MIN_LEN, ArbitrageError::InvalidPoolData);
+    require_key(
+        &data, pool_layouts::CPMM_POOL_AMM_CONFIG, accounts.amm_config,
+        "CPMM.amm_config", ArbitrageError::PoolConfigMismatch,
+    )?;
+    require_key(
+        &data, pool_layouts::CPMM_POOL_TOKEN_0_VAULT, accounts.token0_vault,
+        "CPMM.token0_vault", ArbitrageError::PoolVaultMismatch,
+    )?;
+    require_key(
+        &data, pool_layouts::CPMM_POOL_TOKEN_1_VAULT, accounts.token1_vault,
+        "CPMM.token1_vault", ArbitrageError::PoolVaultMismatch,
+    )?;
+    require_key(
+        &data, pool_layouts::CPMM_POOL_OBSERVATION_KEY, accounts.observation_state,
+        "CPMM.observation_state", ArbitrageError::PoolObservationMismatch,
+    )?;
+    let mint0 = pubkey_at(&data, pool_layouts::CPMM_POOL_TOKEN_0_MINT)?;
+    let mint1 = pubkey_at(&data, pool_layouts::CPMM_POOL_TOKEN_1_MINT)?;
+    require_pair(&mint0, &mint1, accounts.input_mint, accounts.output_mint, "CPMM")?;
+    Ok(())
+}
+
+/// CLMM：程序/系统程序 ID + pool/config/observation 归属 + 方向一致的 vault/mint 对齐 + 动态账户归属本池
+pub fn validate_raydium_clmm(accounts: &RaydiumClmmAccounts, program_ids: &ProgramIds) -> Result<()> {
+    let program = expected_program(program_ids, DexType::RaydiumClmm, accounts.pool_state.owner);
+    require_program_id(accounts.clmm_program, &program, "CLMM.clmm_program")?;
+    require_program_id(accounts.token_program, &program_ids.token_program, "CLMM.token_program")?;
+    require_program_id(accounts.token_program_2022, &program_ids.token_2022_program, "CLMM.token_program_2022")?;
+    require_program_id(accounts.memo_program, &program_ids.memo_program, "CLMM.memo_program")?;
+    require_owner(accounts.pool_state, &program, "CLMM.pool_state")?;
+    require_owner(accounts.amm_config, &program, "CLMM.amm_config")?;
+    require_owner(accounts.observation_state, &program, "CLMM.observation_state")?;
+
+    let data = accounts.pool_state.try_borrow_data()?;
+    require!(data.len() >= pool_layouts::CLMM_POOL_MIN_LEN, ArbitrageError::InvalidPoolData);
+    require_key(
+        &data, pool_layouts::CLMM_POOL_AMM_CONFIG, accounts.amm_config,
+        "CLMM.amm_config", ArbitrageError::PoolConfigMismatch,
+    )?;
+    require_key(
+        &data, pool_layouts::CLMM_POOL_OBSERVATION_KEY, accounts.observation_state,
+        "CLMM.observation_state", ArbitrageError::PoolObservationMismatch,
+    )?;
+    let mint0 = pubkey_at(&data, pool_layouts::CLMM_POOL_TOKEN_MINT_0)?;
+    let mint1 = pubkey_at(&data, pool_layouts::CLMM_POOL_TOKEN_MINT_1)?;
+    require_pair(&mint0, &mint1, accounts.input_vault_mint, accounts.output_vault_mint, "CLMM")?;
+    // vault 与 mint 方向一致：input_vault_mint == token_mint_0 ⇒ input_vault == token_vault_0
+    let zero_for_one = accounts.input_vault_mint.key() == mint0;
+    let (input_vault_offset, output_vault_offset) = if zero_for_one {
+        (pool_layouts::CLMM_POOL_TOKEN_VAULT_0, pool_layouts::CLMM_POOL_TOKEN_VAULT_1)
+    } else {
+        (pool_layouts::CLMM_POOL_TOKEN_VAULT_1, pool_layouts::CLMM_POOL_TOKEN_VAULT_0)
+    };
+    require_key(
+        &data, input_vault_offset, accounts.input_vault,
+        "CLMM.input_vault", ArbitrageError::PoolVaultMismatch,
+    )?;
+    require_key(
+        &data, output_vault_offset, accounts.output_vault,
+        "CLMM.output_vault", ArbitrageError::PoolVaultMismatch,
+    )?;
+    let current_start = tick_array_start_index(
+        i32_at(&data, pool_layouts::CLMM_POOL_TICK_CURRENT)?,
+        u16_at(&data, pool_layouts::CLMM_POOL_TICK_SPACING)?,
+    );
+    validate_clmm_dynamic_accounts(accounts, &program, current_start, zero_for_one)
+}
+
+/// CLMM 动态账户（indices 尾部）：可选 bitmap extension 仅可居首，其后 1..=`RAYDIUM_CLMM_MAX_TICK_ARRAYS` 个 tick arrays；
+/// 均须归属 CLMM 程序、discriminator 匹配且 pool_id（两者同在偏移 8）为本步 pool_state。
+///
+/// tick arrays 须按交换方向排列：首个的 start_tick_index 为 `tick_current` 所在数组或位于其方向前方，
+/// 其后逐个严格沿方向推进（zero_for_one 递减，反之递增）；重复或乱序返回 `PoolTickArrayMismatch`。
+fn validate_clmm_dynamic_accounts(
+    accounts: &RaydiumClmmAccounts,
+    program: &Pubkey,
+    current_start: i32,
+    zero_for_one: bool,
+) -> Result<()> {
+    let pool_key = accounts.pool_state.key();
+    let mut tick_arrays = 0u8;
+    let mut previous_start: Option<i32> = None;
+    for (i, ai) in accounts.dynamic_accounts.iter().enumerate() {
+        require_owner(ai, program, "CLMM.tick_array")?;
+        let data = ai.try_borrow_data()?;
+        let role = if data.starts_with(raydium_clmm::accounts::TickArrayState::DISCRIMINATOR) {
+            tick_arrays += 1;
+            "CLMM.tick_array"
+        } else if i == 0 && data.starts_with(raydium_clmm::accounts::TickArrayBitmapExtension::DISCRIMINATOR) {
+            "CLMM.tick_array_extension"
+        } else {
+            msg!("[Validate] CLMM dynamic account #{} is neither tick array nor leading extension: key={}", i, ai.key());
+            return Err(ArbitrageError::PoolTickArrayMismatch.into());
+        };
+        let pool_id = pubkey_at(&data, pool_layouts::CLMM_TICK_ARRAY_POOL_ID)?;
+        if pool_id != pool_key {
+            msg!("[Validate] {} pool mismatch: key={} pool_id={} pool_state={}", role, ai.key(), pool_id, pool_key);
+            return Err(ArbitrageError::PoolTickArrayMismatch.into());
+        }
+        if role == "CLMM.tick_array" {
+            let start = i32_at(&data, pool_layouts::CLMM_TICK_ARRAY_START_TICK_INDEX)?;
+            let in_order = match (previous_start, zero_for_one) {
+                (None, true) => start <= current_start,
+                (None, false) => start >= current_start,
+                (Some(previous), true) => start < previous,
+                (Some(previous), false) => start > previous,
+            };
+            if !in_order {
+                msg!(
+                    "[Validate] CLMM tick array out of order: key={} start={} previous={:?} current_start={} zero_for_one={}",
+                    ai.key(),
+                    start,
+                    previous_start,
+                    current_start,
+                    zero_for_one
+                );
+                return Err(ArbitrageError::PoolTickArrayMismatch.into());
+            }
+            previous_start = Some(start);
+        }
+    }
+    if tick_arrays == 0 || tick_arrays > constants::RAYDIUM_CLMM_MAX_TICK_ARRAYS {
+        msg!(
+            "[Validate] CLMM tick array count {} out of range 1..={}",
+            tick_arrays,
+            constants::RAYDIUM_CLMM_MAX_TICK_ARRAYS
+        );
+        return Err(ArbitrageError::InvalidAccountCount.into());
+    }
+    Ok(())
+}
+
+/// PumpFun：bonding_curve 归属 + PDA(["bonding-curve", mint]) 对齐 + creator 与曲线状态一致
+pub fn validate_pumpfun(accounts: &PumpfunAccounts, program_ids: &ProgramIds) -> Result<()> {
+    let program = expected_program(program_ids, DexType::PumpFunBondingCurve, accounts.bonding_curve.owner);
+    require_owner(accounts.bonding_curve, &program, "PumpFun.bonding_curve")?;
+    let expected_curve = pda_utils::derive_pumpfun_bonding_curve(&accounts.mint.key(), accounts.bumps.pool, &program)?;
+    if expected_curve != accounts.bonding_curve.key() {
+        msg!(
+            "[Validate] PumpFun.mint mismatch: curve={} expected_curve={}",
+            accounts.bonding_curve.key(),
+            expected_curve
+        );
+        return Err(ArbitrageError::PoolMintMismatch.into());
+    }
+
+    let data = accounts.bonding_curve.try_borrow_data()?;
+    require!(data.len() >= pool_layouts::PUMPFUN_BONDING_CURVE_MIN_LEN, ArbitrageError::InvalidPoolData);
+    require_key(
+        &data, pool_layouts::PUMPFUN_BONDING_CURVE_CREATOR, accounts.creator,
+        "PumpFun.creator", ArbitrageError::PoolCreatorMismatch,
+    )?;
+    Ok(())
+}
+
+/// PumpSwap：pool 归属 + Pool 中 base/quote mint 与 coin_creator 对齐（池两侧 vault 在 swaps 中按 Pool 字段定位）
+pub fn validate_pumpswap(accounts: &PumpswapAccounts, program_ids: &ProgramIds) -> Result<()> {
+    let program = expected_program(program_ids, DexType::PumpSwap, accounts.pool_state.owner);
+    require_owner(accounts.pool_state, &program, "PumpSwap.pool_state")?;
+
+    let data = accounts.pool_state.try_borrow_data()?;
+    require!(data.len() >= pool_layouts::PUMPSWAP_POOL_MIN_LEN, ArbitrageError::InvalidPoolData);
+    require_key(
+        &data, pool_layouts::PUMPSWAP_POOL_BASE_MINT, accounts.base_mint,
+        "PumpSwap.base_mint", ArbitrageError::PoolMintMismatch,
+    )?;
+    require_key(
+        &data, pool_layouts::PUMPSWAP_POOL_QUOTE_MINT, accounts.quote_mint,
+        "PumpSwap.quote_mint", ArbitrageError::PoolMintMismatch,
+    )?;
+    require_key(
+        &data, pool_layouts::PUMPSWAP_POOL_COIN_CREATOR, accounts.coin_creator,
+        "PumpSwap.coin_creator", ArbitrageError::PoolCreatorMismatch,
+    )?;
+    Ok(())
+}
+
+/// 步声明与池一致：池的两侧 mint 须恰为本步 (input_mint, output_mint)；
+/// 账户表给出输入侧的场景（CPMM input_mint、CLMM input_vault_mint）还须与声明方向相同。
+///
+/// PumpFun 的两侧为 (mint, WSOL)。拆分腿逐腿调用，保证同一步的各腿是同一交易对。
+pub fn validate_step_mints(
+    accounts: &DexAccounts,
+    input_mint: &Pubkey,
+    output_mint: &Pubkey,
+    wrapped_sol_mint: &Pubkey,
+) -> Result<()> {
+    let (mint0, mint1, resolved_input, dex) = match accounts {
+        DexAccounts::RaydiumCpmm(a) => {
+            let data = a.pool_state.try_borrow_data()?;
+            (
+                pubkey_at(&data, pool_layouts::CPMM_POOL_TOKEN_0_MINT)?,
+                pubkey_at(&data, pool_layouts::CPMM_POOL_TOKEN_1_MINT)?,
+                Some(a.input_mint.key()),
+                "CPMM",
+            )
+        }
+        DexAccounts::RaydiumClmm(a) => {
+            let data = a.pool_state.try_borrow_data()?;
+            (
+                pubkey_at(&data, pool_layouts::CLMM_POOL_TOKEN_MINT_0)?,
+                pubkey_at(&data, pool_layouts::CLMM_POOL_TOKEN_MINT_1)?,
+                Some(a.input_vault_mint.key()),
+                "CLMM",
+            )
+        }
+        DexAccounts::Pumpfun(a) => (a.mint.key(), *wrapped_sol_mint, None, "PumpFun"),
+        DexAccounts::Pumpswap(a) => {
+            let data = a.pool_state.try_borrow_data()?;
+            (
+                pubkey_at(&data, pool_layouts::PUMPSWAP_POOL_BASE_MINT)?,
+                pubkey_at(&data, pool_layouts::PUMPSWAP_POOL_QUOTE_MINT)?,
+                None,
+                "PumpSwap",
+            )
+        }
+    };
+    let pair_ok = (*input_mint == mint0 && *output_mint == mint1) || (*input_mint == mint1 && *output_mint == mint0);
+    let direction_ok = resolved_input.is_none_or(|k| k == *input_mint);
+    if !pair_ok || !direction_ok {
+        msg!(
+            "[Validate] {} step mints mismatch: step=({} -> {}) pool=({}, {}) resolved_input={:?}",
+            dex, input_mint, output_mint, mint0, mint1, resolved_input
+        );
+        return Err(ArbitrageError::StepMintMismatch.into());
+    }
+    Ok(())
+}
+
+/// 期望的 DEX 程序：默认取配置值；`flex` 下若 pool_state.owner 为该 DEX 的已知 mainnet/devnet 部署则取之，
+/// 否则仍取配置值（随后的归属校验失败）。任意 owner 都不会成为期望程序。
+#[inline]
+pub(crate) fn expected_program(program_ids: &ProgramIds, dex_type: DexType, pool_owner: &Pubkey) -> Pubkey {
+    if program_ids.accepts_dex_program(dex_type, pool_owner) { *pool_owner } else { program_ids.dex_program(dex_type) }
+}
+
+/// 按偏移读取 Pubkey（调用方已校验最小长度）
+#[inline]
+pub(crate) fn pubkey_at(data: &[u8], offset: usize) -> Result<Pubkey> {
+    let bytes: [u8; 32] = data
+        .get(offset..offset + 32)
+        .and_then(|s| s.try_into().ok())
+        .ok_or(ArbitrageError::InvalidPoolData)?;
+    Ok(Pubkey::new_from_array(bytes))
+}
+
+/// 按偏移读取小端 i32 / u16（越界返回 `InvalidPoolData`）
+#[inline]
+fn i32_at(data: &[u8], offset: usize) -> Result<i32> {
+    let bytes: [u8; 4] = data
+        .get(offset..offset + 4)
+        .and_then(|s| s.try_into().ok())
+        .ok_or(ArbitrageError::InvalidPoolData)?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+#[inline]
+fn u16_at(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes: [u8; 2] = data
+        .get(offset..offset + 2)
+        .and_then(|s| s.try_into().ok())
+        .ok_or(ArbitrageError::InvalidPoolData)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn require_owner(ai: &AccountInfo, expected: &Pubkey, role: &str) -> Result<()> {
+    if ai.owner != expected {
+        msg!("[Validate] {} owner mismatch: key={} owner={} expected={}", role, ai.key(), ai.owner, expected);
+        return Err(ArbitrageError::InvalidAccountOwner.into());
+    }
+    Ok(())
+}
+
+fn require_program_id(ai: &AccountInfo, expected: &Pubkey, role: &str) -> Result<()> {
+    if ai.key() != *expected || !ai.executable {
+        msg!("[Validate] {} program mismatch: key={} expected={}", role, ai.key(), expected);
+        return Err(ArbitrageError::UnexpectedProgramId.into());
+    }
+    Ok(())
+}
+
+fn require_key(data: &[u8], offset: usize, ai: &AccountInfo, role: &str, err: ArbitrageError) -> Result<()> {
+    let expected = pubkey_at(data, offset)?;
+    if ai.key() != expected {
+        msg!("[Validate] {} mismatch: got={} pool_state={}", role, ai.key(), expected);
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+/// 输入/输出 mint 必须恰为池的两侧 mint（任意方向）
+fn require_pair(mint0: &Pubkey, mint1: &Pubkey, input: &AccountInfo, output: &AccountInfo, dex: &str) -> Result<()> {
+    let (i, o) = (input.key(), output.key());
+    let ok = (i == *mint0 && o == *mint1) || (i == *mint1 && o == *mint0);
+    if !ok {
+        msg!("[Validate] {} mint pair mismatch: in={} out={} pool=({}, {})", dex, i, o, mint0, mint1);
+        return Err(ArbitrageError::PoolMintMismatch.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Accounts = Vec<&'static AccountInfo<'static>>;
+
+    fn account(owner: Pubkey, data: Vec<u8>) -> &'static AccountInfo<'static> {
+        Box::leak(Box::new(AccountInfo::new(
+            Box::leak(Box::new(Pubkey::new_unique())),
+            false,
+            true,
+            Box::leak(Box::new(0u64)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )))
+    }
+
+    fn tick_array(program: Pubkey, pool: &Pubkey, start_tick_index: i32) -> &'static AccountInfo<'static> {
+        let mut data = vec![0u8; pool_layouts::CLMM_TICK_ARRAY_MIN_LEN];
+        data[..8].copy_from_slice(raydium_clmm::accounts::TickArrayState::DISCRIMINATOR);
+        data[pool_layouts::CLMM_TICK_ARRAY_POOL_ID..][..32].copy_from_slice(pool.as_ref());
+        data[pool_layouts::CLMM_TICK_ARRAY_START_TICK_INDEX..][..4].copy_from_slice(&start_tick_index.to_le_bytes());
+        account(program, data)
+    }
+
+    fn extension(program: Pubkey, pool: &Pubkey) -> &'static AccountInfo<'static> {
+        let mut data = vec![0u8; pool_layouts::CLMM_BITMAP_EXTENSION_MIN_LEN];
+        data[..8].copy_from_slice(raydium_clmm::accounts::TickArrayBitmapExtension::DISCRIMINATOR);
+        data[pool_layouts::CLMM_TICK_ARRAY_POOL_ID..][..32].copy_from_slice(pool.as_ref());
+        account(program, data)
+    }
+
+    /// 仅 pool_state 与尾部账户参与动态账户校验，其余基础账户以占位账户填充
+    fn clmm_accounts(program: Pubkey, dynamic: impl Fn(&Pubkey) -> Accounts) -> RaydiumClmmAccounts<'static> {
+        let pool_state = account(program, Vec::new());
+        let filler = account(Pubkey::default(), Vec::new());
+        RaydiumClmmAccounts {
+            clmm_program: filler,
+            amm_config: filler,
+            pool_state,
+            input_vault: filler,
+            output_vault: filler,
+            observation_state: filler,
+            token_program: filler,
+            token_program_2022: filler,
+            memo_program: filler,
+            input_vault_mint: filler,
+            output_vault_mint: filler,
+            dynamic_accounts: dynamic(pool_state.key),
+        }
+    }
+
+    #[test]
+    fn clmm_tick_arrays_follow_swap_direction() {
+        let program = Pubkey::new_unique();
+        // tick_spacing 10：数组跨度 600，当前 tick 所在数组 start = 0
+        // (zero_for_one, starts, 是否带 extension, 期望通过)
+        let cases: [(bool, &[i32], bool, bool); 10] = [
+            (true, &[0, -600, -1200], false, true),
+            (false, &[0, 600, 1200, 1800], true, true),
+            // 当前数组未初始化时首个可位于方向前方
+            (true, &[-1200, -3000], false, true),
+            (false, &[600], true, true),
+            // 重复
+            (true, &[0, 0], false, false),
+            (false, &[600, 600], false, false),
+            // 乱序 / 反方向
+            (true, &[0, -1200, -600], false, false),
+            (false, &[0, 1200, 600], true, false),
+            (true, &[600, 0], false, false),
+            (false, &[-600, 0], false, false),
+        ];
+        for (zero_for_one, starts, with_extension, ok) in cases {
+            let accounts = clmm_accounts(program, |pool| {
+                with_extension
+                    .then(|| extension(program, pool))
+                    .into_iter()
+                    .chain(starts.iter().map(|&start| tick_array(program, pool, start)))
+                    .collect()
+            });
+            let result = validate_clmm_dynamic_accounts(&accounts, &program, 0, zero_for_one);
+            if ok {
+                result.unwrap();
+            } else {
+                assert_eq!(
+                    result.unwrap_err(),
+                    ArbitrageError::PoolTickArrayMismatch.into(),
+                    "zero_for_one={zero_for_one} starts={starts:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn clmm_dynamic_accounts_reject_foreign_or_misplaced_accounts() {
+        let program = Pubkey::new_unique();
+        let other_pool = Pubkey::new_unique();
+        let reject = |name: &str, dynamic: &dyn Fn(&Pubkey) -> Accounts, expected: ArbitrageError| {
+            let accounts = clmm_accounts(program, dynamic);
+            assert_eq!(validate_clmm_dynamic_accounts(&accounts, &program, 0, false).unwrap_err(), expected.into(), "{name}");
+        };
+        reject("foreign pool", &|_| vec![tick_array(program, &other_pool, 0)], ArbitrageError::PoolTickArrayMismatch);
+        reject(
+            "extension not first",
+            &|pool| vec![tick_array(program, pool, 0), extension(program, pool)],
+            ArbitrageError::PoolTickArrayMismatch,
+        );
+        reject("extension only", &|pool| vec![extension(program, pool)], ArbitrageError::InvalidAccountCount);
+        reject(
+            "wrong owner",
+            &|pool| vec![tick_array(Pubkey::new_unique(), pool, 0)],
+            ArbitrageError::InvalidAccountOwner,
+        );
+    }
+}
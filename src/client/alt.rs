@@ -0,0 +1,175 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::address_lookup_table::instruction::{create_lookup_table, extend_lookup_table};
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::message::{v0, AddressLookupTableAccount, CompileError};
+
+use crate::errors::ArbitrageError;
+use super::builder::BuiltArbitrage;
+
+/// 单笔交易序列化上限（`PACKET_DATA_SIZE`）
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
+/// 单个 ALT 最多容纳的地址数
+pub const MAX_LOOKUP_TABLE_ADDRESSES: usize = 256;
+/// 每条 extend 指令携带的地址数（保证 extend 交易本身不超过包大小）
+pub const MAX_EXTEND_ADDRESSES: usize = 30;
+
+/// v0 交易的账户规划结果
+///
+/// 静态 key 与 lookup 条目的划分由 `v0::Message::try_compile` 完成，保证：
+/// signer（payer 居首）→ 可写非 signer → 只读的顺序合法，signer 与被顶层调用的程序始终留在静态 key 中。
+#[derive(Clone, Debug)]
+pub struct AltPlan {
+    pub message: v0::Message,
+    /// 留在静态 key 中、但可以放入 ALT 的账户（非 signer、非顶层调用程序）
+    pub uncovered: Vec<Pubkey>,
+    /// 估算的已签名交易字节数（与 `MAX_TRANSACTION_SIZE` 比较）
+    pub transaction_size: usize,
+}
+
+impl AltPlan {
+    pub fn static_keys(&self) -> &[Pubkey] {
+        &self.message.account_keys
+    }
+
+    /// 经 ALT 加载的账户数（可写 + 只读）
+    pub fn lookup_entry_count(&self) -> usize {
+        self.message
+            .address_table_lookups
+            .iter()
+            .map(|l| l.writable_indexes.len() + l.readonly_indexes.len())
+            .sum()
+    }
+
+    pub fn fits(&self) -> bool {
+        self.transaction_size <= MAX_TRANSACTION_SIZE
+    }
+}
+
+/// 按现有 ALT 规划交易账户：能命中表的账户转为 lookup 条目，其余留作静态 key
+///
+/// `recent_blockhash` 仅参与体积估算（固定 32 字节），规划阶段可传 `Hash::default()`。
+pub fn plan_transaction(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Result<AltPlan> {
+    let message = v0::Message::try_compile(payer, instructions, lookup_tables, recent_blockhash)
+        .map_err(compile_error)?;
+
+    let header = &message.header;
+    let signer_count = header.num_required_signatures as usize;
+    let uncovered = message
+        .account_keys
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i >= signer_count && !message.is_key_called_as_program(*i))
+        .map(|(_, key)| *key)
+        .collect();
+    let transaction_size = transaction_size(&message);
+    Ok(AltPlan { message, uncovered, transaction_size })
+}
+
+impl BuiltArbitrage {
+    /// 以 `user` 为 payer 规划整笔交易（`prefix` 为前置指令，如 ComputeBudget）
+    pub fn plan_transaction(
+        &self,
+        prefix: &[Instruction],
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<AltPlan> {
+        let mut instructions = prefix.to_vec();
+        instructions.push(self.instruction());
+        plan_transaction(&self.user, &instructions, lookup_tables, Hash::default())
+    }
+}
+
+/// v0 已签名交易的序列化字节数（签名数组 + 版本前缀 + 消息体）
+pub fn transaction_size(message: &v0::Message) -> usize {
+    let signatures = message.header.num_required_signatures as usize;
+    let mut size = compact_len(signatures) + signatures * 64;
+    size += 1 + 3; // 版本前缀 + header
+    size += compact_len(message.account_keys.len()) + message.account_keys.len() * 32;
+    size += 32; // recent_blockhash
+    size += compact_len(message.instructions.len());
+    for ix in &message.instructions {
+        size += 1 + compact_len(ix.accounts.len()) + ix.accounts.len();
+        size += compact_len(ix.data.len()) + ix.data.len();
+    }
+    size += compact_len(message.address_table_lookups.len());
+    for lookup in &message.address_table_lookups {
+        size += 32;
+        size += compact_len(lookup.writable_indexes.len()) + lookup.writable_indexes.len();
+        size += compact_len(lookup.readonly_indexes.len()) + lookup.readonly_indexes.len();
+    }
+    size
+}
+
+/// short_vec（compact-u16）长度前缀字节数
+fn compact_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+fn compile_error(err: CompileError) -> Error {
+    match err {
+        CompileError::UnknownInstructionKey(key) => {
+            msg!("[ALT] unknown instruction key {}", key);
+            ArbitrageError::AccountNotFound.into()
+        }
+        _ => ArbitrageError::InvalidAccountCount.into(),
+    }
+}
+
+/// ALT 管理：为规划中未覆盖的账户生成 create/extend 指令
+///
+/// 新建表需等待一个 slot 后才能在交易中使用；extend 后同样需等待生效。
+pub struct AltManager {
+    pub authority: Pubkey,
+    pub payer: Pubkey,
+}
+
+impl AltManager {
+    pub fn new(authority: Pubkey, payer: Pubkey) -> Self {
+        Self { authority, payer }
+    }
+
+    /// 新建表：返回 (create 指令, 表地址)
+    pub fn create(&self, recent_slot: u64) -> (Instruction, Pubkey) {
+        create_lookup_table(self.authority, self.payer, recent_slot)
+    }
+
+    /// 追加地址：按 `MAX_EXTEND_ADDRESSES` 分批，每批一条 extend 指令
+    pub fn extend(&self, lookup_table: Pubkey, addresses: &[Pubkey]) -> Vec<Instruction> {
+        addresses
+            .chunks(MAX_EXTEND_ADDRESSES)
+            .map(|chunk| extend_lookup_table(lookup_table, self.authority, Some(self.payer), chunk.to_vec()))
+            .collect()
+    }
+
+    /// 覆盖规划中未命中的账户：`table` 为 None 时先新建表；
+    /// 已有表时按剩余容量截断（超出部分需另建表）。返回 (指令, 表地址, 已覆盖地址数)
+    pub fn cover_uncovered(
+        &self,
+        plan: &AltPlan,
+        table: Option<&AddressLookupTableAccount>,
+        recent_slot: u64,
+    ) -> (Vec<Instruction>, Pubkey, usize) {
+        let mut instructions = Vec::new();
+        let (table_key, existing) = match table {
+            Some(t) => (t.key, t.addresses.len()),
+            None => {
+                let (ix, key) = self.create(recent_slot);
+                instructions.push(ix);
+                (key, 0)
+            }
+        };
+        let capacity = MAX_LOOKUP_TABLE_ADDRESSES.saturating_sub(existing);
+        let count = plan.uncovered.len().min(capacity);
+        instructions.extend(self.extend(table_key, &plan.uncovered[..count]));
+        (instructions, table_key, count)
+    }
+}
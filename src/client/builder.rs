@@ -0,0 +1,770 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::InstructionData;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+use crate::account_derivation::types::{get_fixed_addresses, pda_seeds, FixedAddresses, ProgramIds};
+use crate::dex_router::types::constants::RAYDIUM_CLMM_MAX_TICK_ARRAYS;
+use crate::errors::ArbitrageError;
+use crate::instructions::EXECUTE_ARBITRAGE_V3_DISCRIMINATOR;
+use crate::state::{
+    ArbitrageCycle, ArbitrageParamsV3, LogLevel, PathAccountMappingV3, PathStepV3, PdaBumps, ProtocolVersion, SplitLeg,
+};
+use super::route::{PoolAccounts, Route, RouteStep};
+
+/// 全局账户表（去重 + 可写性取并集）
+///
+/// 与链上 `AccountTable` 相同：indices 为 u8，最多 256 项；表内账户一律不作为 signer
+/// （唯一签名者 `user` 在固定 4 账户中）。
+#[derive(Clone, Debug, Default)]
+pub struct AccountTableBuilder {
+    metas: Vec<AccountMeta>,
+}
+
+impl AccountTableBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入账户并返回其表内索引；已存在时复用索引，`writable` 只升不降
+    pub fn push(&mut self, key: Pubkey, writable: bool) -> Result<u8> {
+        if let Some(i) = self.metas.iter().position(|m| m.pubkey == key) {
+            self.metas[i].is_writable |= writable;
+            return Ok(i as u8);
+        }
+        require!(self.metas.len() <= u8::MAX as usize, ArbitrageError::InvalidAccountCount);
+        self.metas.push(AccountMeta { pubkey: key, is_signer: false, is_writable: writable });
+        Ok((self.metas.len() - 1) as u8)
+    }
+
+    pub fn len(&self) -> usize {
+        self.metas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metas.is_empty()
+    }
+
+    pub fn metas(&self) -> &[AccountMeta] {
+        &self.metas
+    }
+
+    pub fn into_metas(self) -> Vec<AccountMeta> {
+        self.metas
+    }
+}
+
+/// 构建结果：参数 + 全局表（即 remaining_accounts）
+#[derive(Clone, Debug)]
+pub struct BuiltArbitrage {
+    pub user: Pubkey,
+    pub params: ArbitrageParamsV3,
+    pub remaining_accounts: Vec<AccountMeta>,
+    token_program: Pubkey,
+    associated_token_program: Pubkey,
+    system_program: Pubkey,
+}
+
+impl BuiltArbitrage {
+    /// 固定 4 账户（user / token_program / associated_token_program / system_program）+ 全局表
+    pub fn account_metas(&self) -> Vec<AccountMeta> {
+        let mut metas = crate::accounts::ExecuteArbitrage {
+            user: self.user,
+            token_program: self.token_program,
+            associated_token_program: self.associated_token_program,
+            system_program: self.system_program,
+        }
+        .to_account_metas(None);
+        metas.extend(self.remaining_accounts.iter().cloned());
+        metas
+    }
+
+    /// 版本化 payload：V3 标签 + Borsh `ArbitrageParamsV3`
+    pub fn payload(&self) -> Vec<u8> {
+        let mut payload = vec![ProtocolVersion::V3 as u8];
+        self.params.serialize(&mut payload).expect("Vec<u8> writer is infallible");
+        payload
+    }
+
+    /// `execute_arbitrage_v3` 指令（discriminator + Borsh `Vec<u8>` payload；该方法仅在 `idl-build` 下生成
+    /// `instruction::ExecuteArbitrageV3`，故按同一编码手工拼接）
+    pub fn instruction(&self) -> Instruction {
+        let payload = self.payload();
+        let mut data = Vec::with_capacity(EXECUTE_ARBITRAGE_V3_DISCRIMINATOR.len() + 4 + payload.len());
+        data.extend_from_slice(&EXECUTE_ARBITRAGE_V3_DISCRIMINATOR);
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(&payload);
+        Instruction { program_id: crate::ID, accounts: self.account_metas(), data }
+    }
+
+    /// `quote_path` 只读报价指令（账户与 payload 同 `execute_arbitrage_v3`，用于 simulate 取 return data）
+    pub fn quote_instruction(&self) -> Instruction {
+        Instruction {
+            program_id: crate::ID,
+            accounts: self.account_metas(),
+            data: crate::instruction::QuotePath { payload: self.payload() }.data(),
+        }
+    }
+}
+
+/// `execute_arbitrage_v3` 指令构建器
+///
+/// 程序 ID 与固定地址取 `ProgramIds::default()` / `get_fixed_addresses()`（同链上，支持 ENV 覆盖）；
+/// 未登记的 mint 默认属于 SPL Token 程序，Token-2022 mint 须经 `with_token_program` 声明。
+pub struct ArbitrageIxBuilder {
+    user: Pubkey,
+    program_ids: ProgramIds,
+    fixed: FixedAddresses,
+    token_programs: Vec<(Pubkey, Pubkey)>,
+    max_slippage_bps: u16,
+    close_intermediate_atas: bool,
+    min_cu_reserve: Option<u64>,
+    log_level: Option<LogLevel>,
+}
+
+impl ArbitrageIxBuilder {
+    pub fn new(user: Pubkey) -> Result<Self> {
+        Ok(Self {
+            user,
+            program_ids: ProgramIds::default(),
+            fixed: get_fixed_addresses()?,
+            token_programs: Vec::new(),
+            max_slippage_bps: 0,
+            close_intermediate_atas: false,
+            min_cu_reserve: None,
+            log_level: None,
+        })
+    }
+
+    pub fn with_program_ids(mut self, program_ids: ProgramIds) -> Self {
+        self.program_ids = program_ids;
+        self
+    }
+
+    /// 声明 mint 所属 token program（决定 ATA 推导与是否追加 Token-2022 程序）
+    pub fn with_token_program(mut self, mint: Pubkey, token_program: Pubkey) -> Self {
+        match self.token_programs.iter_mut().find(|(m, _)| *m == mint) {
+            Some(entry) => entry.1 = token_program,
+            None => self.token_programs.push((mint, token_program)),
+        }
+        self
+    }
+
+    pub fn max_slippage_bps(mut self, bps: u16) -> Self {
+        self.max_slippage_bps = bps;
+        self
+    }
+
+    pub fn close_intermediate_atas(mut self, close: bool) -> Self {
+        self.close_intermediate_atas = close;
+        self
+    }
+
+    pub fn min_cu_reserve(mut self, reserve: Option<u64>) -> Self {
+        self.min_cu_reserve = reserve;
+        self
+    }
+
+    pub fn log_level(mut self, level: Option<LogLevel>) -> Self {
+        self.log_level = level;
+        self
+    }
+
+    /// 由主环与额外环生成参数与全局表（表按首次出现顺序排列，各环共享）
+    pub fn build(&self, primary: &Route, extra_cycles: &[Route]) -> Result<BuiltArbitrage> {
+        let mut table = AccountTableBuilder::new();
+        let (path_steps, account_mappings) = self.build_route(&mut table, primary)?;
+        let mut cycles = Vec::with_capacity(extra_cycles.len());
+        for route in extra_cycles {
+            let (path_steps, account_mappings) = self.build_route(&mut table, route)?;
+            cycles.push(ArbitrageCycle {
+                input_amount: route.input_amount,
+                min_profit_lamports: route.min_profit_lamports,
+                path_steps,
+                account_mappings,
+                skip_if_unprofitable: route.skip_if_unprofitable,
+            });
+        }
+
+        let params = ArbitrageParamsV3 {
+            input_amount: primary.input_amount,
+            min_profit_lamports: primary.min_profit_lamports,
+            max_slippage_bps: self.max_slippage_bps,
+            path_steps,
+            account_mappings,
+            close_intermediate_atas: self.close_intermediate_atas,
+            min_cu_reserve: self.min_cu_reserve,
+            log_level: self.log_level,
+            extra_cycles: cycles,
+            quote_before_execute: primary.skip_if_unprofitable,
+        };
+        Ok(BuiltArbitrage {
+            user: self.user,
+            params,
+            remaining_accounts: table.into_metas(),
+            token_program: self.program_ids.token_program,
+            associated_token_program: self.program_ids.associated_token_program,
+            system_program: self.program_ids.system_program,
+        })
+    }
+
+    fn build_route(
+        &self,
+        table: &mut AccountTableBuilder,
+        route: &Route,
+    ) -> Result<(Vec<PathStepV3>, Vec<PathAccountMappingV3>)> {
+        let mut steps = Vec::with_capacity(route.steps.len());
+        let mut mappings = Vec::with_capacity(route.steps.len());
+        for step in &route.steps {
+            let (path_step, mapping) = self.build_step(table, step)?;
+            steps.push(path_step);
+            mappings.push(mapping);
+        }
+        Ok((steps, mappings))
+    }
+
+    fn build_step(
+        &self,
+        table: &mut AccountTableBuilder,
+        step: &RouteStep,
+    ) -> Result<(PathStepV3, PathAccountMappingV3)> {
+        // 用户两侧 ATA（余额差 / CPI 账户），链上按 mint 的 token program 推导后在全局表定位
+        table.push(self.user_ata(&step.input_mint), true)?;
+        table.push(self.user_ata(&step.output_mint), true)?;
+        for mint in [&step.input_mint, &step.output_mint] {
+            if self.token_program_for(mint) == self.program_ids.token_2022_program {
+                table.push(self.program_ids.token_2022_program, false)?;
+            }
+        }
+
+        let mapping = self.collect_pool(table, &step.pool, &step.input_mint, &step.output_mint)?;
+        let mut split_legs = Vec::with_capacity(step.split_legs.len());
+        for leg in &step.split_legs {
+            split_legs.push(SplitLeg {
+                pool_id: Some(leg.pool.pool_id()),
+                dex_type: leg.pool.dex_type(),
+                share_bps: leg.share_bps,
+                minimum_amount_out: leg.minimum_amount_out,
+                sqrt_price_limit_x64: leg.sqrt_price_limit_x64,
+                mapping: self.collect_pool(table, &leg.pool, &step.input_mint, &step.output_mint)?,
+            });
+        }
+
+        let path_step = PathStepV3 {
+            pool_id: Some(step.pool.pool_id()),
+            dex_type: step.pool.dex_type(),
+            input_mint: step.input_mint,
+            output_mint: step.output_mint,
+            minimum_amount_out: step.minimum_amount_out,
+            amount_mode: step.amount_mode,
+            swap_mode: step.swap_mode,
+            sqrt_price_limit_x64: step.sqrt_price_limit_x64,
+            split_legs,
+        };
+        Ok((path_step, mapping))
+    }
+
+    /// 单个池：最小集写入 indices，追加项仅入表（顺序与链上 resolver 角色一致）
+    fn collect_pool(
+        &self,
+        table: &mut AccountTableBuilder,
+        pool: &PoolAccounts,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+    ) -> Result<PathAccountMappingV3> {
+        let program_ids = &self.program_ids;
+        let mut bumps = PdaBumps::default();
+        let indices = match pool {
+            PoolAccounts::RaydiumCpmm { pool_state, amm_config, token0_vault, token1_vault, observation_state } => {
+                let indices = vec![
+                    table.push(*amm_config, false)?,
+                    table.push(*pool_state, true)?,
+                    table.push(*token0_vault, true)?,
+                    table.push(*token1_vault, true)?,
+                    table.push(*input_mint, false)?,
+                    table.push(*output_mint, false)?,
+                    table.push(*observation_state, true)?,
+                ];
+                table.push(program_ids.raydium_cpmm, false)?;
+                table.push(self.fixed.raydium_cpmm_authority, false)?;
+                indices
+            }
+            PoolAccounts::RaydiumClmm {
+                pool_state,
+                amm_config,
+                input_vault,
+                output_vault,
+                observation_state,
+                tick_arrays,
+                tick_array_extension,
+            } => {
+                require!(
+                    !tick_arrays.is_empty() && tick_arrays.len() <= RAYDIUM_CLMM_MAX_TICK_ARRAYS as usize,
+                    ArbitrageError::InvalidAccountCount
+                );
+                let mut indices = vec![
+                    table.push(program_ids.raydium_clmm, false)?,
+                    table.push(*amm_config, false)?,
+                    table.push(*pool_state, true)?,
+                    table.push(*input_vault, true)?,
+                    table.push(*output_vault, true)?,
+                    table.push(*observation_state, true)?,
+                    table.push(program_ids.token_program, false)?,
+                    table.push(program_ids.token_2022_program, false)?,
+                    table.push(program_ids.memo_program, false)?,
+                    table.push(*input_mint, false)?,
+                    table.push(*output_mint, false)?,
+                ];
+                // indices 尾部：可选 extension 居首，其后 tick arrays（链上校验归属本池并按序注入）
+                if let Some(extension) = tick_array_extension {
+                    indices.push(table.push(*extension, true)?);
+                }
+                for tick_array in tick_arrays {
+                    indices.push(table.push(*tick_array, true)?);
+                }
+                indices
+            }
+            PoolAccounts::PumpFun { bonding_curve, mint, creator, fee_recipient } => {
+                let pumpfun = &program_ids.pumpfun;
+                let fee_recipient = fee_recipient.unwrap_or(self.fixed.pumpfun_fee_recipient);
+                let indices = vec![
+                    table.push(*bonding_curve, true)?,
+                    table.push(*mint, false)?,
+                    table.push(*creator, false)?,
+                    table.push(fee_recipient, true)?,
+                ];
+
+                let (_, pool_bump) =
+                    Pubkey::find_program_address(&[pda_seeds::PUMPFUN_BONDING_CURVE, mint.as_ref()], pumpfun);
+                let (global, global_bump) = Pubkey::find_program_address(&[pda_seeds::PUMPFUN_GLOBAL], pumpfun);
+                let (event_authority, event_bump) =
+                    Pubkey::find_program_address(&[pda_seeds::PUMPFUN_EVENT_AUTHORITY], pumpfun);
+                let (creator_vault, creator_vault_bump) =
+                    Pubkey::find_program_address(&[pda_seeds::PUMPFUN_CREATOR_VAULT, creator.as_ref()], pumpfun);
+                bumps.pool = Some(pool_bump);
+                bumps.global = Some(global_bump);
+                bumps.event_authority = Some(event_bump);
+                bumps.creator_vault = Some(creator_vault_bump);
+
+                table.push(*pumpfun, false)?;
+                table.push(global, false)?;
+                table.push(event_authority, false)?;
+                table.push(self.ata(bonding_curve, mint), true)?;
+                table.push(creator_vault, true)?;
+
+                // 买入（WSOL → token）需 volume accumulators
+                if *input_mint == self.fixed.wrapped_sol_mint {
+                    let (gva, gva_bump) =
+                        Pubkey::find_program_address(&[pda_seeds::PUMPFUN_GLOBAL_VOLUME_ACCUMULATOR], pumpfun);
+                    let (uva, uva_bump) = Pubkey::find_program_address(
+                        &[pda_seeds::PUMPFUN_USER_VOLUME_ACCUMULATOR, self.user.as_ref()],
+                        pumpfun,
+                    );
+                    bumps.global_volume_accumulator = Some(gva_bump);
+                    bumps.user_volume_accumulator = Some(uva_bump);
+                    table.push(gva, true)?;
+                    table.push(uva, true)?;
+                }
+                indices
+            }
+            PoolAccounts::PumpSwap {
+                pool_state,
+                base_mint,
+                quote_mint,
+                coin_creator,
+                pool_base_token_account,
+                pool_quote_token_account,
+                fee_recipient,
+                fee_recipient_ata,
+            } => {
+                let amm = &program_ids.pumpswap;
+                let (fee_recipient, fee_recipient_ata) = match (fee_recipient, fee_recipient_ata) {
+                    (None, None) => (self.fixed.pumpswap_fee_recipient, self.fixed.pumpswap_fee_recipient_ata),
+                    (recipient, ata) => {
+                        let recipient = recipient.unwrap_or(self.fixed.pumpswap_fee_recipient);
+                        (recipient, ata.unwrap_or_else(|| self.ata(&recipient, quote_mint)))
+                    }
+                };
+                let indices = vec![
+                    table.push(*pool_state, false)?,
+                    table.push(*base_mint, false)?,
+                    table.push(*quote_mint, false)?,
+                    table.push(*coin_creator, false)?,
+                    table.push(fee_recipient, false)?,
+                    table.push(fee_recipient_ata, true)?,
+                ];
+
+                let (global_config, global_bump) =
+                    Pubkey::find_program_address(&[pda_seeds::PUMPSWAP_GLOBAL_CONFIG], amm);
+                let (event_authority, event_bump) =
+                    Pubkey::find_program_address(&[pda_seeds::PUMPSWAP_EVENT_AUTHORITY], amm);
+                let (creator_vault_authority, creator_vault_bump) = Pubkey::find_program_address(
+                    &[pda_seeds::PUMPSWAP_CREATOR_VAULT, coin_creator.as_ref()],
+                    amm,
+                );
+                bumps.global = Some(global_bump);
+                bumps.event_authority = Some(event_bump);
+                bumps.creator_vault = Some(creator_vault_bump);
+
+                table.push(*amm, false)?;
+                table.push(global_config, false)?;
+                table.push(event_authority, false)?;
+                table.push(*pool_base_token_account, true)?;
+                table.push(*pool_quote_token_account, true)?;
+                table.push(creator_vault_authority, false)?;
+                table.push(self.ata(&creator_vault_authority, quote_mint), true)?;
+                indices
+            }
+        };
+
+        Ok(PathAccountMappingV3 {
+            dex_type: pool.dex_type(),
+            contract_type: pool.contract_type(),
+            indices,
+            bumps,
+        })
+    }
+
+    fn token_program_for(&self, mint: &Pubkey) -> Pubkey {
+        self.token_programs
+            .iter()
+            .find(|(m, _)| m == mint)
+            .map(|(_, p)| *p)
+            .unwrap_or(self.program_ids.token_program)
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program_for(mint))
+    }
+
+    fn user_ata(&self, mint: &Pubkey) -> Pubkey {
+        self.ata(&self.user, mint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account_resolver::AccountResolver;
+    use crate::client::route::RouteLeg;
+    use crate::state::DexType;
+
+    /// 按全局表构造链上 remaining_accounts（可写性取自 meta；测试内泄漏以满足 `'info`）
+    fn remaining_accounts(metas: &[AccountMeta]) -> &'static [AccountInfo<'static>] {
+        let owner: &'static Pubkey = Box::leak(Box::new(Pubkey::default()));
+        let infos = metas
+            .iter()
+            .map(|meta| {
+                AccountInfo::new(
+                    Box::leak(Box::new(meta.pubkey)),
+                    meta.is_signer,
+                    meta.is_writable,
+                    Box::leak(Box::new(0u64)),
+                    Box::leak(Vec::new().into_boxed_slice()),
+                    owner,
+                    false,
+                    0,
+                )
+            })
+            .collect::<Vec<_>>();
+        Box::leak(infos.into_boxed_slice())
+    }
+
+    fn builder(user: Pubkey) -> ArbitrageIxBuilder {
+        ArbitrageIxBuilder::new(user).unwrap()
+    }
+
+    fn single_step(pool: PoolAccounts, input_mint: Pubkey, output_mint: Pubkey) -> Route {
+        Route {
+            input_amount: 1_000_000,
+            min_profit_lamports: 0,
+            steps: vec![RouteStep::new(pool, input_mint, output_mint, 1)],
+            skip_if_unprofitable: false,
+        }
+    }
+
+    fn assert_key(info: &AccountInfo, key: Pubkey, writable: bool) {
+        assert_eq!(*info.key, key);
+        assert_eq!(info.is_writable, writable, "writable mismatch for {key}");
+        assert!(!info.is_signer);
+    }
+
+    fn table_contains(built: &BuiltArbitrage, key: Pubkey, writable: bool) -> bool {
+        built.remaining_accounts.iter().any(|m| m.pubkey == key && m.is_writable == writable)
+    }
+
+    #[test]
+    fn table_dedupes_and_promotes_writable() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut table = AccountTableBuilder::new();
+        assert_eq!(table.push(a, false).unwrap(), 0);
+        assert_eq!(table.push(b, true).unwrap(), 1);
+        assert_eq!(table.push(a, true).unwrap(), 0);
+        assert_eq!(table.push(b, false).unwrap(), 1);
+        assert_eq!(table.len(), 2);
+        assert!(table.metas().iter().all(|m| m.is_writable && !m.is_signer));
+
+        let mut full = AccountTableBuilder::new();
+        for _ in 0..=u8::MAX as usize {
+            full.push(Pubkey::new_unique(), false).unwrap();
+        }
+        assert_eq!(full.push(a, false).unwrap_err(), ArbitrageError::InvalidAccountCount.into());
+    }
+
+    #[test]
+    fn instruction_carries_versioned_v3_payload() {
+        let ix = builder(Pubkey::new_unique());
+        let pool = PoolAccounts::PumpFun {
+            bonding_curve: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            creator: Pubkey::new_unique(),
+            fee_recipient: None,
+        };
+        let mut route = single_step(pool, ix.fixed.wrapped_sol_mint, Pubkey::new_unique());
+        route.skip_if_unprofitable = true;
+        let built = ix.build(&route, &[]).unwrap();
+        assert!(built.params.quote_before_execute);
+
+        let instruction = built.instruction();
+        assert_eq!(instruction.accounts.len(), 4 + built.remaining_accounts.len());
+        let body = instruction.data.strip_prefix(&EXECUTE_ARBITRAGE_V3_DISCRIMINATOR).unwrap();
+        let payload = Vec::<u8>::try_from_slice(body).unwrap();
+        assert_eq!(payload, built.payload());
+        assert_eq!(ProtocolVersion::from_tag(payload[0]), Some(ProtocolVersion::V3));
+        let decoded = ArbitrageParamsV3::try_from_slice(&payload[1..]).unwrap();
+        assert_eq!(decoded.path_steps[0].pool_id, built.params.path_steps[0].pool_id);
+        assert_eq!(decoded.account_mappings[0].bumps, built.params.account_mappings[0].bumps);
+    }
+
+    #[test]
+    fn raydium_cpmm_indices_resolve_on_chain() {
+        let ix = builder(Pubkey::new_unique());
+        let wsol = ix.fixed.wrapped_sol_mint;
+        let mint = Pubkey::new_unique();
+        let (pool_state, amm_config, token0_vault, token1_vault, observation_state) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = PoolAccounts::RaydiumCpmm { pool_state, amm_config, token0_vault, token1_vault, observation_state };
+        // 往返两步共用同一池：池账户只入表一次，第二步 indices 交换 mint 方向
+        let route = Route {
+            input_amount: 1_000_000,
+            min_profit_lamports: 0,
+            steps: vec![RouteStep::new(pool.clone(), wsol, mint, 1), RouteStep::new(pool, mint, wsol, 1)],
+            skip_if_unprofitable: false,
+        };
+        let built = ix.build(&route, &[]).unwrap();
+        let resolver = AccountResolver::new(remaining_accounts(&built.remaining_accounts), LogLevel::Error);
+
+        let [first, second] = &built.params.account_mappings[..] else { panic!("expected two mappings") };
+        assert_eq!(first.indices[..4], second.indices[..4]);
+        assert_eq!((first.indices[4], first.indices[5]), (second.indices[5], second.indices[4]));
+        assert_eq!(first.indices[6], second.indices[6]);
+
+        for (mapping, (input, output)) in [(first, (wsol, mint)), (second, (mint, wsol))] {
+            assert_eq!(mapping.dex_type, DexType::RaydiumCpmm);
+            assert_eq!(mapping.bumps, PdaBumps::default());
+            resolver.validate_indices_for_dex(mapping.view()).unwrap();
+            let accounts = resolver.resolve_raydium_cpmm_by_indices(mapping.view()).unwrap();
+            assert_key(accounts.amm_config, amm_config, false);
+            assert_key(accounts.pool_state, pool_state, true);
+            assert_key(accounts.token0_vault, token0_vault, true);
+            assert_key(accounts.token1_vault, token1_vault, true);
+            assert_key(accounts.input_mint, input, false);
+            assert_key(accounts.output_mint, output, false);
+            assert_key(accounts.observation_state, observation_state, true);
+        }
+        let keys: Vec<_> = built.remaining_accounts.iter().map(|m| m.pubkey).collect();
+        assert!(keys.iter().enumerate().all(|(i, k)| !keys[i + 1..].contains(k)), "table must be deduplicated");
+        assert!(table_contains(&built, ix.program_ids.raydium_cpmm, false));
+        assert!(table_contains(&built, ix.fixed.raydium_cpmm_authority, false));
+        assert!(table_contains(&built, ix.user_ata(&wsol), true));
+        assert!(table_contains(&built, ix.user_ata(&mint), true));
+    }
+
+    #[test]
+    fn raydium_clmm_indices_resolve_on_chain() {
+        let ix = builder(Pubkey::new_unique());
+        let (input_mint, output_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let tick_arrays = vec![Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let extension = Pubkey::new_unique();
+        let (pool_state, amm_config, input_vault, output_vault, observation_state) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = PoolAccounts::RaydiumClmm {
+            pool_state,
+            amm_config,
+            input_vault,
+            output_vault,
+            observation_state,
+            tick_arrays: tick_arrays.clone(),
+            tick_array_extension: Some(extension),
+        };
+        let built = ix.build(&single_step(pool, input_mint, output_mint), &[]).unwrap();
+        let resolver = AccountResolver::new(remaining_accounts(&built.remaining_accounts), LogLevel::Error);
+        let mapping = &built.params.account_mappings[0];
+        assert_eq!(mapping.indices.len(), 11 + 1 + tick_arrays.len());
+
+        resolver.validate_indices_for_dex(mapping.view()).unwrap();
+        let accounts = resolver.resolve_raydium_clmm_by_indices(mapping.view()).unwrap();
+        assert_key(accounts.clmm_program, ix.program_ids.raydium_clmm, false);
+        assert_key(accounts.amm_config, amm_config, false);
+        assert_key(accounts.pool_state, pool_state, true);
+        assert_key(accounts.input_vault, input_vault, true);
+        assert_key(accounts.output_vault, output_vault, true);
+        assert_key(accounts.observation_state, observation_state, true);
+        assert_key(accounts.token_program, ix.program_ids.token_program, false);
+        assert_key(accounts.token_program_2022, ix.program_ids.token_2022_program, false);
+        assert_key(accounts.memo_program, ix.program_ids.memo_program, false);
+        assert_key(accounts.input_vault_mint, input_mint, false);
+        assert_key(accounts.output_vault_mint, output_mint, false);
+        // indices 尾部：extension 居首，其后按序为 tick arrays（均可写）
+        let dynamic = [vec![extension], tick_arrays].concat();
+        assert_eq!(accounts.dynamic_accounts.len(), dynamic.len());
+        for (ai, key) in accounts.dynamic_accounts.iter().zip(dynamic) {
+            assert_key(ai, key, true);
+        }
+    }
+
+    #[test]
+    fn pumpfun_buy_indices_and_bumps_resolve_on_chain() {
+        let user = Pubkey::new_unique();
+        let ix = builder(user);
+        let wsol = ix.fixed.wrapped_sol_mint;
+        let (bonding_curve, mint, creator) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = PoolAccounts::PumpFun { bonding_curve, mint, creator, fee_recipient: None };
+        let built = ix.build(&single_step(pool, wsol, mint), &[]).unwrap();
+        let resolver = AccountResolver::new(remaining_accounts(&built.remaining_accounts), LogLevel::Error);
+        let mapping = &built.params.account_mappings[0];
+
+        resolver.validate_indices_for_dex(mapping.view()).unwrap();
+        let accounts = resolver.resolve_pumpfun_by_indices(mapping.view()).unwrap();
+        assert_key(accounts.bonding_curve, bonding_curve, true);
+        assert_key(accounts.mint, mint, false);
+        assert_key(accounts.creator, creator, false);
+        assert_key(accounts.fee_recipient_opt.unwrap(), ix.fixed.pumpfun_fee_recipient, true);
+
+        let pumpfun = &ix.program_ids.pumpfun;
+        let pda = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, pumpfun);
+        let (_, pool_bump) = pda(&[pda_seeds::PUMPFUN_BONDING_CURVE, mint.as_ref()]);
+        let (global, global_bump) = pda(&[pda_seeds::PUMPFUN_GLOBAL]);
+        let (event_authority, event_bump) = pda(&[pda_seeds::PUMPFUN_EVENT_AUTHORITY]);
+        let (creator_vault, creator_vault_bump) = pda(&[pda_seeds::PUMPFUN_CREATOR_VAULT, creator.as_ref()]);
+        let (gva, gva_bump) = pda(&[pda_seeds::PUMPFUN_GLOBAL_VOLUME_ACCUMULATOR]);
+        let (uva, uva_bump) = pda(&[pda_seeds::PUMPFUN_USER_VOLUME_ACCUMULATOR, user.as_ref()]);
+        assert_eq!(
+            accounts.bumps,
+            PdaBumps {
+                global: Some(global_bump),
+                event_authority: Some(event_bump),
+                pool: Some(pool_bump),
+                creator_vault: Some(creator_vault_bump),
+                global_volume_accumulator: Some(gva_bump),
+                user_volume_accumulator: Some(uva_bump),
+            }
+        );
+        for (key, writable) in [
+            (*pumpfun, false),
+            (global, false),
+            (event_authority, false),
+            (ix.ata(&bonding_curve, &mint), true),
+            (creator_vault, true),
+            (gva, true),
+            (uva, true),
+        ] {
+            assert!(table_contains(&built, key, writable), "missing append {key}");
+        }
+
+        // 卖出无需 volume accumulators
+        let sell = PoolAccounts::PumpFun { bonding_curve, mint, creator, fee_recipient: None };
+        let built = ix.build(&single_step(sell, mint, wsol), &[]).unwrap();
+        let bumps = built.params.account_mappings[0].bumps;
+        assert_eq!((bumps.global_volume_accumulator, bumps.user_volume_accumulator), (None, None));
+        assert!(!built.remaining_accounts.iter().any(|m| m.pubkey == gva || m.pubkey == uva));
+    }
+
+    #[test]
+    fn pumpswap_indices_and_bumps_resolve_on_chain() {
+        let ix = builder(Pubkey::new_unique());
+        let wsol = ix.fixed.wrapped_sol_mint;
+        let (pool_state, base_mint, coin_creator) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (pool_base_token_account, pool_quote_token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let fee_recipient = Pubkey::new_unique();
+        let pool = PoolAccounts::PumpSwap {
+            pool_state,
+            base_mint,
+            quote_mint: wsol,
+            coin_creator,
+            pool_base_token_account,
+            pool_quote_token_account,
+            fee_recipient: Some(fee_recipient),
+            fee_recipient_ata: None,
+        };
+        let built = ix.build(&single_step(pool, wsol, base_mint), &[]).unwrap();
+        let resolver = AccountResolver::new(remaining_accounts(&built.remaining_accounts), LogLevel::Error);
+        let mapping = &built.params.account_mappings[0];
+
+        resolver.validate_indices_for_dex(mapping.view()).unwrap();
+        let accounts = resolver.resolve_pumpswap_by_indices(mapping.view()).unwrap();
+        assert_key(accounts.pool_state, pool_state, false);
+        assert_key(accounts.base_mint, base_mint, false);
+        assert_key(accounts.quote_mint, wsol, false);
+        assert_key(accounts.coin_creator, coin_creator, false);
+        assert_key(accounts.fee_recipient_opt.unwrap(), fee_recipient, false);
+        // 仅给出 fee_recipient 时 ATA 由其推导
+        assert_key(accounts.fee_recipient_ata_opt.unwrap(), ix.ata(&fee_recipient, &wsol), true);
+
+        let amm = &ix.program_ids.pumpswap;
+        let pda = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, amm);
+        let (global_config, global_bump) = pda(&[pda_seeds::PUMPSWAP_GLOBAL_CONFIG]);
+        let (event_authority, event_bump) = pda(&[pda_seeds::PUMPSWAP_EVENT_AUTHORITY]);
+        let (creator_vault, creator_vault_bump) = pda(&[pda_seeds::PUMPSWAP_CREATOR_VAULT, coin_creator.as_ref()]);
+        assert_eq!(
+            accounts.bumps,
+            PdaBumps {
+                global: Some(global_bump),
+                event_authority: Some(event_bump),
+                creator_vault: Some(creator_vault_bump),
+                ..PdaBumps::default()
+            }
+        );
+        for (key, writable) in [
+            (*amm, false),
+            (global_config, false),
+            (event_authority, false),
+            (pool_base_token_account, true),
+            (pool_quote_token_account, true),
+            (creator_vault, false),
+            (ix.ata(&creator_vault, &wsol), true),
+        ] {
+            assert!(table_contains(&built, key, writable), "missing append {key}");
+        }
+    }
+
+    #[test]
+    fn split_legs_share_the_table_with_their_own_indices() {
+        let ix = builder(Pubkey::new_unique());
+        let wsol = ix.fixed.wrapped_sol_mint;
+        let mint = Pubkey::new_unique();
+        let cpmm = |pool_state| PoolAccounts::RaydiumCpmm {
+            pool_state,
+            amm_config: Pubkey::new_unique(),
+            token0_vault: Pubkey::new_unique(),
+            token1_vault: Pubkey::new_unique(),
+            observation_state: Pubkey::new_unique(),
+        };
+        let (primary_pool, leg_pool) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut step = RouteStep::new(cpmm(primary_pool), wsol, mint, 1);
+        step.split_legs.push(RouteLeg { pool: cpmm(leg_pool), share_bps: 4000, minimum_amount_out: 1, sqrt_price_limit_x64: 0 });
+        let route = Route { input_amount: 1_000_000, min_profit_lamports: 0, steps: vec![step], skip_if_unprofitable: false };
+        let built = ix.build(&route, &[]).unwrap();
+        let resolver = AccountResolver::new(remaining_accounts(&built.remaining_accounts), LogLevel::Error);
+
+        let leg = &built.params.path_steps[0].split_legs[0];
+        assert_eq!(leg.pool_id, Some(leg_pool));
+        resolver.validate_indices_for_dex(leg.mapping.view()).unwrap();
+        let accounts = resolver.resolve_raydium_cpmm_by_indices(leg.mapping.view()).unwrap();
+        assert_key(accounts.pool_state, leg_pool, true);
+        // 两腿共用 mint 索引，池账户各自独立
+        let primary = &built.params.account_mappings[0];
+        assert_eq!(primary.indices[4..6], leg.mapping.indices[4..6]);
+        assert_ne!(primary.indices[1], leg.mapping.indices[1]);
+    }
+}
@@ -0,0 +1,704 @@
+use anchor_lang::prelude::*;
+use core::str::FromStr;
+
+use crate::errors::ArbitrageError;
+use crate::state::DexType;
+
+/// `[Resolver] role=… idx=… key=… W=… S=…`（verbose）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoleLog {
+    pub role: String,
+    pub index: u8,
+    pub key: Pubkey,
+    pub writable: bool,
+    pub signer: bool,
+}
+
+/// `[ATA] program=… len=… mint=… owner=…`（verbose）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AtaLog {
+    pub program: Pubkey,
+    pub len: usize,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+}
+
+/// `[FINGERPRINT] <label> len=… head8=[…]`（verbose）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FingerprintLog {
+    pub label: String,
+    pub len: usize,
+    pub head8: Vec<u8>,
+}
+
+/// `Step N leg …: share_bps=… amount_in=… amount_out=…`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LegLog {
+    pub dex_type: Option<DexType>,
+    pub share_bps: u16,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+/// `Step N completed: …`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StepAmounts {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub running_amount: u64,
+    pub cu_used: u64,
+    pub cu_left: u64,
+}
+
+/// `Step N aborted: remaining_cu=… < min_cu_reserve=… leg=…`（CU 守卫，与日志级别无关）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepAbort {
+    pub remaining_cu: u64,
+    pub min_cu_reserve: u64,
+    /// 未能发起的腿（0 为主腿）
+    pub leg_index: usize,
+}
+
+/// 单步日志（从 `Executing step N` 到下一步开始之间的全部输出，含嵌套 CPI）
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepTrace {
+    pub cycle_index: usize,
+    pub step_index: usize,
+    pub dex_type: Option<DexType>,
+    pub input_mint: Option<Pubkey>,
+    pub output_mint: Option<Pubkey>,
+    /// `Step N inputs` 中的 amount_in / min_out
+    pub amount_in: Option<u64>,
+    pub minimum_amount_out: Option<u64>,
+    pub roles: Vec<RoleLog>,
+    pub atas: Vec<AtaLog>,
+    pub fingerprints: Vec<FingerprintLog>,
+    pub legs: Vec<LegLog>,
+    /// 成功完成时的金额与 CU；失败步骤为 None
+    pub completed: Option<StepAmounts>,
+    /// CU 守卫中止本步时的剩余 CU 与腿序号
+    pub aborted: Option<StepAbort>,
+    /// 本步内外部程序（CPI）输出的日志：(程序, 行)
+    pub cpi_logs: Vec<(Pubkey, String)>,
+}
+
+/// `AnchorError …. Error Code: X. Error Number: N. Error Message: M.`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedError {
+    pub name: String,
+    pub number: u32,
+    pub message: String,
+}
+
+impl DecodedError {
+    /// 错误号与名称同时匹配（`DexRouterError` 与 `ArbitrageError` 的错误号区间重叠）
+    pub fn is(&self, err: ArbitrageError) -> bool {
+        self.name == err.name() && self.number == u32::from(err)
+    }
+}
+
+/// 交易失败归因
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FailureTrace {
+    pub cycle_index: usize,
+    /// 失败时尚未完成的步骤；步间/环末校验（如 `InsufficientProfit`）失败时为 None
+    pub step_index: Option<usize>,
+    /// 最先报错的（最内层）程序：CPI 失败时为外部 DEX 程序
+    pub program_id: Pubkey,
+    pub depth: usize,
+    /// 运行时给出的原因，如 `custom program error: 0x1771`
+    pub reason: String,
+    pub custom_code: Option<u32>,
+    /// 本程序输出的 `ArbitrageError`（CPI 错误直接透传时为 None）
+    pub error: Option<DecodedError>,
+    /// 外部程序输出的 AnchorError（非 CPI 失败时为 None）
+    pub cpi_error: Option<DecodedError>,
+}
+
+impl FailureTrace {
+    pub fn is_cpi_failure(&self) -> bool {
+        self.depth > 1
+    }
+}
+
+/// 环级结果：`Arbitrage cycle N completed successfully. Profit: P` / `Cycle N skipped: …`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CycleLog {
+    Completed { cycle_index: usize, profit: u64 },
+    /// 报价不足（`quoted=… required=… min_out_met=…`）或报价出错（`quote_error=… quoted=0 required=…`）
+    Skipped { cycle_index: usize, quoted: u64, required: u64, quote_error: Option<String> },
+}
+
+/// 一笔交易中本程序的日志解析结果
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionTrace {
+    pub steps: Vec<StepTrace>,
+    pub cycles: Vec<CycleLog>,
+    pub failure: Option<FailureTrace>,
+    /// 本程序顶层调用的 (consumed, limit) CU
+    pub compute_units: Option<(u64, u64)>,
+    /// 运行时截断了日志（`Log truncated`），结果可能不完整
+    pub truncated: bool,
+}
+
+impl TransactionTrace {
+    pub fn failed_step(&self) -> Option<&StepTrace> {
+        let failure = self.failure.as_ref()?;
+        let step_index = failure.step_index?;
+        self.steps
+            .iter()
+            .rev()
+            .find(|s| s.cycle_index == failure.cycle_index && s.step_index == step_index)
+    }
+}
+
+/// 解析交易日志（`meta.log_messages`）：仅处理 `program_id` 顶层调用内的输出，
+/// 嵌套 CPI 的日志与失败归入当时正在执行的步骤。
+///
+/// 步骤与环级日志仅在 `log_level >= Summary` 时输出：更低级别下 `steps`/`cycles` 为空，
+/// `FailureTrace` 仍能给出失败程序与错误，但 `step_index` 恒为 None（无法归因到步骤）。
+pub fn decode_logs(program_id: &Pubkey, logs: &[String]) -> TransactionTrace {
+    let mut decoder = Decoder { program_id: *program_id, ..Decoder::default() };
+    for line in logs {
+        decoder.line(line);
+    }
+    decoder.trace
+}
+
+#[derive(Default)]
+struct Decoder {
+    program_id: Pubkey,
+    trace: TransactionTrace,
+    /// 调用栈（仅在本程序顶层调用内维护）
+    stack: Vec<Pubkey>,
+    cycle_index: usize,
+    /// 当前未完成的步骤（在 `trace.steps` 中的位置）
+    open_step: Option<usize>,
+    /// 最近一条 AnchorError 及其输出程序
+    last_error: Option<(Pubkey, DecodedError)>,
+}
+
+impl Decoder {
+    fn line(&mut self, line: &str) {
+        if line == "Log truncated" {
+            self.trace.truncated = true;
+            return;
+        }
+        let Some(rest) = line.strip_prefix("Program ") else { return };
+        if let Some(msg) = rest.strip_prefix("log: ") {
+            self.log(msg);
+            return;
+        }
+        if rest.starts_with("data: ") || rest.starts_with("return: ") {
+            return;
+        }
+        let Some((id, tail)) = rest.split_once(' ') else { return };
+        let Ok(id) = Pubkey::from_str(id) else { return };
+        if tail.starts_with("invoke [") {
+            if !self.stack.is_empty() || id == self.program_id {
+                self.stack.push(id);
+            }
+            return;
+        }
+        if self.stack.is_empty() {
+            return;
+        }
+        if tail == "success" {
+            self.stack.pop();
+        } else if let Some(reason) = tail.strip_prefix("failed: ") {
+            self.failed(id, reason);
+            self.stack.pop();
+        } else if let Some(units) = tail.strip_prefix("consumed ") {
+            if self.stack.len() == 1 {
+                self.trace.compute_units = parse_units(units);
+            }
+        }
+    }
+
+    fn failed(&mut self, id: Pubkey, reason: &str) {
+        // 外层程序随后以同一错误失败（CPI 错误透传），以最内层为准
+        if self.trace.failure.is_some() {
+            return;
+        }
+        let (error, cpi_error) = match self.last_error.take() {
+            Some((source, err)) if source == id && id == self.program_id => (Some(err), None),
+            Some((source, err)) if source == id => (None, Some(err)),
+            _ => (None, None),
+        };
+        self.trace.failure = Some(FailureTrace {
+            cycle_index: self.cycle_index,
+            step_index: self.open_step.map(|i| self.trace.steps[i].step_index),
+            program_id: id,
+            depth: self.stack.len(),
+            reason: reason.to_string(),
+            custom_code: reason
+                .strip_prefix("custom program error: 0x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok()),
+            error,
+            cpi_error,
+        });
+    }
+
+    fn log(&mut self, msg: &str) {
+        let Some(&current) = self.stack.last() else { return };
+        if msg.starts_with("AnchorError") {
+            if let Some(err) = parse_anchor_error(msg) {
+                self.last_error = Some((current, err));
+            }
+        }
+        if current != self.program_id {
+            if let Some(i) = self.open_step {
+                self.trace.steps[i].cpi_logs.push((current, msg.to_string()));
+            }
+            return;
+        }
+        self.own_log(msg);
+    }
+
+    fn own_log(&mut self, msg: &str) {
+        if let Some(rest) = msg.strip_prefix("Executing step ") {
+            self.start_step(rest);
+        } else if let Some(rest) = msg.strip_prefix("[Resolver] role=") {
+            let role = rest.split_whitespace().next().unwrap_or_default().to_string();
+            if let (Some(step), Some(index), Some(key), Some(writable), Some(signer)) = (
+                self.step_mut(),
+                kv(msg, "idx").and_then(|v| v.parse().ok()),
+                kv(msg, "key").and_then(|v| Pubkey::from_str(v).ok()),
+                kv(msg, "W").and_then(|v| v.parse().ok()),
+                kv(msg, "S").and_then(|v| v.parse().ok()),
+            ) {
+                step.roles.push(RoleLog { role, index, key, writable, signer });
+            }
+        } else if msg.starts_with("[ATA] ") {
+            if let (Some(step), Some(program), Some(len), Some(mint), Some(owner)) = (
+                self.step_mut(),
+                kv(msg, "program").and_then(|v| Pubkey::from_str(v).ok()),
+                kv(msg, "len").and_then(|v| v.parse().ok()),
+                kv(msg, "mint").and_then(|v| Pubkey::from_str(v).ok()),
+                kv(msg, "owner").and_then(|v| Pubkey::from_str(v).ok()),
+            ) {
+                step.atas.push(AtaLog { program, len, mint, owner });
+            }
+        } else if let Some(rest) = msg.strip_prefix("[FINGERPRINT] ") {
+            let label = rest.split_whitespace().next().unwrap_or_default().to_string();
+            let head8 = kv(msg, "head8")
+                .map(|v| v.trim_matches(['[', ']']).split(',').filter_map(|b| b.parse().ok()).collect())
+                .unwrap_or_default();
+            if let (Some(step), Some(len)) = (self.step_mut(), kv(msg, "len").and_then(|v| v.parse().ok())) {
+                step.fingerprints.push(FingerprintLog { label, len, head8 });
+            }
+        } else if let Some(rest) = msg.strip_prefix("Step ") {
+            self.step_log(rest, msg);
+        } else if let Some(rest) = msg.strip_prefix("Arbitrage cycle ") {
+            // `N completed successfully. Profit: P`
+            let cycle_index = leading_number(rest).unwrap_or(self.cycle_index);
+            let profit = rest.rsplit("Profit: ").next().and_then(|v| v.trim().parse().ok()).unwrap_or(0);
+            self.trace.cycles.push(CycleLog::Completed { cycle_index, profit });
+            self.cycle_index = cycle_index + 1;
+            self.open_step = None;
+        } else if let Some(rest) = msg.strip_prefix("Cycle ") {
+            let Some(cycle_index) = leading_number(rest) else { return };
+            if rest.contains(" skipped:") {
+                let quoted = kv(msg, "quoted").and_then(|v| v.parse().ok()).unwrap_or(0);
+                let required = kv(msg, "required").and_then(|v| v.parse().ok()).unwrap_or(0);
+                let quote_error = kv(msg, "quote_error").map(str::to_string);
+                self.trace.cycles.push(CycleLog::Skipped { cycle_index, quoted, required, quote_error });
+                self.cycle_index = cycle_index + 1;
+            } else {
+                self.cycle_index = cycle_index;
+            }
+        }
+    }
+
+    /// `N: <input_mint> -> <output_mint> on <DexType>`
+    fn start_step(&mut self, rest: &str) {
+        let Some(step_index) = leading_number(rest) else { return };
+        let mut parts = rest.split_whitespace();
+        let _ = parts.next();
+        let input_mint = parts.next().and_then(|v| Pubkey::from_str(v).ok());
+        let _ = parts.next();
+        let output_mint = parts.next().and_then(|v| Pubkey::from_str(v).ok());
+        let _ = parts.next();
+        let dex_type = parts.next().and_then(parse_dex_type);
+        self.trace.steps.push(StepTrace {
+            cycle_index: self.cycle_index,
+            step_index,
+            dex_type,
+            input_mint,
+            output_mint,
+            ..StepTrace::default()
+        });
+        self.open_step = Some(self.trace.steps.len() - 1);
+    }
+
+    /// `N inputs: …` / `N leg …` / `N completed: …` / `N aborted: …`
+    fn step_log(&mut self, rest: &str, msg: &str) {
+        let Some(step) = self.step_mut() else { return };
+        if leading_number(rest) != Some(step.step_index) {
+            return;
+        }
+        let number = |key: &str| kv(msg, key).and_then(|v| v.parse::<u64>().ok());
+        if rest.contains(" inputs:") {
+            step.amount_in = number("amount_in");
+            step.minimum_amount_out = number("min_out");
+        } else if rest.contains(" leg ") {
+            let dex_type = rest.split_whitespace().nth(2).map(|v| v.trim_end_matches(':')).and_then(parse_dex_type);
+            step.legs.push(LegLog {
+                dex_type,
+                share_bps: kv(msg, "share_bps").and_then(|v| v.parse().ok()).unwrap_or(0),
+                amount_in: number("amount_in").unwrap_or(0),
+                amount_out: number("amount_out").unwrap_or(0),
+            });
+        } else if rest.contains(" completed:") {
+            step.completed = Some(StepAmounts {
+                amount_in: number("amount_in").unwrap_or(0),
+                amount_out: number("amount_out").unwrap_or(0),
+                running_amount: number("new_running_amount").unwrap_or(0),
+                cu_used: number("cu_used").unwrap_or(0),
+                cu_left: number("cu_left").unwrap_or(0),
+            });
+            self.open_step = None;
+        } else if rest.contains(" aborted:") {
+            // 随后本程序以 ComputeBudgetExhausted 失败，保持本步未完成以便归因
+            step.aborted = Some(StepAbort {
+                remaining_cu: number("remaining_cu").unwrap_or(0),
+                min_cu_reserve: number("min_cu_reserve").unwrap_or(0),
+                leg_index: kv(msg, "leg").and_then(|v| v.parse().ok()).unwrap_or(0),
+            });
+        }
+    }
+
+    fn step_mut(&mut self) -> Option<&mut StepTrace> {
+        self.open_step.map(|i| &mut self.trace.steps[i])
+    }
+}
+
+/// 取空白分隔的 `key=value` 值（去掉两侧括号与尾随逗号）
+fn kv<'a>(msg: &'a str, key: &str) -> Option<&'a str> {
+    msg.split_whitespace().find_map(|token| {
+        let token = token.trim_start_matches('(').trim_end_matches([',', ')']);
+        token.strip_prefix(key)?.strip_prefix('=')
+    })
+}
+
+fn leading_number<T: FromStr>(s: &str) -> Option<T> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s[..end].parse().ok()
+}
+
+fn parse_dex_type(s: &str) -> Option<DexType> {
+    match s {
+        "RaydiumCpmm" => Some(DexType::RaydiumCpmm),
+        "RaydiumClmm" => Some(DexType::RaydiumClmm),
+        "PumpFunBondingCurve" => Some(DexType::PumpFunBondingCurve),
+        "PumpSwap" => Some(DexType::PumpSwap),
+        _ => None,
+    }
+}
+
+/// `consumed A of B compute units`
+fn parse_units(s: &str) -> Option<(u64, u64)> {
+    let mut parts = s.split_whitespace();
+    let consumed = parts.next()?.parse().ok()?;
+    let limit = parts.nth(1)?.parse().ok()?;
+    Some((consumed, limit))
+}
+
+/// `AnchorError occurred|thrown in …|caused by account: …. Error Code: X. Error Number: N. Error Message: M.`
+fn parse_anchor_error(msg: &str) -> Option<DecodedError> {
+    let (_, rest) = msg.split_once("Error Code: ")?;
+    let (name, rest) = rest.split_once(". Error Number: ")?;
+    let (number, rest) = rest.split_once(". Error Message: ")?;
+    Some(DecodedError {
+        name: name.to_string(),
+        number: number.parse().ok()?,
+        message: rest.trim_end_matches('.').to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Programs {
+        arb: Pubkey,
+        cpmm: Pubkey,
+        pumpswap: Pubkey,
+        token: Pubkey,
+    }
+
+    impl Programs {
+        fn new() -> Self {
+            Self {
+                arb: crate::ID,
+                cpmm: Pubkey::new_unique(),
+                pumpswap: Pubkey::new_unique(),
+                token: Pubkey::new_unique(),
+            }
+        }
+    }
+
+    fn anchor_error_line(location: &str, err: ArbitrageError) -> String {
+        format!(
+            "Program log: AnchorError thrown in {}. Error Code: {}. Error Number: {}. Error Message: {}.",
+            location,
+            err.name(),
+            u32::from(err),
+            err
+        )
+    }
+
+    #[test]
+    fn decodes_successful_and_skipped_cycles() {
+        let p = Programs::new();
+        let (wsol, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let logs: Vec<String> = vec![
+            "Program ComputeBudget111111111111111111111111111111 invoke [1]".into(),
+            "Program ComputeBudget111111111111111111111111111111 success".into(),
+            format!("Program {} invoke [1]", p.arb),
+            "Program log: Instruction: ExecuteArbitrage".into(),
+            "Program log: [CPI_VERSION] 2".into(),
+            format!("Program log: Executing step 0: {} -> {} on RaydiumCpmm", wsol, mint),
+            "Program log: Step 0 inputs: amount_in=1000000, min_out=1, mode=Chained/ExactIn, legs=1".into(),
+            format!("Program {} invoke [2]", p.cpmm),
+            "Program log: Instruction: SwapBaseInput".into(),
+            format!("Program {} invoke [3]", p.token),
+            "Program log: Instruction: TransferChecked".into(),
+            format!("Program {} consumed 6238 of 171000 compute units", p.token),
+            format!("Program {} success", p.token),
+            format!("Program {} consumed 28000 of 190000 compute units", p.cpmm),
+            format!("Program {} success", p.cpmm),
+            "Program log: Step 0 completed: amount_in=1000000 amount_out=1993011 -> new_running_amount=1993011 cu_used=31000 cu_left=160000".into(),
+            format!("Program log: Executing step 1: {} -> {} on PumpSwap", mint, wsol),
+            "Program log: Step 1 inputs: amount_in=1993011, min_out=1000001, mode=Chained/ExactIn, legs=2".into(),
+            format!("Program {} invoke [2]", p.pumpswap),
+            "Program log: Instruction: Sell".into(),
+            format!("Program {} success", p.pumpswap),
+            format!("Program {} invoke [2]", p.cpmm),
+            format!("Program {} success", p.cpmm),
+            "Program log: Step 1 leg RaydiumCpmm: share_bps=4000 amount_in=797204 amount_out=401000".into(),
+            "Program log: Step 1 completed: amount_in=1993011 amount_out=1002000 -> new_running_amount=1002000 cu_used=52000 cu_left=108000".into(),
+            "Program log: Arbitrage cycle 0 completed successfully. Profit: 2000".into(),
+            "Program data: AAAA".into(),
+            "Program log: Cycle 1 skipped: quote_error=InvalidPoolData quoted=0 required=500100".into(),
+            "Program data: AAAA".into(),
+            "Program log: Cycle 2 skipped: quoted=499000 required=500100 min_out_met=true".into(),
+            "Program data: AAAA".into(),
+            format!("Program {} consumed 92000 of 200000 compute units", p.arb),
+            format!("Program return: {} AAAA", p.arb),
+            format!("Program {} success", p.arb),
+        ];
+
+        let trace = decode_logs(&p.arb, &logs);
+        assert!(trace.failure.is_none());
+        assert!(!trace.truncated);
+        assert_eq!(trace.compute_units, Some((92000, 200000)));
+        assert_eq!(
+            trace.cycles,
+            vec![
+                CycleLog::Completed { cycle_index: 0, profit: 2000 },
+                CycleLog::Skipped {
+                    cycle_index: 1,
+                    quoted: 0,
+                    required: 500100,
+                    quote_error: Some("InvalidPoolData".into()),
+                },
+                CycleLog::Skipped { cycle_index: 2, quoted: 499000, required: 500100, quote_error: None },
+            ]
+        );
+
+        assert_eq!(trace.steps.len(), 2);
+        let first = &trace.steps[0];
+        assert_eq!((first.cycle_index, first.step_index), (0, 0));
+        assert_eq!(first.dex_type, Some(DexType::RaydiumCpmm));
+        assert_eq!((first.input_mint, first.output_mint), (Some(wsol), Some(mint)));
+        assert_eq!((first.amount_in, first.minimum_amount_out), (Some(1000000), Some(1)));
+        assert_eq!(
+            first.completed,
+            Some(StepAmounts {
+                amount_in: 1000000,
+                amount_out: 1993011,
+                running_amount: 1993011,
+                cu_used: 31000,
+                cu_left: 160000,
+            })
+        );
+        // 嵌套两层的 CPI 日志按实际输出程序归入本步
+        assert_eq!(
+            first.cpi_logs,
+            vec![
+                (p.cpmm, "Instruction: SwapBaseInput".to_string()),
+                (p.token, "Instruction: TransferChecked".to_string()),
+            ]
+        );
+
+        let second = &trace.steps[1];
+        assert_eq!(second.dex_type, Some(DexType::PumpSwap));
+        assert_eq!(
+            second.legs,
+            vec![LegLog { dex_type: Some(DexType::RaydiumCpmm), share_bps: 4000, amount_in: 797204, amount_out: 401000 }]
+        );
+        assert_eq!(second.completed.map(|c| c.running_amount), Some(1002000));
+        assert_eq!(second.cpi_logs, vec![(p.pumpswap, "Instruction: Sell".to_string())]);
+    }
+
+    #[test]
+    fn attributes_nested_cpi_failure_to_the_open_step() {
+        let p = Programs::new();
+        let (wsol, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let logs: Vec<String> = vec![
+            format!("Program {} invoke [1]", p.arb),
+            format!("Program log: Executing step 0: {} -> {} on RaydiumCpmm", wsol, mint),
+            format!("Program {} invoke [2]", p.cpmm),
+            format!("Program {} success", p.cpmm),
+            "Program log: Step 0 completed: amount_in=1000 amount_out=2000 -> new_running_amount=2000 cu_used=30000 cu_left=150000".into(),
+            format!("Program log: Executing step 1: {} -> {} on PumpSwap", mint, wsol),
+            format!("Program {} invoke [2]", p.pumpswap),
+            "Program log: Instruction: Sell".into(),
+            format!("Program {} invoke [3]", p.token),
+            "Program log: Error: insufficient funds".into(),
+            format!("Program {} consumed 4000 of 120000 compute units", p.token),
+            format!("Program {} failed: custom program error: 0x1", p.token),
+            format!("Program {} consumed 20000 of 136000 compute units", p.pumpswap),
+            format!("Program {} failed: custom program error: 0x1", p.pumpswap),
+            format!("Program {} consumed 64000 of 200000 compute units", p.arb),
+            format!("Program {} failed: custom program error: 0x1", p.arb),
+        ];
+
+        let trace = decode_logs(&p.arb, &logs);
+        let failure = trace.failure.as_ref().expect("failure");
+        assert_eq!(failure.program_id, p.token);
+        assert_eq!(failure.depth, 3);
+        assert!(failure.is_cpi_failure());
+        assert_eq!((failure.cycle_index, failure.step_index), (0, Some(1)));
+        assert_eq!(failure.reason, "custom program error: 0x1");
+        assert_eq!(failure.custom_code, Some(1));
+        assert_eq!((&failure.error, &failure.cpi_error), (&None, &None));
+
+        let step = trace.failed_step().expect("failed step");
+        assert_eq!(step.dex_type, Some(DexType::PumpSwap));
+        assert!(step.completed.is_none());
+        assert_eq!(step.cpi_logs.last(), Some(&(p.token, "Error: insufficient funds".to_string())));
+        assert!(trace.steps[0].completed.is_some());
+    }
+
+    #[test]
+    fn separates_dex_anchor_errors_from_arbitrage_errors() {
+        let p = Programs::new();
+        let (wsol, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let logs: Vec<String> = vec![
+            format!("Program {} invoke [1]", p.arb),
+            format!("Program log: Executing step 0: {} -> {} on RaydiumCpmm", wsol, mint),
+            format!("Program {} invoke [2]", p.cpmm),
+            "Program log: AnchorError occurred. Error Code: ExceededSlippage. Error Number: 6005. Error Message: Exceeds desired slippage limit.".into(),
+            format!("Program {} failed: custom program error: 0x1775", p.cpmm),
+            format!("Program {} failed: custom program error: 0x1775", p.arb),
+        ];
+
+        let trace = decode_logs(&p.arb, &logs);
+        let failure = trace.failure.as_ref().expect("failure");
+        assert_eq!((failure.program_id, failure.depth, failure.step_index), (p.cpmm, 2, Some(0)));
+        assert_eq!(failure.custom_code, Some(6005));
+        assert!(failure.error.is_none());
+        assert_eq!(
+            failure.cpi_error,
+            Some(DecodedError {
+                name: "ExceededSlippage".into(),
+                number: 6005,
+                message: "Exceeds desired slippage limit".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_arbitrage_error_after_completed_steps() {
+        let p = Programs::new();
+        let (wsol, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let err = ArbitrageError::InsufficientProfit;
+        let logs: Vec<String> = vec![
+            format!("Program {} invoke [1]", p.arb),
+            format!("Program log: Executing step 0: {} -> {} on RaydiumCpmm", wsol, mint),
+            format!("Program {} invoke [2]", p.cpmm),
+            format!("Program {} success", p.cpmm),
+            "Program log: Step 0 completed: amount_in=1000 amount_out=990 -> new_running_amount=990 cu_used=30000 cu_left=150000".into(),
+            anchor_error_line("programs/src/instructions/execute_arbitrage.rs:198", err),
+            format!("Program {} consumed 50000 of 200000 compute units", p.arb),
+            format!("Program {} failed: custom program error: {:#x}", p.arb, u32::from(err)),
+        ];
+
+        let trace = decode_logs(&p.arb, &logs);
+        let failure = trace.failure.as_ref().expect("failure");
+        assert_eq!((failure.program_id, failure.depth), (p.arb, 1));
+        assert!(!failure.is_cpi_failure());
+        // 环末利润校验不属于任何步骤
+        assert_eq!(failure.step_index, None);
+        assert!(trace.failed_step().is_none());
+        assert_eq!(failure.custom_code, Some(u32::from(err)));
+        assert!(failure.cpi_error.is_none());
+        let decoded = failure.error.as_ref().expect("arbitrage error");
+        assert!(decoded.is(ArbitrageError::InsufficientProfit));
+        assert!(!decoded.is(ArbitrageError::ComputeBudgetExhausted));
+        assert_eq!(decoded.message, err.to_string());
+    }
+
+    #[test]
+    fn compute_budget_abort_stays_on_its_step() {
+        let p = Programs::new();
+        let (wsol, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let err = ArbitrageError::ComputeBudgetExhausted;
+        let logs: Vec<String> = vec![
+            format!("Program {} invoke [1]", p.arb),
+            format!("Program log: Executing step 0: {} -> {} on PumpSwap", wsol, mint),
+            "Program log: Step 0 inputs: amount_in=1000, min_out=1, mode=Chained/ExactIn, legs=2".into(),
+            format!("Program {} invoke [2]", p.pumpswap),
+            format!("Program {} success", p.pumpswap),
+            "Program log: Step 0 aborted: remaining_cu=12000 < min_cu_reserve=40000 leg=1".into(),
+            anchor_error_line("programs/src/instructions/execute_arbitrage.rs:455", err),
+            format!("Program {} failed: custom program error: {:#x}", p.arb, u32::from(err)),
+        ];
+
+        let trace = decode_logs(&p.arb, &logs);
+        let failure = trace.failure.as_ref().expect("failure");
+        assert_eq!(failure.step_index, Some(0));
+        assert!(failure.error.as_ref().is_some_and(|e| e.is(err)));
+        let step = trace.failed_step().expect("failed step");
+        assert_eq!(step.aborted, Some(StepAbort { remaining_cu: 12000, min_cu_reserve: 40000, leg_index: 1 }));
+        assert!(step.legs.is_empty());
+        assert!(step.completed.is_none());
+    }
+
+    #[test]
+    fn below_summary_level_failures_are_not_attributed_to_steps() {
+        let p = Programs::new();
+        // log_level < Summary：无步骤/环级日志，只剩运行时与外部程序输出
+        let logs: Vec<String> = vec![
+            format!("Program {} invoke [1]", p.arb),
+            "Program log: Instruction: ExecuteArbitrage".into(),
+            format!("Program {} invoke [2]", p.cpmm),
+            format!("Program {} failed: custom program error: 0x1775", p.cpmm),
+            format!("Program {} failed: custom program error: 0x1775", p.arb),
+        ];
+
+        let trace = decode_logs(&p.arb, &logs);
+        assert!(trace.steps.is_empty() && trace.cycles.is_empty());
+        let failure = trace.failure.as_ref().expect("failure");
+        assert_eq!((failure.program_id, failure.depth), (p.cpmm, 2));
+        assert_eq!(failure.step_index, None);
+    }
+
+    #[test]
+    fn ignores_other_programs_and_flags_truncation() {
+        let p = Programs::new();
+        let logs: Vec<String> = vec![
+            format!("Program {} invoke [1]", p.cpmm),
+            "Program log: Executing step 0: x -> y on RaydiumCpmm".into(),
+            format!("Program {} failed: custom program error: 0x1", p.cpmm),
+            "Log truncated".into(),
+        ];
+
+        let trace = decode_logs(&p.arb, &logs);
+        assert!(trace.steps.is_empty());
+        assert!(trace.failure.is_none());
+        assert!(trace.truncated);
+    }
+}
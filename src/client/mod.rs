@@ -0,0 +1,26 @@
+//! 客户端 SDK（`client` 特性，链下使用）
+//!
+//! 由高层路线（每步的池账户 + mint 方向 + 成交参数）生成一条完整的 `execute_arbitrage_v3` 指令：
+//! - 收集各步最小集与追加项（程序、固定地址/PDA、用户/池两侧 ATAs、CLMM tick arrays），去重为全局表；
+//! - 为每步（含拆分腿）生成 `indices` 与 PDA bumps；
+//! - 按 discriminator + 版本化 payload（`0x03` + Borsh `ArbitrageParamsV3`）编码，拼出 `Instruction { accounts = [固定4 + 全局表] }`。
+//!
+//! `tick_arrays` 由 CLMM 池位图（及位图扩展）按方向与成交量选出有序的 tick array PDA，并标记是否需携带位图扩展。
+//!
+//! `logs` 把交易日志解析为结构化 trace（步骤、角色、ATA、指纹、金额与失败归因），嵌套 CPI 的失败归入发起它的步骤。
+//!
+//! `alt` 负责 v0 交易的 ALT 规划（静态 key / lookup 条目划分、1232 字节体积估算）与 create/extend 指令。
+//!
+//! 账户规则与链上 `account_resolver` / `dex_router::swaps` 一一对应，两侧须同步修改。
+
+pub mod route;
+pub mod builder;
+pub mod alt;
+pub mod tick_arrays;
+pub mod logs;
+
+pub use route::*;
+pub use builder::*;
+pub use alt::*;
+pub use tick_arrays::*;
+pub use logs::*;
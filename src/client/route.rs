@@ -0,0 +1,125 @@
+use anchor_lang::prelude::*;
+use crate::state::{AmountMode, ContractType, DexType, SwapMode};
+
+/// 单个池的链下已知账户（来自池状态 / 索引服务），builder 据此生成 indices 与追加项
+///
+/// 仅列出“池自身”的账户；程序 ID、PDA、用户/池侧 ATA、固定地址由 builder 推导补齐。
+#[derive(Clone, Debug)]
+pub enum PoolAccounts {
+    RaydiumCpmm {
+        pool_state: Pubkey,
+        amm_config: Pubkey,
+        token0_vault: Pubkey,
+        token1_vault: Pubkey,
+        observation_state: Pubkey,
+    },
+    RaydiumClmm {
+        pool_state: Pubkey,
+        amm_config: Pubkey,
+        /// 与本步 input_mint 同侧的 vault
+        input_vault: Pubkey,
+        output_vault: Pubkey,
+        observation_state: Pubkey,
+        /// 按交换方向排列的 tick arrays（1..=4 个，写入 indices 尾部；可由 `select_tick_arrays` 生成）
+        tick_arrays: Vec<Pubkey>,
+        tick_array_extension: Option<Pubkey>,
+    },
+    PumpFun {
+        bonding_curve: Pubkey,
+        mint: Pubkey,
+        creator: Pubkey,
+        /// None 取固定地址
+        fee_recipient: Option<Pubkey>,
+    },
+    PumpSwap {
+        pool_state: Pubkey,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        coin_creator: Pubkey,
+        /// `Pool.pool_base_token_account`
+        pool_base_token_account: Pubkey,
+        /// `Pool.pool_quote_token_account`
+        pool_quote_token_account: Pubkey,
+        /// None 取固定地址
+        fee_recipient: Option<Pubkey>,
+        fee_recipient_ata: Option<Pubkey>,
+    },
+}
+
+impl PoolAccounts {
+    pub fn dex_type(&self) -> DexType {
+        match self {
+            PoolAccounts::RaydiumCpmm { .. } => DexType::RaydiumCpmm,
+            PoolAccounts::RaydiumClmm { .. } => DexType::RaydiumClmm,
+            PoolAccounts::PumpFun { .. } => DexType::PumpFunBondingCurve,
+            PoolAccounts::PumpSwap { .. } => DexType::PumpSwap,
+        }
+    }
+
+    pub fn contract_type(&self) -> ContractType {
+        match self {
+            PoolAccounts::RaydiumCpmm { .. } => ContractType::CPMM,
+            PoolAccounts::RaydiumClmm { .. } => ContractType::CLMM,
+            PoolAccounts::PumpFun { .. } => ContractType::BondingCurve,
+            PoolAccounts::PumpSwap { .. } => ContractType::PumpSwap,
+        }
+    }
+
+    /// `PathStepV3.pool_id` / `SplitLeg.pool_id`
+    pub fn pool_id(&self) -> Pubkey {
+        match self {
+            PoolAccounts::RaydiumCpmm { pool_state, .. }
+            | PoolAccounts::RaydiumClmm { pool_state, .. }
+            | PoolAccounts::PumpSwap { pool_state, .. } => *pool_state,
+            PoolAccounts::PumpFun { bonding_curve, .. } => *bonding_curve,
+        }
+    }
+}
+
+/// 拆分腿（同一步、同交易对的另一个池）
+#[derive(Clone, Debug)]
+pub struct RouteLeg {
+    pub pool: PoolAccounts,
+    pub share_bps: u16,
+    pub minimum_amount_out: u64,
+    pub sqrt_price_limit_x64: u128,
+}
+
+/// 路径中的一步
+#[derive(Clone, Debug)]
+pub struct RouteStep {
+    pub pool: PoolAccounts,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub minimum_amount_out: u64,
+    pub amount_mode: AmountMode,
+    pub swap_mode: SwapMode,
+    pub sqrt_price_limit_x64: u128,
+    pub split_legs: Vec<RouteLeg>,
+}
+
+impl RouteStep {
+    /// 默认 `Chained` + `ExactIn`、不设 CLMM 限价、无拆分腿
+    pub fn new(pool: PoolAccounts, input_mint: Pubkey, output_mint: Pubkey, minimum_amount_out: u64) -> Self {
+        Self {
+            pool,
+            input_mint,
+            output_mint,
+            minimum_amount_out,
+            amount_mode: AmountMode::Chained,
+            swap_mode: SwapMode::ExactIn,
+            sqrt_price_limit_x64: 0,
+            split_legs: Vec::new(),
+        }
+    }
+}
+
+/// 一个套利环
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub input_amount: u64,
+    pub min_profit_lamports: u64,
+    pub steps: Vec<RouteStep>,
+    /// 执行前按链上报价跳过不再盈利的环（主环映射为 `quote_before_execute`，额外环为 `skip_if_unprofitable`）
+    pub skip_if_unprofitable: bool,
+}
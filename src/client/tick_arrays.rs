@@ -0,0 +1,328 @@
+use anchor_lang::prelude::*;
+
+use crate::account_derivation::types::pda_seeds;
+use crate::dex_router::types::constants::{
+    RAYDIUM_CLMM_MAX_TICK, RAYDIUM_CLMM_MAX_TICK_ARRAYS, RAYDIUM_CLMM_MIN_TICK, RAYDIUM_CLMM_TICK_ARRAY_SIZE,
+};
+use crate::errors::ArbitrageError;
+use crate::quote::clmm::{clmm_swap, tick_array_start_index};
+use crate::quote::state::{ClmmPoolState, TickArrayBitmapExtension, TickArrayState};
+
+/// 每个位图（池内默认位图 / 扩展中的单个子位图）覆盖的 tick array 数
+const TICK_ARRAY_BITMAP_SIZE: i32 = 512;
+
+/// tick array PDA：`["tick_array", pool_id, start_tick_index(大端)]`
+pub fn tick_array_address(clmm_program: &Pubkey, pool_id: &Pubkey, start_tick_index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[pda_seeds::RAYDIUM_CLMM_TICK_ARRAY, pool_id.as_ref(), &start_tick_index.to_be_bytes()],
+        clmm_program,
+    )
+    .0
+}
+
+/// 位图扩展 PDA：`["pool_tick_array_bitmap_extension", pool_id]`
+pub fn tick_array_bitmap_extension_address(clmm_program: &Pubkey, pool_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[pda_seeds::RAYDIUM_CLMM_TICK_ARRAY_BITMAP_EXTENSION, pool_id.as_ref()],
+        clmm_program,
+    )
+    .0
+}
+
+/// 一次 CLMM swap 所需的 tick arrays（按 swap 方向排序，首个为当前价格所在或方向上最近的已初始化数组）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClmmTickArraySelection {
+    pub pool_id: Pubkey,
+    pub start_indices: Vec<i32>,
+    pub tick_arrays: Vec<Pubkey>,
+    /// 扫描越过池内默认位图范围：swap 时需携带位图扩展账户
+    pub needs_extension: bool,
+    pub extension: Pubkey,
+}
+
+impl ClmmTickArraySelection {
+    /// 填入 `PoolAccounts::RaydiumClmm::tick_array_extension`
+    pub fn extension_account(&self) -> Option<Pubkey> {
+        self.needs_extension.then_some(self.extension)
+    }
+
+    /// 按成交量截取最短前缀：`tick_arrays` 为按 `start_indices` 顺序拉取的账户状态
+    ///
+    /// 截取后的数组足以完成 `amount_specified` 的模拟成交；全部数组仍不足时返回 `InsufficientLiquidity`。
+    pub fn truncate_for_amount(
+        &mut self,
+        pool: &ClmmPoolState,
+        trade_fee_rate: u32,
+        tick_arrays: &[TickArrayState],
+        zero_for_one: bool,
+        amount_specified: u64,
+        is_base_input: bool,
+    ) -> Result<()> {
+        require!(tick_arrays.len() == self.start_indices.len(), ArbitrageError::InvalidAccountCount);
+        for (state, start) in tick_arrays.iter().zip(&self.start_indices) {
+            require!(
+                state.pool_id == self.pool_id && state.start_tick_index == *start,
+                ArbitrageError::InvalidPoolData
+            );
+        }
+        for count in 1..=tick_arrays.len() {
+            match clmm_swap(pool, trade_fee_rate, &tick_arrays[..count], zero_for_one, amount_specified, is_base_input, 0) {
+                Ok(_) => {
+                    self.start_indices.truncate(count);
+                    self.tick_arrays.truncate(count);
+                    return Ok(());
+                }
+                Err(err) if err == Error::from(ArbitrageError::InsufficientLiquidity) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Err(ArbitrageError::InsufficientLiquidity.into())
+    }
+}
+
+/// 由池位图（及可选的位图扩展）按方向列出已初始化的 tick arrays，至多 `RAYDIUM_CLMM_MAX_TICK_ARRAYS` 个
+///
+/// 扫描越出默认位图范围时置 `needs_extension`；此时若未提供 `extension` 则停止扫描（其外的初始化状态未知）。
+/// 结果再经 `truncate_for_amount` 按成交量裁剪。
+pub fn select_tick_arrays(
+    clmm_program: &Pubkey,
+    pool_id: &Pubkey,
+    pool: &ClmmPoolState,
+    extension: Option<&TickArrayBitmapExtension>,
+    zero_for_one: bool,
+) -> ClmmTickArraySelection {
+    let span = RAYDIUM_CLMM_TICK_ARRAY_SIZE * pool.tick_spacing as i32;
+    let min_start = tick_array_start_index(RAYDIUM_CLMM_MIN_TICK, pool.tick_spacing);
+    let max_start = tick_array_start_index(RAYDIUM_CLMM_MAX_TICK, pool.tick_spacing);
+    let step = if zero_for_one { -span } else { span };
+
+    let mut start_indices = Vec::new();
+    let mut needs_extension = false;
+    let mut start = tick_array_start_index(pool.tick_current, pool.tick_spacing);
+    while (min_start..=max_start).contains(&start) && start_indices.len() < RAYDIUM_CLMM_MAX_TICK_ARRAYS as usize {
+        let initialized = if in_default_bitmap(start, span) {
+            Some(default_bitmap_bit(pool, start, span))
+        } else {
+            needs_extension = true;
+            extension.map(|ext| extension_bitmap_bit(ext, start, span))
+        };
+        match initialized {
+            Some(true) => start_indices.push(start),
+            Some(false) => {}
+            None => break,
+        }
+        start += step;
+    }
+
+    ClmmTickArraySelection {
+        pool_id: *pool_id,
+        tick_arrays: start_indices.iter().map(|s| tick_array_address(clmm_program, pool_id, *s)).collect(),
+        start_indices,
+        needs_extension,
+        extension: tick_array_bitmap_extension_address(clmm_program, pool_id),
+    }
+}
+
+/// 池内默认位图覆盖 start ∈ [-512 × span, 512 × span)
+fn in_default_bitmap(start: i32, span: i32) -> bool {
+    let bound = TICK_ARRAY_BITMAP_SIZE * span;
+    (-bound..bound).contains(&start)
+}
+
+fn bit_set(words: &[u64], bit: usize) -> bool {
+    words[bit / 64] >> (bit % 64) & 1 == 1
+}
+
+fn default_bitmap_bit(pool: &ClmmPoolState, start: i32, span: i32) -> bool {
+    bit_set(&pool.tick_array_bitmap, (start / span + TICK_ARRAY_BITMAP_SIZE) as usize)
+}
+
+/// 扩展位图定位（raydium-clmm `get_bitmap_offset` / `tick_array_offset_in_bitmap`）：
+/// 记 B = 512 × span，子位图 i 正向覆盖 start ∈ [(i+1)B, (i+2)B)，负向覆盖 [-(i+2)B, -(i+1)B)，位序均按 start 升序
+fn extension_bitmap_bit(extension: &TickArrayBitmapExtension, start: i32, span: i32) -> bool {
+    let bitmap_span = TICK_ARRAY_BITMAP_SIZE * span;
+    let abs = start.abs();
+    let mut offset = abs / bitmap_span - 1;
+    if start < 0 && abs % bitmap_span == 0 {
+        offset -= 1;
+    }
+    let remainder = abs % bitmap_span;
+    let mut bit = remainder / span;
+    if start < 0 && remainder != 0 {
+        bit = TICK_ARRAY_BITMAP_SIZE - bit;
+    }
+    let bitmaps = if start < 0 { &extension.negative_tick_array_bitmap } else { &extension.positive_tick_array_bitmap };
+    bitmaps
+        .get(offset as usize)
+        .is_some_and(|words| bit_set(words, bit as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quote::state::{CLMM_BITMAP_WORDS, CLMM_EXTENSION_BITMAP_COUNT};
+
+    fn pool(tick_current: i32, tick_spacing: u16) -> ClmmPoolState {
+        ClmmPoolState {
+            amm_config: Pubkey::default(),
+            token_mint_0: Pubkey::default(),
+            token_mint_1: Pubkey::default(),
+            token_vault_0: Pubkey::default(),
+            token_vault_1: Pubkey::default(),
+            observation_key: Pubkey::default(),
+            tick_spacing,
+            liquidity: 0,
+            sqrt_price_x64: 0,
+            tick_current,
+            status: 0,
+            tick_array_bitmap: [0; 16],
+        }
+    }
+
+    fn extension() -> TickArrayBitmapExtension {
+        TickArrayBitmapExtension {
+            pool_id: Pubkey::default(),
+            positive_tick_array_bitmap: [[0; CLMM_BITMAP_WORDS]; CLMM_EXTENSION_BITMAP_COUNT],
+            negative_tick_array_bitmap: [[0; CLMM_BITMAP_WORDS]; CLMM_EXTENSION_BITMAP_COUNT],
+        }
+    }
+
+    fn set_bit(words: &mut [u64], bit: usize) {
+        words[bit / 64] |= 1 << (bit % 64);
+    }
+
+    /// 按 raydium-clmm 布局直接置位：(是否负向, 子位图序号, 位)
+    fn set_extension_bit(ext: &mut TickArrayBitmapExtension, (negative, offset, bit): (bool, usize, usize)) {
+        let bitmaps = if negative { &mut ext.negative_tick_array_bitmap } else { &mut ext.positive_tick_array_bitmap };
+        set_bit(&mut bitmaps[offset], bit);
+    }
+
+    fn span(tick_spacing: u16) -> i32 {
+        RAYDIUM_CLMM_TICK_ARRAY_SIZE * tick_spacing as i32
+    }
+
+    #[test]
+    fn default_bitmap_bit_positions() {
+        for tick_spacing in [1u16, 10, 60] {
+            let span = span(tick_spacing);
+            let bound = TICK_ARRAY_BITMAP_SIZE * span;
+            // (start, bit)：位 512 对应 start 0，覆盖 [-512 × span, 512 × span)
+            for (start, bit) in [(-bound, 0), (-span, 511), (0, 512), (span, 513), (bound - span, 1023)] {
+                assert!(in_default_bitmap(start, span));
+                let mut pool = pool(0, tick_spacing);
+                set_bit(&mut pool.tick_array_bitmap, bit);
+                assert!(default_bitmap_bit(&pool, start, span), "spacing {tick_spacing} start {start}");
+                assert!(start + span == bound || !default_bitmap_bit(&pool, start + span, span));
+            }
+            assert!(!in_default_bitmap(bound, span));
+            assert!(!in_default_bitmap(-bound - span, span));
+        }
+    }
+
+    #[test]
+    fn extension_bitmap_bit_positions() {
+        for tick_spacing in [1u16, 10] {
+            let span = span(tick_spacing);
+            let b = TICK_ARRAY_BITMAP_SIZE * span;
+            // (start, (负向, 子位图, 位))：负向恰为 512 × span 整数倍时落在前一子位图的位 0
+            let cases = [
+                (b, (false, 0, 0)),
+                (b + span, (false, 0, 1)),
+                (2 * b - span, (false, 0, 511)),
+                (2 * b, (false, 1, 0)),
+                (-b - span, (true, 0, 511)),
+                (-2 * b + span, (true, 0, 1)),
+                (-2 * b, (true, 0, 0)),
+                (-2 * b - span, (true, 1, 511)),
+                (-3 * b, (true, 1, 0)),
+                (-4 * b, (true, 2, 0)),
+            ];
+            for (start, position) in cases {
+                let mut ext = extension();
+                set_extension_bit(&mut ext, position);
+                assert!(extension_bitmap_bit(&ext, start, span), "spacing {tick_spacing} start {start} {position:?}");
+                assert!(!extension_bitmap_bit(&ext, start - span, span), "spacing {tick_spacing} start {}", start - span);
+                assert!(!extension_bitmap_bit(&ext, start + span, span), "spacing {tick_spacing} start {}", start + span);
+            }
+        }
+    }
+
+    #[test]
+    fn select_crosses_from_default_bitmap_into_extension() {
+        let (program, pool_id) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let tick_spacing = 10;
+        let span = span(tick_spacing);
+        let b = TICK_ARRAY_BITMAP_SIZE * span;
+
+        // 向上：默认位图末两个数组 + 扩展正向子位图 0 的位 0、2
+        let mut up = pool(b - 2 * span + 1, tick_spacing);
+        set_bit(&mut up.tick_array_bitmap, 1022);
+        set_bit(&mut up.tick_array_bitmap, 1023);
+        let mut ext = extension();
+        set_extension_bit(&mut ext, (false, 0, 0));
+        set_extension_bit(&mut ext, (false, 0, 2));
+        let selection = select_tick_arrays(&program, &pool_id, &up, Some(&ext), false);
+        assert_eq!(selection.start_indices, [b - 2 * span, b - span, b, b + 2 * span]);
+        assert!(selection.needs_extension);
+        assert_eq!(selection.extension_account(), Some(tick_array_bitmap_extension_address(&program, &pool_id)));
+        let expected: Vec<_> = selection.start_indices.iter().map(|s| tick_array_address(&program, &pool_id, *s)).collect();
+        assert_eq!(selection.tick_arrays, expected);
+
+        // 未提供扩展：越界即停止，仍标记需携带扩展
+        let selection = select_tick_arrays(&program, &pool_id, &up, None, false);
+        assert_eq!(selection.start_indices, [b - 2 * span, b - span]);
+        assert!(selection.needs_extension);
+
+        // 向下：默认位图首两个数组 + 扩展负向子位图 0 的位 511、509
+        let mut down = pool(-b + span, tick_spacing);
+        set_bit(&mut down.tick_array_bitmap, 1);
+        set_bit(&mut down.tick_array_bitmap, 0);
+        let mut ext = extension();
+        set_extension_bit(&mut ext, (true, 0, 511));
+        set_extension_bit(&mut ext, (true, 0, 509));
+        let selection = select_tick_arrays(&program, &pool_id, &down, Some(&ext), true);
+        assert_eq!(selection.start_indices, [-b + span, -b, -b - span, -b - 3 * span]);
+        assert!(selection.needs_extension);
+    }
+
+    #[test]
+    fn select_within_default_bitmap_skips_extension() {
+        let (program, pool_id) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let tick_spacing = 1;
+        let span = span(tick_spacing);
+
+        // 当前 tick -1 位于 start -span；向下找满 4 个即停，不触及边界
+        let mut state = pool(-1, tick_spacing);
+        for bit in [511, 509, 508, 300, 0] {
+            set_bit(&mut state.tick_array_bitmap, bit);
+        }
+        let selection = select_tick_arrays(&program, &pool_id, &state, None, true);
+        assert_eq!(selection.start_indices, [-span, -3 * span, -4 * span, -212 * span]);
+        assert!(!selection.needs_extension);
+        assert_eq!(selection.extension_account(), None);
+
+        // 向上仅当前数组已初始化，未找满 4 个即扫到默认位图边界：需携带扩展
+        let selection = select_tick_arrays(&program, &pool_id, &state, None, false);
+        assert_eq!(selection.start_indices, [-span]);
+        assert!(selection.needs_extension);
+    }
+
+    #[test]
+    fn select_stops_at_protocol_bounds() {
+        let (program, pool_id) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let tick_spacing = 1;
+        let span = span(tick_spacing);
+        let min_start = tick_array_start_index(RAYDIUM_CLMM_MIN_TICK, tick_spacing);
+        let b = TICK_ARRAY_BITMAP_SIZE * span;
+
+        // MIN_TICK 所在数组：负向子位图 (|start| / B − 1)，不再向下越界
+        let state = pool(RAYDIUM_CLMM_MIN_TICK, tick_spacing);
+        let offset = (min_start.abs() / b - 1) as usize;
+        let bit = (TICK_ARRAY_BITMAP_SIZE - (min_start.abs() % b) / span) as usize;
+        let mut ext = extension();
+        set_extension_bit(&mut ext, (true, offset, bit));
+        let selection = select_tick_arrays(&program, &pool_id, &state, Some(&ext), true);
+        assert_eq!(selection.start_indices, [min_start]);
+        assert!(selection.needs_extension);
+    }
+}
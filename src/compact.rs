@@ -0,0 +1,525 @@
+//! V3 紧凑编码（小端；mint 以全局 remaining_accounts 的 u8 索引表示）
+//!
+//! 动机：V2 的 Borsh `ArbitrageParams` 每步重复 32 字节 input/output mint 与 `Option<Pubkey>` pool，
+//! 10 步路径仅参数就占 ~800 字节，挤压 1232 字节交易上限。V3 每步固定 13 字节（packed、opt、两个 mint 索引、u64 min_out、indices_len）+ 可选字段 + indices。
+//! 执行不读 `pool_id`（池账户由 indices 给出），故紧凑编码不携带 pool。
+//!
+//! 头部：
+//! ```text
+//! u8  tag                = 0x83（`ProtocolVersion::V3Compact`）
+//! u8  flags              bit0 close_intermediate_atas | bit1 has_min_cu_reserve | bit2..3 log_level(0=默认,1=Error,2=Summary,3=Verbose) | bit4 quote_before_execute
+//! u64 input_amount
+//! u64 min_profit_lamports
+//! u16 max_slippage_bps
+//! [u64 min_cu_reserve]   仅 has_min_cu_reserve
+//! u8  step_count
+//! ```
+//! 每步：
+//! ```text
+//! u8  packed             bit0..1 dex_type | bit2..3 contract_type | bit4 exact_out | bit5..6 amount_mode(0=Chained,1=FullBalance,2=BalanceBps) | bit7 has_sqrt_limit
+//! u8  opt                bit0..5 bump 掩码（global, event_authority, pool, creator_vault, global_volume_accumulator, user_volume_accumulator）| bit6..7 保留
+//! u8  input_mint_idx
+//! u8  output_mint_idx
+//! u64 minimum_amount_out
+//! [u16  balance_bps]       仅 BalanceBps
+//! [u128 sqrt_price_limit]  仅 has_sqrt_limit
+//! [u8 × popcount(mask)]    bump，按掩码位序
+//! u8  indices_len
+//! u8 × indices_len
+//! ```
+//! 解析为零拷贝视图：`parse` 单遍校验全部字节后只保留步骤区切片，`CompactParams::steps` 按需逐步解码
+//! （`CompactStep` 借用指令数据，indices 为切片），不经 Borsh `Vec` 反序列化；
+//! 未知保留位、截断或尾随字节一律 `InvalidInstructionData`。
+
+use anchor_lang::prelude::*;
+use crate::errors::ArbitrageError;
+use crate::state::{
+    AmountMode, ContractType, DexType, LogLevel, MappingView, PdaBumps, ProtocolVersion, StepView,
+    SwapMode,
+};
+
+/// 紧凑编码标签（payload 首字节）
+pub const COMPACT_TAG_V3: u8 = ProtocolVersion::V3Compact as u8;
+
+const FLAG_CLOSE_INTERMEDIATE_ATAS: u8 = 1 << 0;
+const FLAG_HAS_MIN_CU_RESERVE: u8 = 1 << 1;
+const FLAG_LOG_LEVEL_SHIFT: u8 = 2;
+const FLAG_LOG_LEVEL_MASK: u8 = 0b11 << FLAG_LOG_LEVEL_SHIFT;
+const FLAG_QUOTE_BEFORE_EXECUTE: u8 = 1 << 4;
+const FLAG_RESERVED: u8 =
+    !(FLAG_CLOSE_INTERMEDIATE_ATAS | FLAG_HAS_MIN_CU_RESERVE | FLAG_LOG_LEVEL_MASK | FLAG_QUOTE_BEFORE_EXECUTE);
+
+const STEP_EXACT_OUT: u8 = 1 << 4;
+const STEP_AMOUNT_MODE_SHIFT: u8 = 5;
+const STEP_HAS_SQRT_LIMIT: u8 = 1 << 7;
+
+const OPT_BUMP_COUNT: u8 = 6;
+const OPT_RESERVED: u8 = !((1 << OPT_BUMP_COUNT) - 1);
+
+/// 路径步数上限（与 V2 入口一致）
+const MAX_STEPS: u8 = 10;
+
+/// 顺序读取器：越界即 `InvalidInstructionData`
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).ok_or(ArbitrageError::InvalidInstructionData)?;
+        let bytes = self.data.get(self.pos..end).ok_or(ArbitrageError::InvalidInstructionData)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        self.take(N)?.try_into().map_err(|_| error!(ArbitrageError::InvalidInstructionData))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn u128(&mut self) -> Result<u128> {
+        Ok(u128::from_le_bytes(self.array()?))
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+}
+
+/// V3 参数零拷贝视图
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompactParams<'a> {
+    pub input_amount: u64,
+    pub min_profit_lamports: u64,
+    pub max_slippage_bps: u16,
+    pub close_intermediate_atas: bool,
+    pub min_cu_reserve: Option<u64>,
+    pub log_level: Option<LogLevel>,
+    pub quote_before_execute: bool,
+    pub step_count: u8,
+    /// 步骤区原始字节（已整体校验）
+    steps_data: &'a [u8],
+}
+
+/// V3 单步零拷贝视图
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompactStep<'a> {
+    pub dex_type: DexType,
+    pub contract_type: ContractType,
+    pub swap_mode: SwapMode,
+    pub amount_mode: AmountMode,
+    pub input_mint_idx: u8,
+    pub output_mint_idx: u8,
+    pub minimum_amount_out: u64,
+    pub sqrt_price_limit_x64: u128,
+    pub bumps: PdaBumps,
+    pub indices: &'a [u8],
+}
+
+impl<'a> CompactParams<'a> {
+    /// 解析并整体校验（含全部步骤与尾随字节）；`data` 以标签字节开头
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let mut r = ByteReader::new(data);
+        require!(r.u8()? == COMPACT_TAG_V3, ArbitrageError::InvalidInstructionData);
+        let flags = r.u8()?;
+        require!(flags & FLAG_RESERVED == 0, ArbitrageError::InvalidInstructionData);
+        let input_amount = r.u64()?;
+        let min_profit_lamports = r.u64()?;
+        let max_slippage_bps = r.u16()?;
+        let min_cu_reserve = if flags & FLAG_HAS_MIN_CU_RESERVE != 0 { Some(r.u64()?) } else { None };
+        let log_level = match (flags & FLAG_LOG_LEVEL_MASK) >> FLAG_LOG_LEVEL_SHIFT {
+            0 => None,
+            1 => Some(LogLevel::Error),
+            2 => Some(LogLevel::Summary),
+            _ => Some(LogLevel::Verbose),
+        };
+        let step_count = r.u8()?;
+        require!(step_count > 0, ArbitrageError::PathTooShort);
+        require!(step_count <= MAX_STEPS, ArbitrageError::PathTooLong);
+
+        let steps_data = r.rest();
+        let mut steps_reader = ByteReader::new(steps_data);
+        for _ in 0..step_count {
+            read_step(&mut steps_reader)?;
+        }
+        require!(steps_reader.rest().is_empty(), ArbitrageError::InvalidInstructionData);
+
+        Ok(Self {
+            input_amount,
+            min_profit_lamports,
+            max_slippage_bps,
+            close_intermediate_atas: flags & FLAG_CLOSE_INTERMEDIATE_ATAS != 0,
+            min_cu_reserve,
+            log_level,
+            quote_before_execute: flags & FLAG_QUOTE_BEFORE_EXECUTE != 0,
+            step_count,
+            steps_data,
+        })
+    }
+
+    /// 按需逐步解码（`parse` 已校验，迭代期间不会失败；仍以 Result 暴露以免 panic）
+    pub fn steps(&self) -> CompactStepIter<'a> {
+        CompactStepIter { reader: ByteReader::new(self.steps_data), remaining: self.step_count }
+    }
+}
+
+impl<'a> CompactStep<'a> {
+    /// 执行视图：mint 索引按全局表取 Pubkey，indices 直接借用指令数据
+    pub fn view(&self, accounts: &[AccountInfo]) -> Result<StepView<'a>> {
+        let key_at = |idx: u8| -> Result<Pubkey> {
+            accounts
+                .get(idx as usize)
+                .map(|ai| ai.key())
+                .ok_or(ArbitrageError::InvalidAccountIndex.into())
+        };
+        Ok(StepView {
+            dex_type: self.dex_type,
+            input_mint: key_at(self.input_mint_idx)?,
+            output_mint: key_at(self.output_mint_idx)?,
+            minimum_amount_out: self.minimum_amount_out,
+            amount_mode: self.amount_mode,
+            swap_mode: self.swap_mode,
+            sqrt_price_limit_x64: self.sqrt_price_limit_x64,
+            mapping: MappingView {
+                dex_type: self.dex_type,
+                contract_type: self.contract_type,
+                indices: self.indices,
+                bumps: self.bumps,
+            },
+            // V3 紧凑编码暂不承载拆分腿
+            split_legs: &[],
+        })
+    }
+}
+
+pub struct CompactStepIter<'a> {
+    reader: ByteReader<'a>,
+    remaining: u8,
+}
+
+impl<'a> Iterator for CompactStepIter<'a> {
+    type Item = Result<CompactStep<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(read_step(&mut self.reader))
+    }
+}
+
+fn read_step<'a>(r: &mut ByteReader<'a>) -> Result<CompactStep<'a>> {
+    let packed = r.u8()?;
+    let opt = r.u8()?;
+    require!(opt & OPT_RESERVED == 0, ArbitrageError::InvalidInstructionData);
+
+    let dex_type = match packed & 0b11 {
+        0 => DexType::RaydiumCpmm,
+        1 => DexType::RaydiumClmm,
+        2 => DexType::PumpFunBondingCurve,
+        _ => DexType::PumpSwap,
+    };
+    let contract_type = match (packed >> 2) & 0b11 {
+        0 => ContractType::CPMM,
+        1 => ContractType::CLMM,
+        2 => ContractType::BondingCurve,
+        _ => ContractType::PumpSwap,
+    };
+    let swap_mode = if packed & STEP_EXACT_OUT != 0 { SwapMode::ExactOut } else { SwapMode::ExactIn };
+
+    let input_mint_idx = r.u8()?;
+    let output_mint_idx = r.u8()?;
+    let minimum_amount_out = r.u64()?;
+    let amount_mode = match (packed >> STEP_AMOUNT_MODE_SHIFT) & 0b11 {
+        0 => AmountMode::Chained,
+        1 => AmountMode::FullBalance,
+        2 => AmountMode::BalanceBps(r.u16()?),
+        _ => return Err(ArbitrageError::InvalidInstructionData.into()),
+    };
+    let sqrt_price_limit_x64 = if packed & STEP_HAS_SQRT_LIMIT != 0 { r.u128()? } else { 0 };
+
+    let mut bump_slots = [None; OPT_BUMP_COUNT as usize];
+    for (bit, slot) in bump_slots.iter_mut().enumerate() {
+        if opt & (1 << bit) != 0 {
+            *slot = Some(r.u8()?);
+        }
+    }
+    let [global, event_authority, pool, creator_vault, global_volume_accumulator, user_volume_accumulator] = bump_slots;
+
+    let indices_len = r.u8()? as usize;
+    let indices = r.take(indices_len)?;
+
+    Ok(CompactStep {
+        dex_type,
+        contract_type,
+        swap_mode,
+        amount_mode,
+        input_mint_idx,
+        output_mint_idx,
+        minimum_amount_out,
+        sqrt_price_limit_x64,
+        bumps: PdaBumps {
+            global,
+            event_authority,
+            pool,
+            creator_vault,
+            global_volume_accumulator,
+            user_volume_accumulator,
+        },
+        indices,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按模块文档的布局编码单步（测试用编码器，与 `read_step` 互为逆）
+    fn encode_step(out: &mut Vec<u8>, step: &CompactStep) {
+        let mut packed = step.dex_type as u8 | (step.contract_type as u8) << 2;
+        if step.swap_mode == SwapMode::ExactOut {
+            packed |= STEP_EXACT_OUT;
+        }
+        packed |= match step.amount_mode {
+            AmountMode::Chained => 0,
+            AmountMode::FullBalance => 1,
+            AmountMode::BalanceBps(_) => 2,
+        } << STEP_AMOUNT_MODE_SHIFT;
+        if step.sqrt_price_limit_x64 != 0 {
+            packed |= STEP_HAS_SQRT_LIMIT;
+        }
+        let b = step.bumps;
+        let bumps = [b.global, b.event_authority, b.pool, b.creator_vault, b.global_volume_accumulator, b.user_volume_accumulator];
+        let mut opt = 0;
+        for (bit, bump) in bumps.iter().enumerate() {
+            if bump.is_some() {
+                opt |= 1 << bit;
+            }
+        }
+        out.extend_from_slice(&[packed, opt, step.input_mint_idx, step.output_mint_idx]);
+        out.extend_from_slice(&step.minimum_amount_out.to_le_bytes());
+        if let AmountMode::BalanceBps(bps) = step.amount_mode {
+            out.extend_from_slice(&bps.to_le_bytes());
+        }
+        if step.sqrt_price_limit_x64 != 0 {
+            out.extend_from_slice(&step.sqrt_price_limit_x64.to_le_bytes());
+        }
+        out.extend(bumps.iter().flatten());
+        out.push(step.indices.len() as u8);
+        out.extend_from_slice(step.indices);
+    }
+
+    fn encode(params: &CompactParams, steps: &[CompactStep]) -> Vec<u8> {
+        let mut flags = 0;
+        if params.close_intermediate_atas {
+            flags |= FLAG_CLOSE_INTERMEDIATE_ATAS;
+        }
+        if params.min_cu_reserve.is_some() {
+            flags |= FLAG_HAS_MIN_CU_RESERVE;
+        }
+        flags |= match params.log_level {
+            None => 0,
+            Some(LogLevel::Error) => 1,
+            Some(LogLevel::Summary) => 2,
+            Some(LogLevel::Verbose) => 3,
+        } << FLAG_LOG_LEVEL_SHIFT;
+        if params.quote_before_execute {
+            flags |= FLAG_QUOTE_BEFORE_EXECUTE;
+        }
+        let mut out = vec![COMPACT_TAG_V3, flags];
+        out.extend_from_slice(&params.input_amount.to_le_bytes());
+        out.extend_from_slice(&params.min_profit_lamports.to_le_bytes());
+        out.extend_from_slice(&params.max_slippage_bps.to_le_bytes());
+        out.extend(params.min_cu_reserve.map(u64::to_le_bytes).into_iter().flatten());
+        out.push(steps.len() as u8);
+        for step in steps {
+            encode_step(&mut out, step);
+        }
+        out
+    }
+
+    fn header() -> CompactParams<'static> {
+        CompactParams {
+            input_amount: 1_000_000,
+            min_profit_lamports: 5_000,
+            max_slippage_bps: 50,
+            close_intermediate_atas: true,
+            min_cu_reserve: Some(20_000),
+            log_level: Some(LogLevel::Verbose),
+            quote_before_execute: true,
+            step_count: 0,
+            steps_data: &[],
+        }
+    }
+
+    fn sample_steps() -> [CompactStep<'static>; 3] {
+        let minimal = CompactStep {
+            dex_type: DexType::RaydiumCpmm,
+            contract_type: ContractType::CPMM,
+            swap_mode: SwapMode::ExactIn,
+            amount_mode: AmountMode::Chained,
+            input_mint_idx: 3,
+            output_mint_idx: 4,
+            minimum_amount_out: 1,
+            sqrt_price_limit_x64: 0,
+            bumps: PdaBumps::default(),
+            indices: &[5, 6, 7, 8, 9, 10, 11],
+        };
+        [
+            minimal,
+            // 全部可选字段
+            CompactStep {
+                dex_type: DexType::PumpFunBondingCurve,
+                contract_type: ContractType::BondingCurve,
+                swap_mode: SwapMode::ExactOut,
+                amount_mode: AmountMode::BalanceBps(7_500),
+                input_mint_idx: 4,
+                output_mint_idx: 12,
+                minimum_amount_out: u64::MAX,
+                sqrt_price_limit_x64: u128::MAX - 1,
+                bumps: PdaBumps {
+                    global: Some(255),
+                    event_authority: Some(254),
+                    pool: Some(253),
+                    creator_vault: Some(252),
+                    global_volume_accumulator: Some(251),
+                    user_volume_accumulator: Some(250),
+                },
+                indices: &[13, 12, 14],
+            },
+            // 稀疏 bump 掩码 + FullBalance
+            CompactStep {
+                dex_type: DexType::PumpSwap,
+                contract_type: ContractType::PumpSwap,
+                amount_mode: AmountMode::FullBalance,
+                input_mint_idx: 12,
+                output_mint_idx: 3,
+                bumps: PdaBumps { event_authority: Some(9), creator_vault: Some(7), ..PdaBumps::default() },
+                indices: &[15, 12, 3, 16],
+                ..minimal
+            },
+        ]
+    }
+
+    fn assert_rejected(data: &[u8], expected: ArbitrageError) {
+        let err = CompactParams::parse(data).expect_err("must be rejected");
+        assert_eq!(err, expected.into());
+    }
+
+    #[test]
+    fn parse_round_trips_header_and_steps() {
+        let steps = sample_steps();
+        let data = encode(&header(), &steps);
+        let parsed = CompactParams::parse(&data).unwrap();
+        assert_eq!(parsed.input_amount, 1_000_000);
+        assert_eq!(parsed.min_profit_lamports, 5_000);
+        assert_eq!(parsed.max_slippage_bps, 50);
+        assert!(parsed.close_intermediate_atas);
+        assert_eq!(parsed.min_cu_reserve, Some(20_000));
+        assert_eq!(parsed.log_level, Some(LogLevel::Verbose));
+        assert!(parsed.quote_before_execute);
+        assert_eq!(parsed.step_count, 3);
+        let decoded: Vec<_> = parsed.steps().collect::<Result<_>>().unwrap();
+        assert_eq!(decoded, steps);
+        assert_eq!(encode(&parsed, &decoded), data);
+    }
+
+    #[test]
+    fn parse_round_trips_minimal_header() {
+        let params = CompactParams {
+            close_intermediate_atas: false,
+            min_cu_reserve: None,
+            log_level: None,
+            quote_before_execute: false,
+            ..header()
+        };
+        let steps = &sample_steps()[..1];
+        let data = encode(&params, steps);
+        // 头部 21 字节 + 单步固定 13 字节 + 7 个 indices
+        assert_eq!(data.len(), 21 + 13 + 7);
+        let parsed = CompactParams::parse(&data).unwrap();
+        assert_eq!(parsed.min_cu_reserve, None);
+        assert_eq!(parsed.log_level, None);
+        assert!(!parsed.close_intermediate_atas);
+        assert!(!parsed.quote_before_execute);
+        assert_eq!(parsed.steps().next().unwrap().unwrap(), steps[0]);
+    }
+
+    #[test]
+    fn steps_borrow_indices_from_instruction_data() {
+        let data = encode(&header(), &sample_steps());
+        let parsed = CompactParams::parse(&data).unwrap();
+        let range = data.as_ptr_range();
+        for step in parsed.steps() {
+            let indices = step.unwrap().indices;
+            assert!(range.contains(&indices.as_ptr()), "indices must point into the instruction data");
+        }
+    }
+
+    #[test]
+    fn parse_rejects_every_truncation() {
+        let data = encode(&header(), &sample_steps());
+        for len in 0..data.len() {
+            assert!(CompactParams::parse(&data[..len]).is_err(), "truncated to {len} bytes");
+        }
+    }
+
+    #[test]
+    fn parse_rejects_trailing_bytes() {
+        let mut data = encode(&header(), &sample_steps());
+        data.push(0);
+        assert_rejected(&data, ArbitrageError::InvalidInstructionData);
+    }
+
+    #[test]
+    fn parse_rejects_bad_version_tag() {
+        let mut data = encode(&header(), &sample_steps());
+        for tag in [0, ProtocolVersion::V2 as u8, ProtocolVersion::V3 as u8, 0x82, u8::MAX] {
+            data[0] = tag;
+            assert_rejected(&data, ArbitrageError::InvalidInstructionData);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_reserved_bits_and_bad_amount_mode() {
+        let steps = sample_steps();
+        let mut data = encode(&header(), &steps[..1]);
+        data[1] |= 1 << 7;
+        assert_rejected(&data, ArbitrageError::InvalidInstructionData);
+
+        // 步骤区起于头部之后（含 min_cu_reserve 共 29 字节）
+        let step_at = 29;
+        let mut data = encode(&header(), &steps[..1]);
+        data[step_at + 1] |= OPT_RESERVED;
+        assert_rejected(&data, ArbitrageError::InvalidInstructionData);
+
+        let mut data = encode(&header(), &steps[..1]);
+        data[step_at] |= 0b11 << STEP_AMOUNT_MODE_SHIFT;
+        assert_rejected(&data, ArbitrageError::InvalidInstructionData);
+    }
+
+    #[test]
+    fn parse_rejects_step_count_out_of_range() {
+        assert_rejected(&encode(&header(), &[]), ArbitrageError::PathTooShort);
+        let step = sample_steps()[0];
+        assert_rejected(&encode(&header(), &[step; MAX_STEPS as usize + 1]), ArbitrageError::PathTooLong);
+        assert!(CompactParams::parse(&encode(&header(), &[step; MAX_STEPS as usize])).is_ok());
+    }
+}
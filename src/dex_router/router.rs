@@ -1,17 +1,20 @@
 use anchor_lang::prelude::*;
 use crate::account_derivation::DerivedAccounts;
-use crate::state::DexType;
+use crate::account_resolver::{AccountResolver, AccountTable};
+use crate::errors::ArbitrageError;
+use crate::state::{DexType, MappingView, SplitLeg, SwapMode};
 use super::swaps::*;
 use super::types::*;
 
 pub struct DexRouter;
 
 impl DexRouter {
+    #[allow(clippy::too_many_arguments)]
     pub fn execute_swap<'info>(
         dex_type: DexType,
         accounts: DexAccounts<'info>,
         derived: &DerivedAccounts,
-        remaining_accounts: &'info [AccountInfo<'info>],
+        table: &AccountTable<'info>,
         payer: &AccountInfo<'info>,
         token_program: &AccountInfo<'info>,
         associated_token_program: &AccountInfo<'info>,
@@ -20,16 +23,21 @@ impl DexRouter {
         user_output_account: &AccountInfo<'info>,
         amount_in: u64,
         minimum_amount_out: u64,
+        swap_mode: SwapMode,
+        sqrt_price_limit_x64: u128,
     ) -> Result<SwapResult> {
-        msg!("Routing {} swap: {} -> min {}", 
-             match dex_type {
-                 DexType::RaydiumCpmm => "Raydium CPMM",
-                 DexType::RaydiumClmm => "Raydium CLMM", 
-                 DexType::PumpFunBondingCurve => "PumpFun",
-                 DexType::PumpSwap => "PumpSwap",
-             },
-             amount_in, 
-             minimum_amount_out);
+        if derived.log_level.verbose() {
+            msg!("Routing {} swap ({:?}): {} -> min {}", 
+                 match dex_type {
+                     DexType::RaydiumCpmm => "Raydium CPMM",
+                     DexType::RaydiumClmm => "Raydium CLMM", 
+                     DexType::PumpFunBondingCurve => "PumpFun",
+                     DexType::PumpSwap => "PumpSwap",
+                 },
+                 swap_mode,
+                 amount_in, 
+                 minimum_amount_out);
+        }
         
         // Dispatch to the appropriate DEX implementation
         match (dex_type, accounts) {
@@ -37,7 +45,7 @@ impl DexRouter {
                 RaydiumCpmmSwap::execute_swap(
                     cpmm_accounts,
                     derived,
-                    remaining_accounts,
+                    table,
                     payer,
                     token_program,
                     associated_token_program,
@@ -46,13 +54,15 @@ impl DexRouter {
                     user_output_account,
                     amount_in,
                     minimum_amount_out,
+                    swap_mode,
+                    sqrt_price_limit_x64,
                 )
             }
             (DexType::RaydiumClmm, DexAccounts::RaydiumClmm(clmm_accounts)) => {
                 RaydiumClmmSwap::execute_swap(
                     clmm_accounts,
                     derived,
-                    remaining_accounts,
+                    table,
                     payer,
                     token_program,
                     associated_token_program,
@@ -61,13 +71,15 @@ impl DexRouter {
                     user_output_account,
                     amount_in,
                     minimum_amount_out,
+                    swap_mode,
+                    sqrt_price_limit_x64,
                 )
             }
             (DexType::PumpFunBondingCurve, DexAccounts::Pumpfun(pumpfun_accounts)) => {
                 PumpfunSwap::execute_swap(
                     pumpfun_accounts,
                     derived,
-                    remaining_accounts,
+                    table,
                     payer,
                     token_program,
                     associated_token_program,
@@ -76,13 +88,15 @@ impl DexRouter {
                     user_output_account,
                     amount_in,
                     minimum_amount_out,
+                    swap_mode,
+                    sqrt_price_limit_x64,
                 )
             }
             (DexType::PumpSwap, DexAccounts::Pumpswap(pumpswap_accounts)) => {
                 PumpswapSwap::execute_swap(
                     pumpswap_accounts,
                     derived,
-                    remaining_accounts,
+                    table,
                     payer,
                     token_program,
                     associated_token_program,
@@ -91,6 +105,8 @@ impl DexRouter {
                     user_output_account,
                     amount_in,
                     minimum_amount_out,
+                    swap_mode,
+                    sqrt_price_limit_x64,
                 )
             }
             // Mismatched DEX type and accounts
@@ -117,6 +133,54 @@ impl DexRouter {
         Ok(())
     }
     
+    /// 按 DEX 类型将 indices 解析为类型化账户集
+    pub fn resolve_accounts<'info>(
+        resolver: &AccountResolver<'info>,
+        dex_type: DexType,
+        mapping: MappingView,
+    ) -> Result<DexAccounts<'info>> {
+        resolver.validate_indices_for_dex(mapping)?;
+        Ok(match dex_type {
+            DexType::RaydiumCpmm => DexAccounts::RaydiumCpmm(resolver.resolve_raydium_cpmm_by_indices(mapping)?),
+            DexType::RaydiumClmm => DexAccounts::RaydiumClmm(resolver.resolve_raydium_clmm_by_indices(mapping)?),
+            DexType::PumpFunBondingCurve => DexAccounts::Pumpfun(resolver.resolve_pumpfun_by_indices(mapping)?),
+            DexType::PumpSwap => DexAccounts::Pumpswap(resolver.resolve_pumpswap_by_indices(mapping)?),
+        })
+    }
+
+    /// 拆分本步输入：返回 (主腿数量, 各拆分腿数量)
+    ///
+    /// 各腿按 `share_bps` 向下取整，舍入余量归主腿；腿数、份额与主腿非零均在此校验。
+    pub fn split_amount(amount_in: u64, legs: &[SplitLeg]) -> Result<(u64, Vec<u64>)> {
+        require!(legs.len() <= constants::MAX_SPLIT_LEGS, ArbitrageError::InvalidSplitLeg);
+        let mut total_bps: u32 = 0;
+        let mut leg_amounts = Vec::with_capacity(legs.len());
+        for leg in legs {
+            require!(leg.share_bps > 0 && leg.mapping.dex_type == leg.dex_type, ArbitrageError::InvalidSplitLeg);
+            total_bps += leg.share_bps as u32;
+            let amount = (amount_in as u128) * (leg.share_bps as u128) / 10_000u128;
+            let amount = u64::try_from(amount).map_err(|_| ArbitrageError::MathOverflow)?;
+            require!(amount > 0, ArbitrageError::InvalidAmount);
+            leg_amounts.push(amount);
+        }
+        require!(total_bps < 10_000, ArbitrageError::InvalidSplitLeg);
+        let legs_total: u64 = leg_amounts.iter().sum();
+        let primary = amount_in.checked_sub(legs_total).ok_or(ArbitrageError::MathOverflow)?;
+        require!(primary > 0, ArbitrageError::InvalidAmount);
+        Ok((primary, leg_amounts))
+    }
+
+    /// 汇总同一步各腿结果（amount_in/amount_out/fee 分别求和）
+    pub fn aggregate_results(results: &[SwapResult]) -> Result<SwapResult> {
+        let mut total = SwapResult { amount_in: 0, amount_out: 0, fee_amount: 0 };
+        for r in results {
+            total.amount_in = total.amount_in.checked_add(r.amount_in).ok_or(ArbitrageError::MathOverflow)?;
+            total.amount_out = total.amount_out.checked_add(r.amount_out).ok_or(ArbitrageError::MathOverflow)?;
+            total.fee_amount = total.fee_amount.checked_add(r.fee_amount).ok_or(ArbitrageError::MathOverflow)?;
+        }
+        Ok(total)
+    }
+
     /// Gets DEX name for logging
     pub fn get_dex_name(dex_type: DexType) -> &'static str {
         match dex_type {
@@ -2,28 +2,36 @@ use anchor_lang::prelude::*;
 use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
 use anchor_lang::solana_program::program::invoke;
 
-use crate::account_derivation::DerivedAccounts;
-// use crate::account_derivation::ProgramIds;
+use crate::account_derivation::{DerivedAccounts, ProgramIds};
+use crate::account_resolver::validation::expected_program;
 use crate::account_derivation::types::instruction_discriminators::{
     RAYDIUM_CPMM_SWAP_BASE_IN,
+    RAYDIUM_CPMM_SWAP_BASE_OUT,
     RAYDIUM_CLMM_SWAP_V2,
     PUMPFUN_BUY,
     PUMPFUN_SELL,
     PUMPSWAP_BUY,
+    PUMPSWAP_SELL,
 };
 use crate::account_resolver::{
-    RaydiumCpmmAccounts, RaydiumClmmAccounts, PumpfunAccounts, PumpswapAccounts,
+    AccountTable, RaydiumCpmmAccounts, RaydiumClmmAccounts, PumpfunAccounts, PumpswapAccounts,
 };
 use crate::dex_router::types::{DexSwap, SwapResult};
 use crate::errors::ArbitrageError;
-use crate::account_derivation::types::{pda_utils, pda_seeds};
+use crate::account_derivation::types::{pda_utils, pool_layouts};
+use crate::dex_router::types::constants::{RAYDIUM_CLMM_MIN_SQRT_PRICE_X64, RAYDIUM_CLMM_MAX_SQRT_PRICE_X64};
+use crate::quote::math::{pumpfun_buy_within_budget, pumpswap_buy_within_budget};
+use crate::state::{DexType, SwapMode};
 
 // 说明：本文件采用 Anchor+原生 invoke 的混合模式。
 // 作用：按解析出的 DEX 账户，直接构造外部 DEX 指令（discriminator+data+metas），
 // 利用 invoke 执行，前后读取用户输出 ATA 余额差以得到真实 amount_out，供链上滑点校验使用。
+// 成交模式：ExactIn 时 (amount_in, minimum_amount_out) = (输入, 产出下限)；
+// ExactOut 时 minimum_amount_out 为目标产出、amount_in 为输入上限，实际花费取输入 ATA 余额差。
+// Pump 系列 BUY 链上只按 token 数量成交：ExactIn 时按当前曲线/池储备把 amount_in 换算为该数量（amount_in 作为花费上限）。
 
 /// 读取 SPL Token(或Token-2022) 账户的 amount 字段（余额差法）
-fn read_token_amount<'info>(ai: &AccountInfo<'info>) -> Result<u64> {
+pub(crate) fn read_token_amount<'info>(ai: &AccountInfo<'info>) -> Result<u64> {
     // 至少包含 mint(32) + owner(32) + amount(u64) = 72 字节
     if ai.data_len() < 72 {
         return Err(ArbitrageError::InvalidAccount.into());
@@ -34,12 +42,45 @@ fn read_token_amount<'info>(ai: &AccountInfo<'info>) -> Result<u64> {
     Ok(u64::from_le_bytes(amount_bytes))
 }
 
-// 通用工具：在 remaining_accounts 中按 Pubkey 查找 AccountInfo
-fn find_ai<'a>(ais: &'a [AccountInfo<'a>], key: &Pubkey) -> Result<&'a AccountInfo<'a>> {
-    for ai in ais {
-        if ai.key() == *key { return Ok(ai); }
+/// 严格定位 CPI 目标程序账户：program_id 须在 DEX 白名单内（`flex` 亦只扩展到已知的 mainnet/devnet 部署），
+/// 且全局表中 key 完全相等、可执行。不存在“任一可执行账户”之类的回退，
+/// 避免以用户签名权限 CPI 到任意程序。
+fn find_dex_program_ai<'info>(
+    table: &AccountTable<'info>,
+    program_id: &Pubkey,
+    program_ids: &ProgramIds,
+    role: &str,
+) -> Result<&'info AccountInfo<'info>> {
+    if !program_ids.is_dex_program(program_id) {
+        msg!("[{}] program_id={} not in allowlist", role, program_id);
+        return Err(ArbitrageError::UnexpectedProgramId.into());
     }
-    Err(ArbitrageError::AccountNotFound.into())
+    let ai = table.find(program_id).ok_or_else(|| {
+        msg!("[{}] program account {} missing from table", role, program_id);
+        ArbitrageError::ProgramAccountNotFound
+    })?;
+    require!(ai.executable, ArbitrageError::UnexpectedProgramId);
+    Ok(ai)
+}
+
+/// 定位 mint 所属 token 程序账户：mint.owner 须为 Token/Token-2022；入口 token_program 命中则直接复用
+fn find_token_program_ai<'info>(
+    table: &AccountTable<'info>,
+    mint: &AccountInfo<'info>,
+    entry_token_program: &AccountInfo<'info>,
+    program_ids: &ProgramIds,
+) -> Result<AccountInfo<'info>> {
+    let program_id = *mint.owner;
+    if !program_ids.is_token_program(&program_id) {
+        msg!("[TokenProgram] mint={} owner={} is not a token program", mint.key(), program_id);
+        return Err(ArbitrageError::UnexpectedProgramId.into());
+    }
+    if program_id == entry_token_program.key() {
+        return Ok(entry_token_program.clone());
+    }
+    let ai = table.find(&program_id).ok_or(ArbitrageError::ProgramAccountNotFound)?;
+    require!(ai.executable, ArbitrageError::UnexpectedProgramId);
+    Ok(ai.clone())
 }
 
 // 通用工具：读取 token 账户的 mint（前 32 字节）
@@ -53,27 +94,110 @@ fn token_account_mint(ai: &AccountInfo) -> Option<Pubkey> {
     None
 }
 
-// 通用工具：判断某 AccountInfo 是否为指定 owner+mint 的 SPL(Token/2022) 账户
-fn is_token_account_for(owner: &Pubkey, mint: &Pubkey, ai: &AccountInfo) -> bool {
-    if ai.data_len() < 64 { return false; }
-    if let Ok(data) = ai.try_borrow_data() {
-        let mint_bytes = &data[0..32];
-        let owner_bytes = &data[32..64];
-        let mint_pk = Pubkey::new_from_array(mint_bytes.try_into().unwrap_or([0u8;32]));
-        let owner_pk = Pubkey::new_from_array(owner_bytes.try_into().unwrap_or([0u8;32]));
-        return &mint_pk == mint && &owner_pk == owner;
+// 通用工具：按偏移读取账户数据中的 Pubkey
+fn read_pubkey_at(ai: &AccountInfo, offset: usize) -> Option<Pubkey> {
+    let data = ai.try_borrow_data().ok()?;
+    let bytes: [u8; 32] = data.get(offset..offset + 32)?.try_into().ok()?;
+    Some(Pubkey::new_from_array(bytes))
+}
+
+/// CLMM 价格限制：方向由 input_vault_mint 与 PoolState.token_mint_0 比较得出
+/// （zero_for_one 价格下行，限制须位于 (MIN, 当前价)；反向须位于 (当前价, MAX)）。
+/// 客户端传 0 时取协议边界 MIN+1 / MAX-1，即不设限；非 0 时校验落在协议边界内。
+fn clmm_sqrt_price_limit(accounts: &RaydiumClmmAccounts, requested: u128) -> Result<u128> {
+    let token_mint_0 = read_pubkey_at(accounts.pool_state, pool_layouts::CLMM_POOL_TOKEN_MINT_0)
+        .ok_or(ArbitrageError::InvalidAccount)?;
+    let token_mint_1 = read_pubkey_at(accounts.pool_state, pool_layouts::CLMM_POOL_TOKEN_MINT_1)
+        .ok_or(ArbitrageError::InvalidAccount)?;
+    let input_mint = accounts.input_vault_mint.key();
+    let zero_for_one = if input_mint == token_mint_0 {
+        true
+    } else if input_mint == token_mint_1 {
+        false
+    } else {
+        return Err(ArbitrageError::InvalidTokenMint.into());
+    };
+    if requested == 0 {
+        return Ok(if zero_for_one {
+            RAYDIUM_CLMM_MIN_SQRT_PRICE_X64 + 1
+        } else {
+            RAYDIUM_CLMM_MAX_SQRT_PRICE_X64 - 1
+        });
     }
-    false
+    require!(
+        requested > RAYDIUM_CLMM_MIN_SQRT_PRICE_X64 && requested < RAYDIUM_CLMM_MAX_SQRT_PRICE_X64,
+        ArbitrageError::InvalidSqrtPriceLimit
+    );
+    Ok(requested)
+}
+
+// 通用工具：按偏移读取账户数据中的 u64（小端）
+fn read_u64_at(ai: &AccountInfo, offset: usize) -> Option<u64> {
+    let data = ai.try_borrow_data().ok()?;
+    let bytes: [u8; 8] = data.get(offset..offset + 8)?.try_into().ok()?;
+    Some(u64::from_le_bytes(bytes))
 }
 
-// 通用工具：在 remaining_accounts 中查找 owner+mint 对应的 token 账户
-fn find_ata<'a>(ais: &'a [AccountInfo<'a>], owner: &Pubkey, mint: &Pubkey) -> Option<&'a AccountInfo<'a>> {
-    for ai in ais {
-        if is_token_account_for(owner, mint, ai) { return Some(ai); }
+/// PumpFun ExactIn 买入：按 bonding curve 虚拟储备与 Global 费率（协议费 + 创作者费），
+/// 把 SOL 预算换算为 `buy` 的 token 数量（成本不超过预算）
+fn pumpfun_exact_in_token_amount(bonding_curve: &AccountInfo, global: &AccountInfo, sol_budget: u64) -> Result<u64> {
+    let curve_u64 = |offset| read_u64_at(bonding_curve, offset).ok_or(ArbitrageError::InvalidAccount);
+    let fee_bps = read_u64_at(global, pool_layouts::PUMPFUN_GLOBAL_FEE_BASIS_POINTS)
+        .ok_or(ArbitrageError::InvalidAccount)?
+        .saturating_add(read_u64_at(global, pool_layouts::PUMPFUN_GLOBAL_CREATOR_FEE_BASIS_POINTS).unwrap_or(0));
+    let (tokens, _) = pumpfun_buy_within_budget(
+        sol_budget,
+        curve_u64(pool_layouts::PUMPFUN_BONDING_CURVE_VIRTUAL_SOL_RESERVES)?,
+        curve_u64(pool_layouts::PUMPFUN_BONDING_CURVE_VIRTUAL_TOKEN_RESERVES)?,
+        curve_u64(pool_layouts::PUMPFUN_BONDING_CURVE_REAL_TOKEN_RESERVES)?,
+        fee_bps,
+    )?;
+    Ok(tokens)
+}
+
+/// PumpSwap ExactIn 买入：储备取池两侧 vault 余额，lp/protocol/coin_creator 费率取 GlobalConfig
+/// （coin_creator 未设置的池不收创作者费），把 quote 预算换算为 `buy` 的 base 数量（成本不超过预算）
+fn pumpswap_exact_in_base_amount(
+    pool_base_vault: &AccountInfo,
+    pool_quote_vault: &AccountInfo,
+    global_config: &AccountInfo,
+    coin_creator: &Pubkey,
+    quote_budget: u64,
+) -> Result<u64> {
+    let config_u64 = |offset| read_u64_at(global_config, offset).ok_or(ArbitrageError::InvalidAccount);
+    let creator_fee_bps = if *coin_creator == Pubkey::default() {
+        0
+    } else {
+        read_u64_at(global_config, pool_layouts::PUMPSWAP_GLOBAL_COIN_CREATOR_FEE_BASIS_POINTS).unwrap_or(0)
+    };
+    let fee_bps = [
+        config_u64(pool_layouts::PUMPSWAP_GLOBAL_LP_FEE_BASIS_POINTS)?,
+        config_u64(pool_layouts::PUMPSWAP_GLOBAL_PROTOCOL_FEE_BASIS_POINTS)?,
+        creator_fee_bps,
+    ];
+    let (base_out, _) = pumpswap_buy_within_budget(
+        quote_budget,
+        read_token_amount(pool_base_vault)?,
+        read_token_amount(pool_quote_vault)?,
+        &fee_bps,
+    )?;
+    Ok(base_out)
+}
+
+/// Pump 系列 BUY 的成交数量：ExactOut 取目标产出；ExactIn 取预算换算结果，且须满足产出下限
+fn pump_buy_amount(swap_mode: SwapMode, minimum_amount_out: u64, exact_in_amount: impl FnOnce() -> Result<u64>) -> Result<u64> {
+    match swap_mode {
+        SwapMode::ExactOut => Ok(minimum_amount_out),
+        SwapMode::ExactIn => {
+            let amount = exact_in_amount()?;
+            require!(amount > 0, ArbitrageError::ZeroAmountOut);
+            require!(amount >= minimum_amount_out, ArbitrageError::InsufficientOutputAmount);
+            Ok(amount)
+        }
     }
-    None
 }
 
+
 pub struct RaydiumCpmmSwap;
 
 impl<'info> DexSwap<'info> for RaydiumCpmmSwap {
@@ -82,7 +206,7 @@ impl<'info> DexSwap<'info> for RaydiumCpmmSwap {
     fn execute_swap(
         _accounts: Self::Accounts,
         _derived: &DerivedAccounts,
-        _remaining_accounts: &'info [AccountInfo<'info>],
+        _table: &AccountTable<'info>,
         _payer: &AccountInfo<'info>,
         _token_program: &AccountInfo<'info>,
         _associated_token_program: &AccountInfo<'info>,
@@ -91,45 +215,44 @@ impl<'info> DexSwap<'info> for RaydiumCpmmSwap {
         _user_output_account: &AccountInfo<'info>,
         _amount_in: u64,
         _minimum_amount_out: u64,
+        _swap_mode: SwapMode,
+        _sqrt_price_limit_x64: u128,
     ) -> Result<SwapResult> {
+        let pre_in = read_token_amount(_user_input_account)?;
         let pre_out = read_token_amount(_user_output_account)?;
 
         // Resolve Raydium authority from derived fixed addresses, then fetch AccountInfo from remaining_accounts
         let fixed = _derived.get_fixed_addresses().ok_or(ArbitrageError::AccountNotFound)?;
-        let authority_ai = find_ai(_remaining_accounts, &fixed.raydium_cpmm_authority)?;
-        // Program account (required by invoke): derive from state owner to兼容不同网络
-        let cpmm_program_id = *_accounts.amm_config.owner;
-        let cpmm_program_ai = find_ai(_remaining_accounts, &cpmm_program_id)?;
-        // 安全校验：仅要求可执行，具体 program_id 由客户端传入并与状态账户 owner 一致
-        require!(cpmm_program_ai.executable, ArbitrageError::InvalidAccount);
-        msg!("[CPMM] program_id={} ok", cpmm_program_ai.key());
-
-        // Build instruction data: discriminator + amount_in + minimum_amount_out
+        let program_ids = _derived.get_program_ids().ok_or(ArbitrageError::AccountNotFound)?;
+        let authority_ai = _table.get(&fixed.raydium_cpmm_authority)?;
+        // Program account (required by invoke)：按配置 ID 严格定位（`flex` 下可取 amm_config.owner，限已知部署）
+        let cpmm_program_id = expected_program(program_ids, DexType::RaydiumCpmm, _accounts.amm_config.owner);
+        let cpmm_program_ai = find_dex_program_ai(_table, &cpmm_program_id, program_ids, "CPMM")?;
+        if _derived.log_level.verbose() {
+            msg!("[CPMM] program_id={} ok", cpmm_program_ai.key());
+        }
+
+        // Build instruction data:
+        // - ExactIn:  swap_base_input(amount_in, minimum_amount_out)
+        // - ExactOut: swap_base_output(max_amount_in, amount_out)，两者账户顺序一致
         let mut data = Vec::with_capacity(8 + 8 + 8);
-        data.extend_from_slice(RAYDIUM_CPMM_SWAP_BASE_IN);
+        match _swap_mode {
+            SwapMode::ExactIn => data.extend_from_slice(RAYDIUM_CPMM_SWAP_BASE_IN),
+            SwapMode::ExactOut => data.extend_from_slice(RAYDIUM_CPMM_SWAP_BASE_OUT),
+        }
         data.extend_from_slice(&_amount_in.to_le_bytes());
         data.extend_from_slice(&_minimum_amount_out.to_le_bytes());
 
-        // 为输入/输出 mint 分别选择 Token/Token-2022 程序（根据 mint.owner 动态判定）
-        let input_prog_pk = *_accounts.input_mint.owner;
-        let output_prog_pk = *_accounts.output_mint.owner;
-        let token_prog_tokenkeg = _token_program.key();
-        let input_token_prog_ai = if input_prog_pk == token_prog_tokenkeg {
-            _token_program.clone()
-        } else {
-            // 例如 Token-2022 程序需从 remaining_accounts 定位
-            find_ai(_remaining_accounts, &input_prog_pk)?.clone()
-        };
-        let output_token_prog_ai = if output_prog_pk == token_prog_tokenkeg {
-            _token_program.clone()
-        } else {
-            find_ai(_remaining_accounts, &output_prog_pk)?.clone()
-        };
+        // 为输入/输出 mint 分别选择 Token/Token-2022 程序（根据 mint.owner 动态判定；Token-2022 需从 remaining_accounts 定位）
+        let input_token_prog_ai =
+            find_token_program_ai(_table, _accounts.input_mint, _token_program, program_ids)?;
+        let output_token_prog_ai =
+            find_token_program_ai(_table, _accounts.output_mint, _token_program, program_ids)?;
 
         // 根据输入 mint 动态选择 input_vault/output_vault，确保与 input_token_mint/output_token_mint 一致
         let input_mint_key = _accounts.input_mint.key();
-        let token0_mint = token_account_mint(&_accounts.token0_vault).ok_or(ArbitrageError::InvalidTokenMint)?;
-        let token1_mint = token_account_mint(&_accounts.token1_vault).ok_or(ArbitrageError::InvalidTokenMint)?;
+        let token0_mint = token_account_mint(_accounts.token0_vault).ok_or(ArbitrageError::InvalidTokenMint)?;
+        let token1_mint = token_account_mint(_accounts.token1_vault).ok_or(ArbitrageError::InvalidTokenMint)?;
         let (input_vault_ai, output_vault_ai) = if token0_mint == input_mint_key {
             (_accounts.token0_vault.clone(), _accounts.token1_vault.clone())
         } else if token1_mint == input_mint_key {
@@ -181,9 +304,10 @@ impl<'info> DexSwap<'info> for RaydiumCpmmSwap {
         invoke(&ix, &account_infos)?;
 
         // 读取执行后余额并计算真实产出
+        let post_in = read_token_amount(_user_input_account)?;
         let post_out = read_token_amount(_user_output_account)?;
         let amount_out = post_out.saturating_sub(pre_out);
-        Ok(SwapResult { amount_out, fee_amount: 0 })
+        Ok(SwapResult { amount_in: pre_in.saturating_sub(post_in), amount_out, fee_amount: 0 })
     }
 }
 
@@ -195,7 +319,7 @@ impl<'info> DexSwap<'info> for RaydiumClmmSwap {
     fn execute_swap(
         _accounts: Self::Accounts, 
         _derived: &DerivedAccounts,
-        _remaining_accounts: &'info [AccountInfo<'info>],
+        _table: &AccountTable<'info>,
         _payer: &AccountInfo<'info>,
         _token_program: &AccountInfo<'info>,
         _associated_token_program: &AccountInfo<'info>,
@@ -204,15 +328,26 @@ impl<'info> DexSwap<'info> for RaydiumClmmSwap {
         _user_output_account: &AccountInfo<'info>,
         _amount_in: u64,
         _minimum_amount_out: u64,
+        _swap_mode: SwapMode,
+        _sqrt_price_limit_x64: u128,
     ) -> Result<SwapResult> {
+        let pre_in = read_token_amount(_user_input_account)?;
         let pre_out = read_token_amount(_user_output_account)?;
         // Build instruction data
-        let mut data = Vec::with_capacity(8 + 8 + 16 + 1);
+        // swap_v2(amount, other_amount_threshold, sqrt_price_limit, is_base_input)
+        // - ExactIn:  amount=amount_in,  threshold=min_out
+        // - ExactOut: amount=目标产出,    threshold=输入上限
+        let (amount, other_amount_threshold, is_base_input) = match _swap_mode {
+            SwapMode::ExactIn => (_amount_in, _minimum_amount_out, 1u8),
+            SwapMode::ExactOut => (_minimum_amount_out, _amount_in, 0u8),
+        };
+        let sqrt_price_limit_x64 = clmm_sqrt_price_limit(&_accounts, _sqrt_price_limit_x64)?;
+        let mut data = Vec::with_capacity(8 + 8 + 8 + 16 + 1);
         data.extend_from_slice(RAYDIUM_CLMM_SWAP_V2);
-        data.extend_from_slice(&_amount_in.to_le_bytes());
-        data.extend_from_slice(&_minimum_amount_out.to_le_bytes());
-        data.extend_from_slice(&u128::MAX.to_le_bytes()); // sqrt_price_limit
-        data.push(1); // is_base_input
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&other_amount_threshold.to_le_bytes());
+        data.extend_from_slice(&sqrt_price_limit_x64.to_le_bytes());
+        data.push(is_base_input);
 
         // Prefer token program from accounts
         let token_prog_ai = _accounts.token_program;
@@ -233,9 +368,18 @@ impl<'info> DexSwap<'info> for RaydiumClmmSwap {
             AccountMeta::new_readonly(_accounts.output_vault_mint.key(), false),
         ];
 
-        // 安全校验：CLMM 程序账户（来自 indices）必须为可执行程序
-        require!(_accounts.clmm_program.executable, ArbitrageError::InvalidAccount);
-        msg!("[CLMM] program_id={} ok", _accounts.clmm_program.key());
+        // 安全校验：CLMM 程序账户（来自 indices）必须为白名单内的可执行程序
+        let program_ids = _derived.get_program_ids().ok_or(ArbitrageError::AccountNotFound)?;
+        let expected_clmm = expected_program(program_ids, DexType::RaydiumClmm, _accounts.pool_state.owner);
+        require!(
+            program_ids.is_dex_program(&expected_clmm)
+                && _accounts.clmm_program.key() == expected_clmm
+                && _accounts.clmm_program.executable,
+            ArbitrageError::UnexpectedProgramId
+        );
+        if _derived.log_level.verbose() {
+            msg!("[CLMM] program_id={} ok", _accounts.clmm_program.key());
+        }
         // 先构建基础 account_infos
         let mut account_infos: Vec<AccountInfo<'info>> = vec![
             _payer.clone(),
@@ -255,29 +399,20 @@ impl<'info> DexSwap<'info> for RaydiumClmmSwap {
             _accounts.clmm_program.clone(),
         ];
 
-        // 动态补充：从 remaining_accounts 追加与 CLMM 程序相关且不在基础集中的账户（例如 tick arrays/extension）
-        let clmm_program_id = _accounts.clmm_program.key();
-        use std::collections::HashSet as _HashSet;
-        let mut base_keys: _HashSet<Pubkey> = _HashSet::new();
-        for ai in account_infos.iter() { base_keys.insert(ai.key()); }
-        for ai in _remaining_accounts.iter() {
-            if ai.owner != &clmm_program_id { continue; }
-            if base_keys.contains(&ai.key()) { continue; }
-            // 与引擎对齐：tick arrays 与扩展在引擎侧以可写形式传递
+        // 动态账户：仅本步 indices 尾部的 extension/tick arrays（已在校验阶段核对归属本池），按原顺序可写追加
+        for ai in _accounts.dynamic_accounts.iter() {
             metas.push(AccountMeta::new(ai.key(), false));
-            account_infos.push(ai.clone());
-            base_keys.insert(ai.key());
+            account_infos.push((*ai).clone());
         }
 
-        let program_id = clmm_program_id;
-        let ix = Instruction { program_id, accounts: metas.clone(), data };
-
-        // account_infos 已在上方构建并包含动态追加
+        let program_id = _accounts.clmm_program.key();
+        let ix = Instruction { program_id, accounts: metas, data };
 
         invoke(&ix, &account_infos)?;
+        let post_in = read_token_amount(_user_input_account)?;
         let post_out = read_token_amount(_user_output_account)?;
         let amount_out = post_out.saturating_sub(pre_out);
-        Ok(SwapResult { amount_out, fee_amount: 0 })
+        Ok(SwapResult { amount_in: pre_in.saturating_sub(post_in), amount_out, fee_amount: 0 })
     }
 }
 
@@ -289,7 +424,7 @@ impl<'info> DexSwap<'info> for PumpfunSwap {
     fn execute_swap(
         _accounts: Self::Accounts,
         _derived: &DerivedAccounts,
-        _remaining_accounts: &'info [AccountInfo<'info>],
+        _table: &AccountTable<'info>,
         _payer: &AccountInfo<'info>,
         _token_program: &AccountInfo<'info>,
         _associated_token_program: &AccountInfo<'info>,
@@ -298,40 +433,40 @@ impl<'info> DexSwap<'info> for PumpfunSwap {
         _user_output_account: &AccountInfo<'info>,
         _amount_in: u64,
         _minimum_amount_out: u64,
+        _swap_mode: SwapMode,
+        _sqrt_price_limit_x64: u128,
     ) -> Result<SwapResult> {
+        let pre_in = read_token_amount(_user_input_account)?;
         let pre_out = read_token_amount(_user_output_account)?;
 
-        // 先确定 pumpfun 程序ID（来自入参账户 owner）
-        let pumpfun_program_id = *_accounts.bonding_curve.owner;
+        // 先确定 pumpfun 程序ID：按配置 ID 严格定位（`flex` 下可取 bonding_curve.owner，限已知部署）
+        let program_ids = _derived.get_program_ids().ok_or(ArbitrageError::AccountNotFound)?;
+        let pumpfun_program_id = expected_program(program_ids, DexType::PumpFunBondingCurve, _accounts.bonding_curve.owner);
+        let pumpfun_program_ai = find_dex_program_ai(_table, &pumpfun_program_id, program_ids, "PumpFun")?;
         // Fixed addresses（从配置加载；若 devnet 值不同，需由配置覆盖并在全局表提供对应账户）
         let fixed = _derived.get_fixed_addresses().ok_or(ArbitrageError::AccountNotFound)?;
-        // 优先用 PDA 派生并在全局表定位（global 与 event_authority），失败再回退到固定地址
-        let (global_key, _) = Pubkey::find_program_address(&[pda_seeds::PUMPFUN_GLOBAL], &pumpfun_program_id);
-        let global_ai = match find_ai(_remaining_accounts, &global_key) {
-            Ok(ai) => ai,
-            Err(_) => find_ai(_remaining_accounts, &fixed.pumpfun_global_account)?,
-        };
-        let (event_key, _) = Pubkey::find_program_address(&[pda_seeds::PUMPFUN_EVENT_AUTHORITY], &pumpfun_program_id);
-        let event_ai = match find_ai(_remaining_accounts, &event_key) {
-            Ok(ai) => ai,
-            Err(_) => find_ai(_remaining_accounts, &fixed.pumpfun_event_authority)?,
-        };
+        // global 与 event_authority 仅接受由已确认程序派生的 PDA（不再回退到固定地址）
+        // 客户端提供 bump 时仅做 create_program_address 复核，省去 bump 搜索
+        let bumps = _accounts.bumps;
+        let global_key = pda_utils::derive_pumpfun_global(bumps.global, &pumpfun_program_id)?;
+        let global_ai = _table.get(&global_key)?;
+        let event_key = pda_utils::derive_pumpfun_event_authority(bumps.event_authority, &pumpfun_program_id)?;
+        let event_ai = _table.get(&event_key)?;
         // fee_recipient：若可选索引提供则优先，否则回退到固定地址
-        let fee_recipient_ai = if let Some(fr) = _accounts.fee_recipient_opt { fr } else { find_ai(_remaining_accounts, &fixed.pumpfun_fee_recipient)? };
+        let fee_recipient_ai = if let Some(fr) = _accounts.fee_recipient_opt { fr } else { _table.get(&fixed.pumpfun_fee_recipient)? };
 
         // 通过扫描 token 账户数据在全局表定位所需账户
-        let associated_bonding_curve_ai = _remaining_accounts.iter()
-            .find(|ai| is_token_account_for(&_accounts.bonding_curve.key(), &_accounts.mint.key(), ai))
+        let associated_bonding_curve_ai = _table
+            .token_account(&_accounts.bonding_curve.key(), &_accounts.mint.key())
             .ok_or(ArbitrageError::AccountNotFound)?;
-        let associated_user_ai = _remaining_accounts.iter()
-            .find(|ai| is_token_account_for(&_payer.key(), &_accounts.mint.key(), ai))
+        let associated_user_ai = _table
+            .token_account(&_payer.key(), &_accounts.mint.key())
             .ok_or(ArbitrageError::AccountNotFound)?;
 
         // 追加：creator_vault（PDA）使用“传入的 pumpfun 程序”派生，兼容不同网络
         let creator_key = _accounts.creator.key();
-        let expected_creator_vault = pda_utils::derive_pumpfun_creator_vault(&creator_key, &pumpfun_program_id)
-            .map_err(|_| ArbitrageError::AccountNotFound)?;
-        let creator_vault_ai = find_ai(_remaining_accounts, &expected_creator_vault)?;
+        let expected_creator_vault = pda_utils::derive_pumpfun_creator_vault(&creator_key, bumps.creator_vault, &pumpfun_program_id)?;
+        let creator_vault_ai = _table.get(&expected_creator_vault)?;
 
         // 基于输入/输出账户的 mint 与 WSOL 判断买/卖方向
         let in_mint = token_account_mint(_user_input_account).ok_or(ArbitrageError::InvalidAccount)?;
@@ -339,23 +474,32 @@ impl<'info> DexSwap<'info> for PumpfunSwap {
         let wsol = fixed.wrapped_sol_mint;
         let is_buy = in_mint == wsol; // 用 SOL 买代币
         let is_sell = out_mint == wsol; // 卖代币换 SOL
+        // SELL 仅支持精确输入；BUY 按 token 数量成交（见 pump_buy_amount）
+        if is_sell && _swap_mode == SwapMode::ExactOut {
+            return Err(ArbitrageError::UnsupportedSwapMode.into());
+        }
 
-        // volume accumulators（仅买入路径尽力追加，不阻塞）
+        // volume accumulators（仅买入路径；账户不在全局表时不追加，但客户端 bump 错误返回 InvalidPdaBump）
         let (maybe_gva_ai, maybe_uva_ai) = if is_buy {
-            let maybe_gva_key = pda_utils::derive_pumpfun_global_volume_accumulator(&pumpfun_program_id).ok();
-            let maybe_uva_key = pda_utils::derive_pumpfun_user_volume_accumulator(&_payer.key(), &pumpfun_program_id).ok();
+            let gva_key =
+                pda_utils::derive_pumpfun_global_volume_accumulator(bumps.global_volume_accumulator, &pumpfun_program_id)?;
+            let uva_key =
+                pda_utils::derive_pumpfun_user_volume_accumulator(&_payer.key(), bumps.user_volume_accumulator, &pumpfun_program_id)?;
             (
-                if let Some(k) = maybe_gva_key { _remaining_accounts.iter().find(|ai| ai.key() == k) } else { None },
-                if let Some(k) = maybe_uva_key { _remaining_accounts.iter().find(|ai| ai.key() == k) } else { None },
+                _table.find(&gva_key),
+                _table.find(&uva_key),
             )
         } else { (None, None) };
 
         // 构造 data 与账户顺序（严格按 BUY/SELL 对齐）
         let (data, metas): (Vec<u8>, Vec<AccountMeta>) = if is_buy {
-            // BUY: data = [BUY, token_amount, max_sol_cost] → 使用 min_out 作为 token_amount，上界用 amount_in
+            // BUY: data = [BUY, token_amount, max_sol_cost] → token_amount 见 pump_buy_amount，上界用 amount_in
+            let token_amount = pump_buy_amount(_swap_mode, _minimum_amount_out, || {
+                pumpfun_exact_in_token_amount(_accounts.bonding_curve, global_ai, _amount_in)
+            })?;
             let mut data = Vec::with_capacity(8 + 8 + 8);
             data.extend_from_slice(PUMPFUN_BUY);
-            data.extend_from_slice(&_minimum_amount_out.to_le_bytes()); // token_amount
+            data.extend_from_slice(&token_amount.to_le_bytes());        // token_amount
             data.extend_from_slice(&_amount_in.to_le_bytes());          // max_sol_cost
 
             let mut metas = vec![
@@ -426,16 +570,17 @@ impl<'info> DexSwap<'info> for PumpfunSwap {
         if let Some(gva) = maybe_gva_ai { account_infos.push(gva.clone()); }
         if let Some(uva) = maybe_uva_ai { account_infos.push(uva.clone()); }
 
-        // Pumpfun 程序账户（invoke 需要程序 AccountInfo）：仅校验可执行
-        let pumpfun_program_ai = find_ai(_remaining_accounts, &pumpfun_program_id)?;
-        require!(pumpfun_program_ai.executable, ArbitrageError::InvalidAccount);
-        msg!("[PumpFun] program_id={} ok", pumpfun_program_ai.key());
+        // Pumpfun 程序账户（invoke 需要程序 AccountInfo）：已在入口处严格定位
+        if _derived.log_level.verbose() {
+            msg!("[PumpFun] program_id={} ok", pumpfun_program_ai.key());
+        }
         account_infos.push(pumpfun_program_ai.clone());
 
         invoke(&ix, &account_infos)?;
+        let post_in = read_token_amount(_user_input_account)?;
         let post_out = read_token_amount(_user_output_account)?;
         let amount_out = post_out.saturating_sub(pre_out);
-        Ok(SwapResult { amount_out, fee_amount: 0 })
+        Ok(SwapResult { amount_in: pre_in.saturating_sub(post_in), amount_out, fee_amount: 0 })
     }
 }
 
@@ -447,7 +592,7 @@ impl<'info> DexSwap<'info> for PumpswapSwap {
     fn execute_swap(
         _accounts: Self::Accounts,
         _derived: &DerivedAccounts,
-        _remaining_accounts: &'info [AccountInfo<'info>],
+        _table: &AccountTable<'info>,
         _payer: &AccountInfo<'info>,
         _token_program: &AccountInfo<'info>,
         _associated_token_program: &AccountInfo<'info>,
@@ -456,18 +601,23 @@ impl<'info> DexSwap<'info> for PumpswapSwap {
         _user_output_account: &AccountInfo<'info>,
         _amount_in: u64,
         _minimum_amount_out: u64,
+        _swap_mode: SwapMode,
+        _sqrt_price_limit_x64: u128,
     ) -> Result<SwapResult> {
+        let pre_in = read_token_amount(_user_input_account)?;
         let pre_out = read_token_amount(_user_output_account)?;
         let fixed = _derived.get_fixed_addresses().ok_or(ArbitrageError::AccountNotFound)?;
 
-        let mut data = Vec::with_capacity(8 + 8 + 8);
-        data.extend_from_slice(PUMPSWAP_BUY);
-        data.extend_from_slice(&_amount_in.to_le_bytes());
-        data.extend_from_slice(&_minimum_amount_out.to_le_bytes());
-
         // 解析用户与池两侧 ATAs（根据 mint 判定 input/output 的归属）
         let base_mint = _accounts.base_mint.key();
         let quote_mint = _accounts.quote_mint.key();
+
+        // 方向：输入为 base → SELL(base_amount_in, min_quote_out)，仅支持精确输入；
+        // 输入为 quote → BUY(base_amount_out, max_quote_in)，按 base 数量成交（见 pump_buy_amount）
+        let is_sell = token_account_mint(_user_input_account) == Some(base_mint);
+        if is_sell && _swap_mode == SwapMode::ExactOut {
+            return Err(ArbitrageError::UnsupportedSwapMode.into());
+        }
         let (user_base_ata_ai, user_quote_ata_ai) = match (token_account_mint(_user_input_account), token_account_mint(_user_output_account)) {
             (Some(m0), Some(_m1)) => {
                 let a = if m0 == base_mint { _user_input_account } else { _user_output_account };
@@ -478,46 +628,47 @@ impl<'info> DexSwap<'info> for PumpswapSwap {
         };
 
         // 期望地址（用于在 remaining_accounts 中查找）：pool 两侧、fee_recipient_ata、creator_vault_*、event_authority、amm_program
-        let pool_key = _accounts.pool_state.key();
-        
-        // AMM 程序账户：仅校验可执行；兼容不同网络的程序ID
-        let amm_program_ai = match find_ai(_remaining_accounts, &fixed.pumpswap_amm_program) {
-            Ok(ai) => ai,
-            Err(_) => {
-                // 若配置中的固定ID未找到，则在 remaining_accounts 中寻找任一可执行账户作为 AMM 程序（宽松）
-                let mut found: Option<&AccountInfo> = None;
-                for ai in _remaining_accounts.iter() {
-                    if ai.executable { found = Some(ai); break; }
-                }
-                found.ok_or(ArbitrageError::AccountNotFound)?
-            }
-        };
-        require!(amm_program_ai.executable, ArbitrageError::InvalidAccount);
-        // derive global_config 与 event_authority PDA 并在 remaining_accounts 中定位（失败回退 fixed）
-        let amm_pid = amm_program_ai.key();
-        let (global_cfg_key, _) = Pubkey::find_program_address(&[pda_seeds::PUMPSWAP_GLOBAL_CONFIG], &amm_pid);
-        let global_cfg_ai = match find_ai(_remaining_accounts, &global_cfg_key) {
-            Ok(ai) => ai,
-            Err(_) => find_ai(_remaining_accounts, &fixed.pumpswap_global_config)?,
-        };
-        let (event_auth_key, _) = Pubkey::find_program_address(&[pda_seeds::PUMPSWAP_EVENT_AUTHORITY], &amm_pid);
-        let event_authority_ai = match find_ai(_remaining_accounts, &event_auth_key) {
-            Ok(ai) => ai,
-            Err(_) => find_ai(_remaining_accounts, &fixed.pumpswap_event_authority)?,
-        };
+        // AMM 程序账户：按配置 ID 严格定位（`flex` 下可取 pool_state.owner，限已知部署），不回退到任意可执行账户
+        let program_ids = _derived.get_program_ids().ok_or(ArbitrageError::AccountNotFound)?;
+        let amm_pid = expected_program(program_ids, DexType::PumpSwap, _accounts.pool_state.owner);
+        let amm_program_ai = find_dex_program_ai(_table, &amm_pid, program_ids, "PumpSwap")?;
+        // global_config 与 event_authority 仅接受由已确认程序派生的 PDA（不再回退到固定地址）
+        // 客户端提供 bump 时仅做 create_program_address 复核，省去 bump 搜索
+        let bumps = _accounts.bumps;
+        let global_cfg_key = pda_utils::derive_pumpswap_global_config(bumps.global, &amm_pid)?;
+        let global_cfg_ai = _table.get(&global_cfg_key)?;
+        let event_auth_key = pda_utils::derive_pumpswap_event_authority(bumps.event_authority, &amm_pid)?;
+        let event_authority_ai = _table.get(&event_auth_key)?;
         // fee_recipient 及其 ATA：若可选索引提供则优先，否则回退 fixed/扫描
-        let fee_recipient_ai = if let Some(fr) = _accounts.fee_recipient_opt { fr } else { find_ai(_remaining_accounts, &fixed.pumpswap_fee_recipient)? };
+        let fee_recipient_ai = if let Some(fr) = _accounts.fee_recipient_opt { fr } else { _table.get(&fixed.pumpswap_fee_recipient)? };
         let fee_recipient_key = fee_recipient_ai.key();
         // creator_vault 派生
         let creator_key = _accounts.coin_creator.key();
-        let creator_vault_authority_key = crate::account_derivation::types::pda_utils::derive_pumpswap_creator_vault(&creator_key, &amm_pid)
-            .map_err(|_| ArbitrageError::AccountNotFound)?;
-        let creator_vault_authority_ai = find_ai(_remaining_accounts, &creator_vault_authority_key)?;
-        // 查找池/fee/creator 的 ATAs（通过 owner+mint 扫描找到 AccountInfo）
-        let pool_base_ata_ai = find_ata(_remaining_accounts, &pool_key, &base_mint).ok_or(ArbitrageError::AccountNotFound)?;
-        let pool_quote_ata_ai = find_ata(_remaining_accounts, &pool_key, &quote_mint).ok_or(ArbitrageError::AccountNotFound)?;
-        let fee_recipient_ata_ai = if let Some(fra) = _accounts.fee_recipient_ata_opt { fra } else { find_ata(_remaining_accounts, &fee_recipient_key, &quote_mint).ok_or(ArbitrageError::AccountNotFound)? };
-        let creator_vault_ata_ai = find_ata(_remaining_accounts, &creator_vault_authority_key, &quote_mint).ok_or(ArbitrageError::AccountNotFound)?;
+        let creator_vault_authority_key = pda_utils::derive_pumpswap_creator_vault(&creator_key, bumps.creator_vault, &amm_pid)?;
+        let creator_vault_authority_ai = _table.get(&creator_vault_authority_key)?;
+        // 池两侧 vault：以 Pool 状态中记录的 token account 为准（成员关系由状态保证），fee/creator 的 ATAs 仍按 owner+mint 扫描
+        let pool_base_vault_key = read_pubkey_at(_accounts.pool_state, pool_layouts::PUMPSWAP_POOL_BASE_TOKEN_ACCOUNT)
+            .ok_or(ArbitrageError::InvalidPoolData)?;
+        let pool_quote_vault_key = read_pubkey_at(_accounts.pool_state, pool_layouts::PUMPSWAP_POOL_QUOTE_TOKEN_ACCOUNT)
+            .ok_or(ArbitrageError::InvalidPoolData)?;
+        let pool_base_ata_ai = _table.get(&pool_base_vault_key)?;
+        let pool_quote_ata_ai = _table.get(&pool_quote_vault_key)?;
+        let fee_recipient_ata_ai = if let Some(fra) = _accounts.fee_recipient_ata_opt { fra } else { _table.token_account(&fee_recipient_key, &quote_mint).ok_or(ArbitrageError::AccountNotFound)? };
+        let creator_vault_ata_ai = _table.token_account(&creator_vault_authority_key, &quote_mint).ok_or(ArbitrageError::AccountNotFound)?;
+
+        let mut data = Vec::with_capacity(8 + 8 + 8);
+        if is_sell {
+            data.extend_from_slice(PUMPSWAP_SELL);
+            data.extend_from_slice(&_amount_in.to_le_bytes());
+            data.extend_from_slice(&_minimum_amount_out.to_le_bytes());
+        } else {
+            let base_amount_out = pump_buy_amount(_swap_mode, _minimum_amount_out, || {
+                pumpswap_exact_in_base_amount(pool_base_ata_ai, pool_quote_ata_ai, global_cfg_ai, &creator_key, _amount_in)
+            })?;
+            data.extend_from_slice(PUMPSWAP_BUY);
+            data.extend_from_slice(&base_amount_out.to_le_bytes());
+            data.extend_from_slice(&_amount_in.to_le_bytes());
+        }
 
         // 账户 metas（参照引擎构造顺序）
         let metas = vec![
@@ -563,13 +714,16 @@ impl<'info> DexSwap<'info> for PumpswapSwap {
             creator_vault_ata_ai.clone(),
             creator_vault_authority_ai.clone(),
         ];
-        msg!("[PumpSwap] program_id={} ok", amm_program_ai.key());
+        if _derived.log_level.verbose() {
+            msg!("[PumpSwap] program_id={} ok", amm_program_ai.key());
+        }
         let program_id = amm_program_ai.key();
         let ix = Instruction { program_id, accounts: metas, data };
         invoke(&ix, &account_infos)?;
+        let post_in = read_token_amount(_user_input_account)?;
         let post_out = read_token_amount(_user_output_account)?;
         let amount_out = post_out.saturating_sub(pre_out);
-        Ok(SwapResult { amount_out, fee_amount: 0 })
+        Ok(SwapResult { amount_in: pre_in.saturating_sub(post_in), amount_out, fee_amount: 0 })
     }
 }
 
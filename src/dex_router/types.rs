@@ -1,11 +1,13 @@
 use anchor_lang::prelude::*;
-use crate::account_resolver::{RaydiumCpmmAccounts, RaydiumClmmAccounts, PumpfunAccounts, PumpswapAccounts};
+use crate::account_resolver::{AccountTable, RaydiumCpmmAccounts, RaydiumClmmAccounts, PumpfunAccounts, PumpswapAccounts};
 use crate::account_derivation::DerivedAccounts;
-use crate::state::DexType;
+use crate::state::{DexType, SwapMode};
 
-/// Swap result containing output amount and fees
-#[derive(Debug, Clone)]
+/// Swap result containing input/output amounts and fees
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
 pub struct SwapResult {
+    /// 实际花费（输入 ATA 余额差；ExactOut 下可能小于上限）
+    pub amount_in: u64,
     pub amount_out: u64,
     pub fee_amount: u64,
 }
@@ -14,10 +16,12 @@ pub struct SwapResult {
 pub trait DexSwap<'info> {
     type Accounts;
     
+    // 按 CPI 所需账户逐项传参
+    #[allow(clippy::too_many_arguments)]
     fn execute_swap(
         accounts: Self::Accounts,
         derived: &DerivedAccounts,
-        remaining_accounts: &'info [AccountInfo<'info>],
+        table: &AccountTable<'info>,
         payer: &AccountInfo<'info>,
         token_program: &AccountInfo<'info>,
         associated_token_program: &AccountInfo<'info>,
@@ -26,6 +30,8 @@ pub trait DexSwap<'info> {
         user_output_account: &AccountInfo<'info>,
         amount_in: u64,
         minimum_amount_out: u64,
+        swap_mode: SwapMode,
+        sqrt_price_limit_x64: u128,
     ) -> Result<SwapResult>;
 }
 
@@ -41,13 +47,25 @@ pub enum DexAccounts<'info> {
 pub mod constants {
     // CPI/常量集版本号（用于日志与治理）
     pub const CPI_VERSION: u32 = 1;
+    // 单步拆分腿上限（不含主腿）
+    pub const MAX_SPLIT_LEGS: usize = 4;
+    // 单条指令内额外套利环上限（不含主环）
+    pub const MAX_EXTRA_CYCLES: usize = 3;
     // 仅客户端传入账户数量（按 indices 协议）
     // Raydium CPMM：amm_config, pool_state, token0_vault, token1_vault, input_mint, output_mint, observation_state
     pub const RAYDIUM_CPMM_ACCOUNT_COUNT: u8 = 7;
     
-    // Raydium CLMM（本实现按 11 个基础账户解析；tick arrays 动态由客户端另行传递到 remaining_accounts 但不在 indices 中计数）
+    // Raydium CLMM：11 个基础账户 + indices 尾部动态账户（可选 bitmap extension 居首，其后 1..=4 个 tick arrays，按 swap 方向排序）
     pub const RAYDIUM_CLMM_BASE_ACCOUNT_COUNT: u8 = 11;
-    pub const RAYDIUM_CLMM_MAX_TICK_ARRAYS: u8 = 4; // 备用
+    pub const RAYDIUM_CLMM_MAX_TICK_ARRAYS: u8 = 4;
+    pub const RAYDIUM_CLMM_MAX_ACCOUNT_COUNT: u8 = RAYDIUM_CLMM_BASE_ACCOUNT_COUNT + 1 + RAYDIUM_CLMM_MAX_TICK_ARRAYS;
+    // CLMM sqrt_price_x64 协议边界（对应 tick ∈ [-443636, 443636]）
+    pub const RAYDIUM_CLMM_MIN_SQRT_PRICE_X64: u128 = 4295048016;
+    pub const RAYDIUM_CLMM_MAX_SQRT_PRICE_X64: u128 = 79226673521066979257578248091;
+    pub const RAYDIUM_CLMM_MIN_TICK: i32 = -443636;
+    pub const RAYDIUM_CLMM_MAX_TICK: i32 = 443636;
+    // 每个 TickArrayState 含 60 个 tick（跨度 60 × tick_spacing）
+    pub const RAYDIUM_CLMM_TICK_ARRAY_SIZE: i32 = 60;
     
     // PumpFun Bonding（bonding_curve, mint, creator）
     pub const PUMPFUN_ACCOUNT_COUNT: u8 = 3;
@@ -71,8 +89,8 @@ pub fn validate_account_count(dex_type: DexType, actual_count: u8) -> Result<()>
     let expected = get_expected_account_count(dex_type);
     match dex_type {
         DexType::RaydiumClmm => {
-            // CLMM allows variable tick arrays
-            if actual_count < expected || actual_count > expected + constants::RAYDIUM_CLMM_MAX_TICK_ARRAYS {
+            // CLMM allows variable tick arrays (plus an optional bitmap extension)
+            if actual_count <= expected || actual_count > constants::RAYDIUM_CLMM_MAX_ACCOUNT_COUNT {
                 return Err(error!(crate::errors::ArbitrageError::InvalidAccountCount));
             }
         }
@@ -86,4 +86,62 @@ pub enum ArbitrageError {
     
     #[msg("Invalid instruction data")]
     InvalidInstructionData,
+    
+    #[msg("Balance amount modes are only allowed on the first step")]
+    InvalidAmountMode,
+    
+    #[msg("Swap mode not supported for this DEX direction")]
+    UnsupportedSwapMode,
+
+    #[msg("Invalid sqrt price limit")]
+    InvalidSqrtPriceLimit,
+
+    // 池归属/成员校验
+    #[msg("Account owner does not match expected DEX program")]
+    InvalidAccountOwner,
+
+    #[msg("Unexpected program id")]
+    UnexpectedProgramId,
+
+    #[msg("Program account not found in remaining accounts")]
+    ProgramAccountNotFound,
+
+    #[msg("Pool account data is malformed")]
+    InvalidPoolData,
+
+    #[msg("Config account does not match pool state")]
+    PoolConfigMismatch,
+
+    #[msg("Vault does not belong to pool")]
+    PoolVaultMismatch,
+
+    #[msg("Mint does not belong to pool")]
+    PoolMintMismatch,
+
+    #[msg("Observation account does not match pool state")]
+    PoolObservationMismatch,
+
+    #[msg("Creator does not match pool state")]
+    PoolCreatorMismatch,
+
+    #[msg("Pool mints do not match the step's input/output mints")]
+    StepMintMismatch,
+
+    #[msg("Remaining compute units below reserve")]
+    ComputeBudgetExhausted,
+
+    #[msg("Supplied PDA bump does not yield a valid address")]
+    InvalidPdaBump,
+
+    #[msg("Invalid split leg configuration")]
+    InvalidSplitLeg,
+
+    #[msg("Too many arbitrage cycles")]
+    TooManyCycles,
+
+    #[msg("Return data exceeds the runtime limit")]
+    ReturnDataTooLarge,
+
+    #[msg("Tick array does not belong to pool")]
+    PoolTickArrayMismatch,
 }
\ No newline at end of file
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+/// 套利环执行完成（每个环一条；主环 cycle_index = 0，`extra_cycles[i]` 为 i + 1）
+#[event]
+pub struct CycleCompleted {
+    pub cycle_index: u8,
+    /// 首步实际投入
+    pub input_amount: u64,
+    /// 末步实际产出
+    pub final_amount: u64,
+    pub profit: u64,
+}
+
+/// 套利环因预报价不足或报价出错而跳过（未发起任何 CPI）
+#[event]
+pub struct Skipped {
+    pub cycle_index: u8,
+    pub input_amount: u64,
+    /// 预报价的末步产出（任一腿低于其 minimum_amount_out 时为该路径报价的终值，仍视为不足；报价出错时为 0）
+    pub quoted_amount: u64,
+    /// 满足利润要求所需产出（input_amount + min_profit_lamports）
+    pub required_amount: u64,
+}
@@ -0,0 +1,182 @@
+//! 外部 DEX 程序的类型化绑定（由 `idls/*.json` 经 `declare_program!` 在编译期生成）
+//!
+//! 生成内容（以 `pumpfun` 为例）：
+//! - `pumpfun::ID`：IDL 中的程序地址；
+//! - `pumpfun::accounts::{BondingCurve, Global, ..}`：账户结构（实现 `AccountDeserialize` / `Discriminator`）；
+//! - `pumpfun::client::{accounts, args}`：指令账户与参数（`ToAccountMetas` + `InstructionData`），用于链下构建指令；
+//! - `pumpfun::cpi`：CPI 封装。
+//!
+//! 热路径仍按 `instruction_discriminators` / `pool_layouts` 手写字节偏移（避免整结构反序列化的 CU 开销）；
+//! 下方 const 断言把这些常量与 IDL 绑定：IDL 更新导致 discriminator 或零拷贝账户偏移变化时直接编译失败。
+
+// 生成的 CPI 封装按 IDL 指令账户逐项传参
+#![allow(clippy::too_many_arguments)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use crate::account_derivation::types::{instruction_discriminators as ix, pool_layouts as layout};
+
+declare_program!(pumpfun);
+declare_program!(pumpswap);
+declare_program!(raydium_clmm);
+
+/// const 上下文中的字节切片比较
+const fn bytes_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+// 指令 discriminator
+const _: () = assert!(bytes_eq(ix::PUMPFUN_BUY, pumpfun::client::args::Buy::DISCRIMINATOR));
+const _: () = assert!(bytes_eq(ix::PUMPFUN_SELL, pumpfun::client::args::Sell::DISCRIMINATOR));
+const _: () = assert!(bytes_eq(ix::PUMPSWAP_BUY, pumpswap::client::args::Buy::DISCRIMINATOR));
+const _: () = assert!(bytes_eq(ix::PUMPSWAP_SELL, pumpswap::client::args::Sell::DISCRIMINATOR));
+const _: () = assert!(bytes_eq(ix::RAYDIUM_CLMM_SWAP_V2, raydium_clmm::client::args::SwapV2::DISCRIMINATOR));
+
+// CLMM 零拷贝账户（repr(C, packed)）：字段偏移 = 8 字节 discriminator + 结构内偏移
+const DISC_LEN: usize = 8;
+const _: () = {
+    use core::mem::{offset_of, size_of};
+    use raydium_clmm::accounts::{PoolState, TickArrayBitmapExtension, TickArrayState};
+    use raydium_clmm::types::TickState;
+
+    assert!(DISC_LEN + offset_of!(PoolState, amm_config) == layout::CLMM_POOL_AMM_CONFIG);
+    assert!(DISC_LEN + offset_of!(PoolState, token_mint_0) == layout::CLMM_POOL_TOKEN_MINT_0);
+    assert!(DISC_LEN + offset_of!(PoolState, token_mint_1) == layout::CLMM_POOL_TOKEN_MINT_1);
+    assert!(DISC_LEN + offset_of!(PoolState, token_vault_0) == layout::CLMM_POOL_TOKEN_VAULT_0);
+    assert!(DISC_LEN + offset_of!(PoolState, token_vault_1) == layout::CLMM_POOL_TOKEN_VAULT_1);
+    assert!(DISC_LEN + offset_of!(PoolState, observation_key) == layout::CLMM_POOL_OBSERVATION_KEY);
+    assert!(DISC_LEN + offset_of!(PoolState, tick_spacing) == layout::CLMM_POOL_TICK_SPACING);
+    assert!(DISC_LEN + offset_of!(PoolState, liquidity) == layout::CLMM_POOL_LIQUIDITY);
+    assert!(DISC_LEN + offset_of!(PoolState, sqrt_price_x64) == layout::CLMM_POOL_SQRT_PRICE_X64);
+    assert!(DISC_LEN + offset_of!(PoolState, tick_current) == layout::CLMM_POOL_TICK_CURRENT);
+    assert!(DISC_LEN + offset_of!(PoolState, status) == layout::CLMM_POOL_STATUS);
+    assert!(DISC_LEN + offset_of!(PoolState, tick_array_bitmap) == layout::CLMM_POOL_TICK_ARRAY_BITMAP);
+
+    assert!(DISC_LEN + offset_of!(TickArrayState, pool_id) == layout::CLMM_TICK_ARRAY_POOL_ID);
+    assert!(DISC_LEN + offset_of!(TickArrayState, start_tick_index) == layout::CLMM_TICK_ARRAY_START_TICK_INDEX);
+    assert!(DISC_LEN + offset_of!(TickArrayState, ticks) == layout::CLMM_TICK_ARRAY_TICKS);
+    assert!(DISC_LEN + size_of::<TickArrayState>() >= layout::CLMM_TICK_ARRAY_MIN_LEN);
+
+    assert!(DISC_LEN + offset_of!(TickArrayBitmapExtension, pool_id) == layout::CLMM_BITMAP_EXTENSION_POOL_ID);
+    assert!(DISC_LEN + offset_of!(TickArrayBitmapExtension, positive_tick_array_bitmap) == layout::CLMM_BITMAP_EXTENSION_POSITIVE);
+    assert!(DISC_LEN + offset_of!(TickArrayBitmapExtension, negative_tick_array_bitmap) == layout::CLMM_BITMAP_EXTENSION_NEGATIVE);
+    assert!(DISC_LEN + size_of::<TickArrayBitmapExtension>() == layout::CLMM_BITMAP_EXTENSION_MIN_LEN);
+
+    assert!(size_of::<TickState>() == layout::CLMM_TICK_STATE_LEN);
+    assert!(offset_of!(TickState, liquidity_net) == layout::CLMM_TICK_STATE_LIQUIDITY_NET);
+    assert!(offset_of!(TickState, liquidity_gross) == layout::CLMM_TICK_STATE_LIQUIDITY_GROSS);
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quote::state::*;
+    use anchor_lang::AccountDeserialize;
+
+    /// 以 IDL discriminator 开头、其余为零的账户数据
+    fn account_data(discriminator: &[u8], len: usize) -> Vec<u8> {
+        let mut data = vec![0u8; len];
+        data[..DISC_LEN].copy_from_slice(discriminator);
+        data
+    }
+
+    fn put(data: &mut [u8], offset: usize, bytes: &[u8]) {
+        data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    #[test]
+    fn instruction_discriminators_match_idl() {
+        assert_eq!(ix::PUMPFUN_BUY, pumpfun::client::args::Buy::DISCRIMINATOR);
+        assert_eq!(ix::PUMPFUN_SELL, pumpfun::client::args::Sell::DISCRIMINATOR);
+        assert_eq!(ix::PUMPSWAP_BUY, pumpswap::client::args::Buy::DISCRIMINATOR);
+        assert_eq!(ix::PUMPSWAP_SELL, pumpswap::client::args::Sell::DISCRIMINATOR);
+        assert_eq!(ix::RAYDIUM_CLMM_SWAP_V2, raydium_clmm::client::args::SwapV2::DISCRIMINATOR);
+    }
+
+    // Borsh 账户无法在编译期求偏移：按手写偏移写入字段，再用 IDL 结构反序列化比对
+    #[test]
+    fn pumpfun_layouts_match_idl() {
+        let mut data = account_data(pumpfun::accounts::BondingCurve::DISCRIMINATOR, layout::PUMPFUN_BONDING_CURVE_MIN_LEN);
+        put(&mut data, layout::PUMPFUN_BONDING_CURVE_VIRTUAL_TOKEN_RESERVES, &1u64.to_le_bytes());
+        put(&mut data, layout::PUMPFUN_BONDING_CURVE_VIRTUAL_SOL_RESERVES, &2u64.to_le_bytes());
+        put(&mut data, layout::PUMPFUN_BONDING_CURVE_REAL_TOKEN_RESERVES, &3u64.to_le_bytes());
+        put(&mut data, layout::PUMPFUN_BONDING_CURVE_REAL_SOL_RESERVES, &4u64.to_le_bytes());
+        put(&mut data, layout::PUMPFUN_BONDING_CURVE_TOKEN_TOTAL_SUPPLY, &5u64.to_le_bytes());
+        put(&mut data, layout::PUMPFUN_BONDING_CURVE_COMPLETE, &[1]);
+        put(&mut data, layout::PUMPFUN_BONDING_CURVE_CREATOR, &[6u8; 32]);
+        let idl = pumpfun::accounts::BondingCurve::try_deserialize(&mut &data[..]).unwrap();
+        let ours = PumpFunBondingCurve::from_account_data(&data).unwrap();
+        assert_eq!(idl.virtual_token_reserves, ours.virtual_token_reserves);
+        assert_eq!(idl.virtual_sol_reserves, ours.virtual_sol_reserves);
+        assert_eq!(idl.real_token_reserves, ours.real_token_reserves);
+        assert_eq!(idl.real_sol_reserves, ours.real_sol_reserves);
+        assert_eq!(idl.token_total_supply, ours.token_total_supply);
+        assert_eq!(idl.complete, ours.complete);
+        assert_eq!(idl.creator, ours.creator);
+
+        let idl_len = DISC_LEN + pumpfun::accounts::Global::default().try_to_vec().unwrap().len();
+        let mut data = account_data(pumpfun::accounts::Global::DISCRIMINATOR, idl_len);
+        put(&mut data, layout::PUMPFUN_GLOBAL_FEE_RECIPIENT, &[7u8; 32]);
+        put(&mut data, layout::PUMPFUN_GLOBAL_FEE_BASIS_POINTS, &95u64.to_le_bytes());
+        put(&mut data, layout::PUMPFUN_GLOBAL_CREATOR_FEE_BASIS_POINTS, &5u64.to_le_bytes());
+        let idl = pumpfun::accounts::Global::try_deserialize(&mut &data[..]).unwrap();
+        let ours = PumpFunGlobal::from_account_data(&data).unwrap();
+        assert_eq!(idl.fee_recipient, ours.fee_recipient);
+        assert_eq!(idl.fee_basis_points, ours.fee_basis_points);
+        assert_eq!(idl.creator_fee_basis_points, ours.creator_fee_basis_points);
+    }
+
+    #[test]
+    fn pumpswap_layouts_match_idl() {
+        let mut data = account_data(pumpswap::accounts::Pool::DISCRIMINATOR, layout::PUMPSWAP_POOL_MIN_LEN);
+        put(&mut data, layout::PUMPSWAP_POOL_BASE_MINT, &[1u8; 32]);
+        put(&mut data, layout::PUMPSWAP_POOL_QUOTE_MINT, &[2u8; 32]);
+        put(&mut data, layout::PUMPSWAP_POOL_LP_MINT, &[3u8; 32]);
+        put(&mut data, layout::PUMPSWAP_POOL_BASE_TOKEN_ACCOUNT, &[4u8; 32]);
+        put(&mut data, layout::PUMPSWAP_POOL_QUOTE_TOKEN_ACCOUNT, &[5u8; 32]);
+        put(&mut data, layout::PUMPSWAP_POOL_LP_SUPPLY, &6u64.to_le_bytes());
+        put(&mut data, layout::PUMPSWAP_POOL_COIN_CREATOR, &[7u8; 32]);
+        let idl = pumpswap::accounts::Pool::try_deserialize(&mut &data[..]).unwrap();
+        let ours = PumpSwapPool::from_account_data(&data).unwrap();
+        assert_eq!(idl.base_mint, ours.base_mint);
+        assert_eq!(idl.quote_mint, ours.quote_mint);
+        assert_eq!(idl.lp_mint, ours.lp_mint);
+        assert_eq!(idl.pool_base_token_account, ours.pool_base_token_account);
+        assert_eq!(idl.pool_quote_token_account, ours.pool_quote_token_account);
+        assert_eq!(idl.lp_supply, ours.lp_supply);
+        assert_eq!(idl.coin_creator, ours.coin_creator);
+
+        let idl_len = DISC_LEN + pumpswap::accounts::GlobalConfig::default().try_to_vec().unwrap().len();
+        let mut data = account_data(pumpswap::accounts::GlobalConfig::DISCRIMINATOR, idl_len);
+        put(&mut data, layout::PUMPSWAP_GLOBAL_LP_FEE_BASIS_POINTS, &20u64.to_le_bytes());
+        put(&mut data, layout::PUMPSWAP_GLOBAL_PROTOCOL_FEE_BASIS_POINTS, &5u64.to_le_bytes());
+        put(&mut data, layout::PUMPSWAP_GLOBAL_COIN_CREATOR_FEE_BASIS_POINTS, &3u64.to_le_bytes());
+        let idl = pumpswap::accounts::GlobalConfig::try_deserialize(&mut &data[..]).unwrap();
+        let ours = PumpSwapGlobalConfig::from_account_data(&data).unwrap();
+        assert_eq!(idl.lp_fee_basis_points, ours.lp_fee_basis_points);
+        assert_eq!(idl.protocol_fee_basis_points, ours.protocol_fee_basis_points);
+        assert_eq!(idl.coin_creator_fee_basis_points, ours.coin_creator_fee_basis_points);
+    }
+
+    #[test]
+    fn clmm_amm_config_layout_matches_idl() {
+        let idl_len = DISC_LEN + raydium_clmm::accounts::AmmConfig::default().try_to_vec().unwrap().len();
+        let mut data = account_data(raydium_clmm::accounts::AmmConfig::DISCRIMINATOR, idl_len);
+        put(&mut data, layout::CLMM_CONFIG_TRADE_FEE_RATE, &2500u32.to_le_bytes());
+        put(&mut data, layout::CLMM_CONFIG_TICK_SPACING, &60u16.to_le_bytes());
+        let idl = raydium_clmm::accounts::AmmConfig::try_deserialize(&mut &data[..]).unwrap();
+        let ours = ClmmAmmConfig::from_account_data(&data).unwrap();
+        assert_eq!(idl.trade_fee_rate, ours.trade_fee_rate);
+        assert_eq!(idl.tick_spacing, ours.tick_spacing);
+    }
+}
@@ -1,9 +1,27 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::compute_units::sol_remaining_compute_units;
+use anchor_lang::solana_program::program::{set_return_data, MAX_RETURN_DATA};
 use anchor_spl::token::Token;
-use crate::state::{ArbitrageParams, PathAccountMappingV2, DexType};
-use crate::account_resolver::AccountResolver;
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022_extensions::transfer_fee::{
+    harvest_withheld_tokens_to_mint, HarvestWithheldTokensToMint,
+};
+use anchor_spl::token_interface::{close_account, CloseAccount};
+use spl_token_2022::extension::{
+    transfer_fee::TransferFeeAmount, BaseStateWithExtensions, StateWithExtensions,
+};
+use crate::state::{
+    AmountMode, ArbitrageOutcome, ArbitrageParams, ArbitrageParamsV3, CycleOutcome, DexType, LogLevel, MappingView, PathAccountMappingV3, PathStepV3,
+    ProtocolVersion, StepView, SwapMode,
+};
+use crate::events::{CycleCompleted, Skipped};
+use crate::quote::quote_path;
+use crate::compact::CompactParams;
+use crate::account_resolver::{AccountResolver, AccountTable, validate_dex_accounts, validate_step_mints};
 use crate::account_derivation::{DerivedAccounts, ProgramIds};
-use crate::dex_router::{DexRouter, DexAccounts};
+use crate::dex_router::{DexRouter, SwapResult};
+use crate::dex_router::types::constants::MAX_EXTRA_CYCLES;
+use crate::dex_router::swaps::read_token_amount;
 use crate::errors::ArbitrageError;
 use anchor_lang::solana_program as _; // ensure linkage
 
@@ -13,9 +31,14 @@ use anchor_lang::solana_program as _; // ensure linkage
 // - 按客户端提供的全局账户表 + indices 解析每步 DEX 账户；
 // - 每步读取真实 amount_out（余额差）、校验 minimum_amount_out；
 // - 终局校验 min_profit_lamports，原子回滚失败路径；
+// - 可先按链上预报价决定是否执行主环（不足则不发起 CPI、发 Skipped 事件后返回 Ok）；
+// - 可在主环之后执行若干独立套利环（共享账户表，按环校验利润，可按链上预报价跳过并发事件）；
 // - Token/Token-2022 的用户 ATA 做 owner/mint/program 快速校验；
+// - 每步 DEX 账户做池归属（owner == ProgramIds）与成员（vault/mint/config 对齐池状态）校验，池两侧 mint 须与步声明一致；
+// - 成功时以 `set_return_data` 写入 Borsh `ArbitrageOutcome`（逐环逐步金额、利润、CU），供 CPI 调用方/模拟器读取；
 // - 打印 CPI_VERSION 和 remaining_accounts 快照（len/hash）用于双端排错；
-// - 调用前统一初始化 DerivedAccounts（固定地址/系统程序）。
+// - 调用前统一初始化 DerivedAccounts（固定地址/系统程序）；
+// - 可选收尾：关闭余额为 0 的中间 mint ATA 回收租金（Token-2022 先归集 withheld 手续费）。
 // ==============================================================================================
 
 #[derive(Accounts)]
@@ -27,30 +50,344 @@ pub struct ExecuteArbitrage<'info> {
     pub system_program: Program<'info, System>,
 }
 
+/// V2 入口（Borsh `ArbitrageParams`，CPI_VERSION 1 布局）：单环，V3 新增的逐步与整笔选项一律取默认
 pub fn execute_arbitrage<'info>(
     ctx: Context<'_, '_, 'info, 'info, ExecuteArbitrage<'info>>,
     params: ArbitrageParams,
 ) -> Result<()> {
-    // 1. 验证参数
-    require!(!params.path_steps.is_empty(), ArbitrageError::PathTooShort);
-    require!(params.path_steps.len() <= 10, ArbitrageError::PathTooLong);
-    require!(params.input_amount > 0, ArbitrageError::InvalidAmount);
-    require!(params.account_mappings_v2.len() == params.path_steps.len(), ArbitrageError::InvalidAccountCount);
+    let cycle = CycleView::from_v2(&params)?;
+    run_cycles(&ctx, &[cycle], ExecutionOptions::default())
+}
+
+/// `execute_arbitrage_v3` 的 discriminator（`sha256("global:execute_arbitrage_v3")[..8]`）
+pub const EXECUTE_ARBITRAGE_V3_DISCRIMINATOR: [u8; 8] = [0xf4, 0x2d, 0x96, 0x36, 0x6e, 0x5c, 0x0c, 0xdc];
+
+/// `execute_arbitrage_v3` 的原始入口（由 `fallback` 调用）：直接读取指令数据切片
+/// （8 字节 discriminator + u32 长度 + payload，与 IDL 中 `payload: bytes` 的编码一致），不经 Borsh `Vec<u8>` 复制
+pub fn execute_arbitrage_v3_raw<'info>(
+    program_id: &Pubkey,
+    accounts: &'info [AccountInfo<'info>],
+    data: &[u8],
+) -> Result<()> {
+    let body = data
+        .strip_prefix(&EXECUTE_ARBITRAGE_V3_DISCRIMINATOR)
+        .ok_or(anchor_lang::error::ErrorCode::InstructionFallbackNotFound)?;
+    let (len, payload) = body.split_first_chunk::<4>().ok_or(ArbitrageError::InvalidInstructionData)?;
+    require!(u32::from_le_bytes(*len) as usize == payload.len(), ArbitrageError::InvalidInstructionData);
+
+    let mut remaining_accounts = accounts;
+    let mut bumps = <ExecuteArbitrage as anchor_lang::Bumps>::Bumps::default();
+    let mut reallocs = std::collections::BTreeSet::new();
+    let mut ix_accounts =
+        ExecuteArbitrage::try_accounts(program_id, &mut remaining_accounts, data, &mut bumps, &mut reallocs)?;
+    execute_arbitrage_v3(Context::new(program_id, &mut ix_accounts, remaining_accounts, bumps), payload)?;
+    ix_accounts.exit(program_id)
+}
+
+/// 版本化入口：`payload` 首字节为协议标签（见 `ProtocolVersion`），按版本解码后走同一执行路径
+pub fn execute_arbitrage_v3<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ExecuteArbitrage<'info>>,
+    payload: &[u8],
+) -> Result<()> {
+    with_payload_cycles(ctx.remaining_accounts, payload, |cycles, options| run_cycles(&ctx, cycles, options))
+}
+
+/// 按协议标签解码 `payload`，以借用参数的环视图与执行选项调用 `f`（执行与只读报价共用）
+///
+/// V2 与 V3 的 Borsh 参数体须恰好占满标签之后的字节；紧凑编码各步按需解码、借用 `payload`。
+/// 未知标签返回 `InvalidInstructionData`。
+pub(crate) fn with_payload_cycles<R>(
+    remaining_accounts: &[AccountInfo],
+    payload: &[u8],
+    f: impl FnOnce(&[CycleView], ExecutionOptions) -> Result<R>,
+) -> Result<R> {
+    let (&tag, body) = payload.split_first().ok_or(ArbitrageError::InvalidInstructionData)?;
+    match ProtocolVersion::from_tag(tag) {
+        Some(ProtocolVersion::V2) => {
+            let params = ArbitrageParams::try_from_slice(body).map_err(|_| error!(ArbitrageError::InvalidInstructionData))?;
+            f(&[CycleView::from_v2(&params)?], ExecutionOptions::default())
+        }
+        Some(ProtocolVersion::V3) => {
+            let params = ArbitrageParamsV3::try_from_slice(body).map_err(|_| error!(ArbitrageError::InvalidInstructionData))?;
+            // 主环 + `extra_cycles`
+            require!(params.extra_cycles.len() <= MAX_EXTRA_CYCLES, ArbitrageError::TooManyCycles);
+            let mut cycles = Vec::with_capacity(1 + params.extra_cycles.len());
+            cycles.push(CycleView::from_v3(
+                params.input_amount,
+                params.min_profit_lamports,
+                params.quote_before_execute,
+                &params.path_steps,
+                &params.account_mappings,
+            )?);
+            for cycle in &params.extra_cycles {
+                cycles.push(CycleView::from_v3(
+                    cycle.input_amount,
+                    cycle.min_profit_lamports,
+                    cycle.skip_if_unprofitable,
+                    &cycle.path_steps,
+                    &cycle.account_mappings,
+                )?);
+            }
+            let options = ExecutionOptions {
+                log_level: params.log_level.unwrap_or_default(),
+                min_cu_reserve: params.min_cu_reserve,
+                close_intermediate_atas: params.close_intermediate_atas,
+            };
+            f(&cycles, options)
+        }
+        Some(ProtocolVersion::V3Compact) => {
+            let params = CompactParams::parse(payload)?;
+            // V3 紧凑编码为单环
+            let cycle = CycleView {
+                input_amount: params.input_amount,
+                min_profit_lamports: params.min_profit_lamports,
+                skip_if_unprofitable: params.quote_before_execute,
+                steps: params
+                    .steps()
+                    .map(|step| step?.view(remaining_accounts))
+                    .collect::<Result<Vec<_>>>()?,
+            };
+            let options = ExecutionOptions {
+                log_level: params.log_level.unwrap_or_default(),
+                min_cu_reserve: params.min_cu_reserve,
+                close_intermediate_atas: params.close_intermediate_atas,
+            };
+            f(&[cycle], options)
+        }
+        None => {
+            msg!("[PROTOCOL] unsupported version tag={}", tag);
+            Err(ArbitrageError::InvalidInstructionData.into())
+        }
+    }
+}
+
+/// 一个套利环的执行视图（步骤借用 V2/V3 参数或紧凑编码的指令数据）
+pub(crate) struct CycleView<'a> {
+    pub input_amount: u64,
+    pub min_profit_lamports: u64,
+    /// 执行前预报价，不足则跳过本环（主环取 `quote_before_execute`）
+    pub skip_if_unprofitable: bool,
+    pub steps: Vec<StepView<'a>>,
+}
+
+impl<'a> CycleView<'a> {
+    fn from_v2(params: &'a ArbitrageParams) -> Result<Self> {
+        require!(params.account_mappings_v2.len() == params.path_steps.len(), ArbitrageError::InvalidAccountCount);
+        Ok(Self {
+            input_amount: params.input_amount,
+            min_profit_lamports: params.min_profit_lamports,
+            skip_if_unprofitable: false,
+            steps: params.path_steps.iter().zip(&params.account_mappings_v2).map(|(step, mapping)| step.view(mapping)).collect(),
+        })
+    }
+
+    fn from_v3(
+        input_amount: u64,
+        min_profit_lamports: u64,
+        skip_if_unprofitable: bool,
+        path_steps: &'a [PathStepV3],
+        mappings: &'a [PathAccountMappingV3],
+    ) -> Result<Self> {
+        require!(mappings.len() == path_steps.len(), ArbitrageError::InvalidAccountCount);
+        Ok(Self {
+            input_amount,
+            min_profit_lamports,
+            skip_if_unprofitable,
+            steps: path_steps.iter().zip(mappings).map(|(step, mapping)| step.view(mapping)).collect(),
+        })
+    }
+}
+
+/// 入口无关的执行选项（V2 入口取默认）
+#[derive(Clone, Copy, Default)]
+pub(crate) struct ExecutionOptions {
+    pub log_level: LogLevel,
+    pub min_cu_reserve: Option<u64>,
+    pub close_intermediate_atas: bool,
+}
+
+/// 入口共用的准备阶段产物（执行与只读 `quote_path` 共用）
+pub(crate) struct ExecutionContext<'info> {
+    pub program_ids: ProgramIds,
+    pub table: AccountTable<'info>,
+    pub derived_accounts: DerivedAccounts,
+}
+
+/// 各入口共用的执行主体：准备阶段 → 逐环执行（可按预报价跳过）→ 可选收尾
+fn run_cycles<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, ExecuteArbitrage<'info>>,
+    cycles: &[CycleView],
+    options: ExecutionOptions,
+) -> Result<()> {
+    let cu_at_entry = sol_remaining_compute_units();
+    // 1-3. 参数/环境校验、全局账户表索引、账户推导缓存
+    let log_level = options.log_level;
+    let ExecutionContext { program_ids, table, derived_accounts } = prepare_execution(ctx, cycles, log_level)?;
+    let mut outcome = ArbitrageOutcome {
+        cycles: Vec::with_capacity(cycles.len()),
+        compute_units_used: 0,
+        steps_truncated: false,
+    };
+    
+    // 4. 依次执行各套利环：各环（主环取 `quote_before_execute`）可按预报价跳过；执行中失败即整笔回滚
+    for (cycle_index, cycle) in cycles.iter().enumerate() {
+        let cycle_index = cycle_index as u8;
+        let CycleView { input_amount, min_profit_lamports, skip_if_unprofitable, ref steps } = *cycle;
+        if skip_if_unprofitable {
+            let quote = match quote_path(
+                ctx.remaining_accounts,
+                &table,
+                &derived_accounts,
+                &program_ids,
+                input_amount,
+                steps,
+            ) {
+                Ok(quote) => quote,
+                // 报价出错（账户/池状态无法解析等）同样跳过本环，而非整笔回滚
+                Err(err) => {
+                    let required_amount = input_amount.saturating_add(min_profit_lamports);
+                    if log_level.summary() {
+                        msg!(
+                            "Cycle {} skipped: quote_error={} quoted=0 required={}",
+                            cycle_index,
+                            error_name(&err),
+                            required_amount
+                        );
+                    }
+                    emit!(Skipped {
+                        cycle_index,
+                        input_amount,
+                        quoted_amount: 0,
+                        required_amount,
+                    });
+                    outcome.cycles.push(CycleOutcome::skipped(cycle_index, input_amount));
+                    continue;
+                }
+            };
+            match quote {
+                Some(quote) if !quote.is_profitable(min_profit_lamports) => {
+                    let required_amount = quote.required_amount(min_profit_lamports);
+                    if log_level.summary() {
+                        msg!(
+                            "Cycle {} skipped: quoted={} required={} min_out_met={}",
+                            cycle_index,
+                            quote.final_amount,
+                            required_amount,
+                            quote.min_out_met
+                        );
+                    }
+                    emit!(Skipped {
+                        cycle_index,
+                        input_amount: quote.initial_amount,
+                        quoted_amount: quote.final_amount,
+                        required_amount,
+                    });
+                    outcome.cycles.push(CycleOutcome::skipped(cycle_index, quote.initial_amount));
+                    continue;
+                }
+                Some(_) => {}
+                None => {
+                    if log_level.summary() {
+                        msg!("Cycle {} not quotable on-chain, executing", cycle_index);
+                    }
+                }
+            }
+        }
+
+        let cu_before_cycle = sol_remaining_compute_units();
+        let (initial_amount, final_amount, steps) =
+            execute_cycle(ctx, &table, &derived_accounts, &program_ids, &options, cycle)?;
+
+        // 5. 验证本环利润
+        require!(
+            final_amount >= initial_amount.saturating_add(min_profit_lamports),
+            ArbitrageError::InsufficientProfit
+        );
+        let profit = final_amount - initial_amount;
+        if log_level.summary() {
+            msg!("Arbitrage cycle {} completed successfully. Profit: {}", cycle_index, profit);
+        }
+        emit!(CycleCompleted {
+            cycle_index,
+            input_amount: initial_amount,
+            final_amount,
+            profit,
+        });
+        outcome.cycles.push(CycleOutcome {
+            cycle_index,
+            skipped: false,
+            initial_amount,
+            final_amount,
+            profit,
+            compute_units_used: cu_before_cycle.saturating_sub(sol_remaining_compute_units()),
+            steps,
+        });
+    }
+
+    // 6. 可选收尾：关闭中间 mint 的临时 ATA
+    if options.close_intermediate_atas {
+        close_intermediate_token_accounts(
+            cycles,
+            &derived_accounts,
+            &table,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &program_ids,
+            log_level,
+        )?;
+    }
+
+    // 7. 结构化返回数据（放在所有 CPI 之后，避免被下游程序覆盖）
+    outcome.compute_units_used = cu_at_entry.saturating_sub(sol_remaining_compute_units());
+    set_outcome_return_data(&mut outcome)?;
+    
+    Ok(())
+}
+
+/// 写入 `ArbitrageOutcome` 返回数据；超出运行时上限时去掉逐步明细（仅保留各环汇总）并置 `steps_truncated`
+fn set_outcome_return_data(outcome: &mut ArbitrageOutcome) -> Result<()> {
+    let mut data = outcome.try_to_vec()?;
+    if data.len() > MAX_RETURN_DATA {
+        for cycle in outcome.cycles.iter_mut() {
+            cycle.steps.clear();
+        }
+        outcome.steps_truncated = true;
+        data = outcome.try_to_vec()?;
+    }
+    set_return_data(&data);
+    Ok(())
+}
+
+/// 参数/环境校验 → 全局账户表一次性建索引 → 为所有环推导账户缓存
+pub(crate) fn prepare_execution<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, ExecuteArbitrage<'info>>,
+    cycles: &[CycleView],
+    log_level: LogLevel,
+) -> Result<ExecutionContext<'info>> {
+    // 1. 验证参数（主环 + 额外环）
+    require!(cycles.len() <= 1 + MAX_EXTRA_CYCLES, ArbitrageError::TooManyCycles);
+    for cycle in cycles {
+        validate_cycle_params(cycle)?;
+    }
     
     // 2. 初始化程序ID配置
     let program_ids = ProgramIds::default(); // 与客户端常量保持一致
-    msg!("[CPI_VERSION] {}", crate::dex_router::types::constants::CPI_VERSION);
-    msg!(
-        "[PROGRAM_IDS] token={} token22={} assoc_token={} system={} cpmm={} clmm={} pumpfun={} pumpswap={}",
-        program_ids.token_program,
-        program_ids.token_2022_program,
-        program_ids.associated_token_program,
-        program_ids.system_program,
-        program_ids.raydium_cpmm,
-        program_ids.raydium_clmm,
-        program_ids.pumpfun,
-        program_ids.pumpswap
-    );
+    if log_level.summary() {
+        msg!("[CPI_VERSION] {}", crate::dex_router::types::constants::CPI_VERSION);
+    }
+    if log_level.verbose() {
+        msg!(
+            "[PROGRAM_IDS] token={} token22={} assoc_token={} system={} cpmm={} clmm={} pumpfun={} pumpswap={}",
+            program_ids.token_program,
+            program_ids.token_2022_program,
+            program_ids.associated_token_program,
+            program_ids.system_program,
+            program_ids.raydium_cpmm,
+            program_ids.raydium_clmm,
+            program_ids.pumpfun,
+            program_ids.pumpswap
+        );
+    }
     // 基础系统程序一致性校验（不影响 Token/Token-2022 选择）
     require!(
         ctx.accounts.associated_token_program.key() == program_ids.associated_token_program,
@@ -61,90 +398,68 @@ pub fn execute_arbitrage<'info>(
         ArbitrageError::InvalidAccount
     );
     
-    // 3. 推导所有需要的账户
+    // 3. 全局账户表一次性建索引（key/(owner,mint) -> 位置），各步 O(1) 定位
+    let table = AccountTable::new(ctx.remaining_accounts, &program_ids)?;
+
+    // 推导所有需要的账户（各环共享缓存）
     let mut derived_accounts = DerivedAccounts::new();
     // 初始化固定地址与系统程序表
-    derived_accounts.initialize(&program_ids)?;
-    derived_accounts.derive_for_path(
-        &params.path_steps,
-        &ctx.accounts.user.key(),
-        &program_ids,
-        ctx.remaining_accounts,
-    )?;
-    
-    // 4. 执行实际的套利路径
-    let mut current_amount = params.input_amount;
-    // 可选：账户表快照已移除（减少日志噪音）
-    
-    for (step_index, step) in params.path_steps.iter().enumerate() {
-        msg!("Executing step {}: {:?} -> {:?} on {:?}", 
-             step_index, step.input_mint, step.output_mint, step.dex_type);
-        msg!(
-            "Step {} inputs: amount_in={}, min_out={}",
-            step_index,
-            current_amount,
-            step.minimum_amount_out
-        );
-        
-        // 获取当前步骤的账户映射（V2 indices 协议）
-        let mapping: &PathAccountMappingV2 = &params.account_mappings_v2[step_index];
-        
-        // 创建临时的账户解析器 
-        let account_resolver = AccountResolver::new(ctx.remaining_accounts);
-        
-        // 验证账户映射（indices 数量）
-        account_resolver.validate_indices_for_dex(mapping)?;
-        
-        // 解析这一步需要的 DEX 账户
-        let dex_accounts = match step.dex_type {
-            DexType::RaydiumCpmm => {
-                DexAccounts::RaydiumCpmm(account_resolver.resolve_raydium_cpmm_by_indices(mapping)?)
-            }
-            DexType::RaydiumClmm => {
-                DexAccounts::RaydiumClmm(account_resolver.resolve_raydium_clmm_by_indices(mapping)?)
-            }
-            DexType::PumpFunBondingCurve => {
-                DexAccounts::Pumpfun(account_resolver.resolve_pumpfun_by_indices(mapping)?)
-            }
-            DexType::PumpSwap => {
-                DexAccounts::Pumpswap(account_resolver.resolve_pumpswap_by_indices(mapping)?)
-            }
-        };
+    derived_accounts.initialize(&program_ids, log_level)?;
+    for cycle in cycles {
+        derived_accounts.derive_for_path(
+            &cycle.steps,
+            &ctx.accounts.user.key(),
+            &program_ids,
+            &table,
+        )?;
+    }
 
-        // 版本治理辅助日志：打印关键账户指纹（长度 + 头8字节），用于多版本池/配置识别
-        match step.dex_type {
-            DexType::RaydiumCpmm => {
-                let pool_idx = mapping.indices[1] as usize; // pool_state
-                let cfg_idx = mapping.indices[0] as usize;  // amm_config
-                if let Some(ai) = ctx.remaining_accounts.get(pool_idx) {
-                    log_account_fingerprint(ai, "CPMM.pool_state");
-                }
-                if let Some(ai) = ctx.remaining_accounts.get(cfg_idx) {
-                    log_account_fingerprint(ai, "CPMM.amm_config");
-                }
-            }
-            DexType::RaydiumClmm => {
-                let pool_idx = mapping.indices[2] as usize; // pool_state
-                let cfg_idx = mapping.indices[1] as usize;  // amm_config
-                if let Some(ai) = ctx.remaining_accounts.get(pool_idx) {
-                    log_account_fingerprint(ai, "CLMM.pool_state");
-                }
-                if let Some(ai) = ctx.remaining_accounts.get(cfg_idx) {
-                    log_account_fingerprint(ai, "CLMM.amm_config");
-                }
-            }
-            DexType::PumpFunBondingCurve => {
-                let bc_idx = mapping.indices[0] as usize; // bonding_curve
-                if let Some(ai) = ctx.remaining_accounts.get(bc_idx) {
-                    log_account_fingerprint(ai, "PumpFun.bonding_curve");
-                }
-            }
-            DexType::PumpSwap => {
-                let pool_idx = mapping.indices[0] as usize; // pool_state
-                if let Some(ai) = ctx.remaining_accounts.get(pool_idx) {
-                    log_account_fingerprint(ai, "PumpSwap.pool_state");
-                }
-            }
+    Ok(ExecutionContext { program_ids, table, derived_accounts })
+}
+
+/// 错误的简短名称（日志用）
+fn error_name(err: &Error) -> String {
+    match err {
+        Error::AnchorError(e) => e.error_name.clone(),
+        Error::ProgramError(e) => e.program_error.to_string(),
+    }
+}
+
+/// 单环参数校验：路径长度、首步投入与各步 amount_mode
+fn validate_cycle_params(cycle: &CycleView) -> Result<()> {
+    require!(!cycle.steps.is_empty(), ArbitrageError::PathTooShort);
+    require!(cycle.steps.len() <= 10, ArbitrageError::PathTooLong);
+    // 首步按余额取数时 input_amount 不参与，仅 Chained 模式要求 > 0
+    if cycle.steps[0].amount_mode == AmountMode::Chained {
+        require!(cycle.input_amount > 0, ArbitrageError::InvalidAmount);
+    }
+    // 余额模式仅限首步：中间步的 ATA 残留无法计入首步成本基准，扫入会被算成利润
+    require!(
+        cycle.steps[1..].iter().all(|s| s.amount_mode == AmountMode::Chained),
+        ArbitrageError::InvalidAmountMode
+    );
+    Ok(())
+}
+
+/// 执行一个套利环的全部步骤，返回 (首步实际投入, 末步实际产出, 每步各腿聚合结果)
+fn execute_cycle<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, ExecuteArbitrage<'info>>,
+    table: &AccountTable<'info>,
+    derived_accounts: &DerivedAccounts,
+    program_ids: &ProgramIds,
+    options: &ExecutionOptions,
+    cycle: &CycleView,
+) -> Result<(u64, u64, Vec<SwapResult>)> {
+    let log_level = options.log_level;
+    let mut current_amount = cycle.input_amount;
+    let mut step_results = Vec::with_capacity(cycle.steps.len());
+    // 利润基准：首步实际投入（余额模式下由链上余额决定）
+    let mut initial_amount = cycle.input_amount;
+    
+    for (step_index, step) in cycle.steps.iter().enumerate() {
+        if log_level.summary() {
+            msg!("Executing step {}: {:?} -> {:?} on {:?}", 
+                 step_index, step.input_mint, step.output_mint, step.dex_type);
         }
         
         // 获取用户的输入输出账户地址
@@ -153,76 +468,355 @@ pub fn execute_arbitrage<'info>(
         let user_output_account_key = derived_accounts.get_user_token_account(&step.output_mint)
             .ok_or(ArbitrageError::MissingTokenAccount)?;
         
-        // 从remaining_accounts中找到对应的AccountInfo
-        // 注意：用户的代币账户应该在remaining_accounts的末尾部分
-        // 这需要客户端按约定放置：DEX账户在前，用户代币账户在后
-        let user_input_account = find_account_info(ctx.remaining_accounts, user_input_account_key)?;
-        let user_output_account = find_account_info(ctx.remaining_accounts, user_output_account_key)?;
+        // 从全局账户表索引中定位对应的AccountInfo（位置不限）
+        let user_input_account = table.get(user_input_account_key)?;
+        let user_output_account = table.get(user_output_account_key)?;
 
         // 安全校验：用户 ATA 的 owner/mint/program 是否符合预期
         validate_user_token_account(
             user_input_account,
             &step.input_mint,
             &ctx.accounts.user.key(),
-            &program_ids,
+            program_ids,
+            log_level,
         )?;
         validate_user_token_account(
             user_output_account,
             &step.output_mint,
             &ctx.accounts.user.key(),
-            &program_ids,
-        )?;
-        
-        // 执行 DEX 交换
-        let swap_result = DexRouter::execute_swap(
-            step.dex_type,
-            dex_accounts,
-            &derived_accounts,
-            ctx.remaining_accounts,
-            &ctx.accounts.user.to_account_info(),
-            &ctx.accounts.token_program.to_account_info(),
-            &ctx.accounts.associated_token_program.to_account_info(),
-            &ctx.accounts.system_program.to_account_info(),
-            user_input_account,
-            user_output_account,
-            current_amount,
-            step.minimum_amount_out,
+            program_ids,
+            log_level,
         )?;
-        
-        // 验证输出
-        DexRouter::validate_swap_result(&swap_result, step.minimum_amount_out)?;
+
+        // 按 amount_mode 确定本步 amount_in（余额模式读取输入 ATA 当前余额）
+        current_amount = resolve_step_amount_in(step.amount_mode, current_amount, user_input_account)?;
+        if step_index == 0 {
+            initial_amount = current_amount;
+        }
+        // 拆分腿：按份额分出输入，主腿取剩余（仅 ExactIn）
+        if !step.split_legs.is_empty() {
+            require!(step.swap_mode == SwapMode::ExactIn, ArbitrageError::UnsupportedSwapMode);
+        }
+        let (primary_amount, leg_amounts) = DexRouter::split_amount(current_amount, step.split_legs)?;
+        if log_level.summary() {
+            msg!(
+                "Step {} inputs: amount_in={}, min_out={}, mode={:?}/{:?}, legs={}",
+                step_index,
+                current_amount,
+                step.minimum_amount_out,
+                step.amount_mode,
+                step.swap_mode,
+                1 + step.split_legs.len()
+            );
+        }
+
+        let cu_before = sol_remaining_compute_units();
+
+        // 执行 DEX 交换：主腿 + 各拆分腿（共享用户输入/输出 ATA，逐腿余额差计量）
+        let primary_leg = LegSpec {
+            dex_type: step.dex_type,
+            mapping: step.mapping,
+            amount_in: primary_amount,
+            minimum_amount_out: step.minimum_amount_out,
+            swap_mode: step.swap_mode,
+            sqrt_price_limit_x64: step.sqrt_price_limit_x64,
+            share_bps: None,
+        };
+        let legs = std::iter::once(primary_leg).chain(step.split_legs.iter().zip(&leg_amounts).map(|(leg, &leg_amount)| {
+            LegSpec {
+                dex_type: leg.dex_type,
+                mapping: leg.mapping.view(),
+                amount_in: leg_amount,
+                minimum_amount_out: leg.minimum_amount_out,
+                swap_mode: SwapMode::ExactIn,
+                sqrt_price_limit_x64: leg.sqrt_price_limit_x64,
+                share_bps: Some(leg.share_bps),
+            }
+        }));
+        let mut leg_results = Vec::with_capacity(1 + step.split_legs.len());
+        for (leg_index, leg) in legs.enumerate() {
+            // CU 预算守卫：每腿 CPI 前剩余 CU 低于客户端给定的 reserve 时提前失败
+            if let Some(reserve) = options.min_cu_reserve {
+                let cu_left = sol_remaining_compute_units();
+                if cu_left < reserve {
+                    msg!(
+                        "Step {} aborted: remaining_cu={} < min_cu_reserve={} leg={}",
+                        step_index,
+                        cu_left,
+                        reserve,
+                        leg_index
+                    );
+                    return Err(ArbitrageError::ComputeBudgetExhausted.into());
+                }
+            }
+            let leg_result = execute_leg(
+                ctx.accounts,
+                ctx.remaining_accounts,
+                table,
+                derived_accounts,
+                program_ids,
+                log_level,
+                step,
+                &leg,
+                user_input_account,
+                user_output_account,
+            )?;
+            if let Some(share_bps) = leg.share_bps {
+                if log_level.summary() {
+                    msg!(
+                        "Step {} leg {:?}: share_bps={} amount_in={} amount_out={}",
+                        step_index,
+                        leg.dex_type,
+                        share_bps,
+                        leg_result.amount_in,
+                        leg_result.amount_out
+                    );
+                }
+            }
+            leg_results.push(leg_result);
+        }
+        let swap_result = DexRouter::aggregate_results(&leg_results)?;
         
         current_amount = swap_result.amount_out;
-        msg!(
-            "Step {} completed: amount_out={} -> new_running_amount={}",
-            step_index,
-            swap_result.amount_out,
-            current_amount
-        );
+        let cu_after = sol_remaining_compute_units();
+        if log_level.summary() {
+            msg!(
+                "Step {} completed: amount_in={} amount_out={} -> new_running_amount={} cu_used={} cu_left={}",
+                step_index,
+                swap_result.amount_in,
+                swap_result.amount_out,
+                current_amount,
+                cu_before.saturating_sub(cu_after),
+                cu_after
+            );
+        }
+        step_results.push(swap_result);
     }
     
-    // 6. 验证最终利润
-    require!(
-        current_amount >= params.input_amount.saturating_add(params.min_profit_lamports),
-        ArbitrageError::InsufficientProfit
-    );
-    let profit = current_amount - params.input_amount;
-    msg!("Arbitrage completed successfully. Profit: {}", profit);
-    
-    Ok(())
+
+    Ok((initial_amount, current_amount, step_results))
+}
+
+/// 单腿执行参数：主腿取自步本身，拆分腿取自 `SplitLeg`（固定 ExactIn）
+struct LegSpec<'a> {
+    dex_type: DexType,
+    mapping: MappingView<'a>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    swap_mode: SwapMode,
+    sqrt_price_limit_x64: u128,
+    /// 拆分腿份额（仅用于日志）；主腿为 None
+    share_bps: Option<u16>,
 }
 
-/// Helper function to find AccountInfo by public key in remaining_accounts
-fn find_account_info<'info>(
-    remaining_accounts: &'info [AccountInfo<'info>], 
-    target_key: &Pubkey
-) -> Result<&'info AccountInfo<'info>> {
-    for account in remaining_accounts {
-        if account.key() == *target_key {
-            return Ok(account);
+/// 执行单腿：解析 indices → 池归属/成员与两侧 mint 校验 →（verbose）指纹日志 → CPI → 产出下限校验
+#[allow(clippy::too_many_arguments)]
+fn execute_leg<'info>(
+    accounts: &ExecuteArbitrage<'info>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    table: &AccountTable<'info>,
+    derived_accounts: &DerivedAccounts,
+    program_ids: &ProgramIds,
+    log_level: LogLevel,
+    step: &StepView,
+    leg: &LegSpec,
+    user_input_account: &'info AccountInfo<'info>,
+    user_output_account: &'info AccountInfo<'info>,
+) -> Result<SwapResult> {
+    // 创建临时的账户解析器并解析这一腿需要的 DEX 账户
+    let account_resolver = AccountResolver::new(remaining_accounts, log_level);
+    let dex_accounts = DexRouter::resolve_accounts(&account_resolver, leg.dex_type, leg.mapping)?;
+
+    // 池归属与成员校验：账户表与池状态不一致时以精确错误码失败（而非下游 CPI 报错）
+    validate_dex_accounts(&dex_accounts, program_ids)?;
+    // 步声明与池一致：每条腿的池两侧 mint 须恰为本步 input/output mint
+    let wrapped_sol_mint = derived_accounts
+        .get_fixed_addresses()
+        .ok_or(ArbitrageError::AccountNotFound)?
+        .wrapped_sol_mint;
+    validate_step_mints(&dex_accounts, &step.input_mint, &step.output_mint, &wrapped_sol_mint)?;
+
+    // 版本治理辅助日志：打印关键账户指纹（长度 + 头8字节），用于多版本池/配置识别（需 verbose）
+    if log_level.verbose() {
+        log_dex_fingerprints(leg.dex_type, leg.mapping, remaining_accounts);
+    }
+
+    let swap_result = DexRouter::execute_swap(
+        leg.dex_type,
+        dex_accounts,
+        derived_accounts,
+        table,
+        &accounts.user.to_account_info(),
+        &accounts.token_program.to_account_info(),
+        &accounts.associated_token_program.to_account_info(),
+        &accounts.system_program.to_account_info(),
+        user_input_account,
+        user_output_account,
+        leg.amount_in,
+        leg.minimum_amount_out,
+        leg.swap_mode,
+        leg.sqrt_price_limit_x64,
+    )?;
+
+    // 验证输出
+    DexRouter::validate_swap_result(&swap_result, leg.minimum_amount_out)?;
+    Ok(swap_result)
+}
+
+/// 按 DEX 打印池/配置账户指纹
+fn log_dex_fingerprints(dex_type: DexType, mapping: MappingView, remaining_accounts: &[AccountInfo]) {
+    match dex_type {
+        DexType::RaydiumCpmm => {
+            let pool_idx = mapping.indices[1] as usize; // pool_state
+            let cfg_idx = mapping.indices[0] as usize;  // amm_config
+            if let Some(ai) = remaining_accounts.get(pool_idx) {
+                log_account_fingerprint(ai, "CPMM.pool_state");
+            }
+            if let Some(ai) = remaining_accounts.get(cfg_idx) {
+                log_account_fingerprint(ai, "CPMM.amm_config");
+            }
+        }
+        DexType::RaydiumClmm => {
+            let pool_idx = mapping.indices[2] as usize; // pool_state
+            let cfg_idx = mapping.indices[1] as usize;  // amm_config
+            if let Some(ai) = remaining_accounts.get(pool_idx) {
+                log_account_fingerprint(ai, "CLMM.pool_state");
+            }
+            if let Some(ai) = remaining_accounts.get(cfg_idx) {
+                log_account_fingerprint(ai, "CLMM.amm_config");
+            }
+        }
+        DexType::PumpFunBondingCurve => {
+            let bc_idx = mapping.indices[0] as usize; // bonding_curve
+            if let Some(ai) = remaining_accounts.get(bc_idx) {
+                log_account_fingerprint(ai, "PumpFun.bonding_curve");
+            }
+        }
+        DexType::PumpSwap => {
+            let pool_idx = mapping.indices[0] as usize; // pool_state
+            if let Some(ai) = remaining_accounts.get(pool_idx) {
+                log_account_fingerprint(ai, "PumpSwap.pool_state");
+            }
         }
     }
-    Err(ArbitrageError::AccountNotFound.into())
+}
+
+/// 按 amount_mode 计算本步输入数量
+fn resolve_step_amount_in(
+    mode: AmountMode,
+    chained_amount: u64,
+    user_input_account: &AccountInfo,
+) -> Result<u64> {
+    let amount_in = match mode {
+        AmountMode::Chained => chained_amount,
+        AmountMode::FullBalance => read_token_amount(user_input_account)?,
+        AmountMode::BalanceBps(bps) => {
+            require!(bps > 0 && bps <= 10_000, ArbitrageError::InvalidAmount);
+            let balance = read_token_amount(user_input_account)?;
+            let scaled = (balance as u128) * (bps as u128) / 10_000u128;
+            u64::try_from(scaled).map_err(|_| ArbitrageError::MathOverflow)?
+        }
+    };
+    require!(amount_in > 0, ArbitrageError::InvalidAmount);
+    Ok(amount_in)
+}
+
+/// 关闭路径中间 mint 的用户 ATA（余额为 0 才关闭），租金退回 user
+///
+/// - 每个环的起始输入 mint 与最终输出 mint 的 ATA 保留（下一轮仍会使用）；
+/// - 余额非 0（残留 dust）的账户跳过，不影响本次套利结果；
+/// - Token-2022：若账户存在 withheld 转账手续费，先 harvest 到 mint，否则 close 会失败。
+fn close_intermediate_token_accounts<'info>(
+    cycles: &[CycleView],
+    derived_accounts: &DerivedAccounts,
+    table: &AccountTable<'info>,
+    user: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    program_ids: &ProgramIds,
+    log_level: LogLevel,
+) -> Result<()> {
+    let endpoints: Vec<Pubkey> = cycles
+        .iter()
+        .filter_map(|cycle| Some([cycle.steps.first()?.input_mint, cycle.steps.last()?.output_mint]))
+        .flatten()
+        .collect();
+    let mut visited: Vec<Pubkey> = Vec::with_capacity(cycles.iter().map(|c| c.steps.len() * 2).sum());
+    let mut closed = 0u8;
+    for mint in cycles
+        .iter()
+        .flat_map(|cycle| cycle.steps.iter())
+        .flat_map(|step| [step.input_mint, step.output_mint])
+    {
+        if endpoints.contains(&mint) || visited.contains(&mint) {
+            continue;
+        }
+        visited.push(mint);
+
+        let ata_key = derived_accounts
+            .get_user_token_account(&mint)
+            .ok_or(ArbitrageError::MissingTokenAccount)?;
+        let ata = table.get(ata_key)?;
+        let (amount, withheld) = read_balance_and_withheld(ata, program_ids)?;
+        if amount > 0 {
+            if log_level.verbose() {
+                msg!("[Cleanup] keep ata={} mint={} amount={}", ata.key(), mint, amount);
+            }
+            continue;
+        }
+
+        let program_ai = if ata.owner == &program_ids.token_program {
+            token_program.clone()
+        } else {
+            table.get(&program_ids.token_2022_program)?.clone()
+        };
+        if withheld > 0 {
+            let mint_ai = table.get(&mint)?;
+            harvest_withheld_tokens_to_mint(
+                CpiContext::new(
+                    program_ai.clone(),
+                    HarvestWithheldTokensToMint {
+                        token_program_id: program_ai.clone(),
+                        mint: mint_ai.clone(),
+                    },
+                ),
+                vec![ata.clone()],
+            )?;
+            if log_level.verbose() {
+                msg!("[Cleanup] harvested withheld={} mint={}", withheld, mint);
+            }
+        }
+        close_account(CpiContext::new(
+            program_ai,
+            CloseAccount {
+                account: ata.clone(),
+                destination: user.clone(),
+                authority: user.clone(),
+            },
+        ))?;
+        closed += 1;
+    }
+    if log_level.summary() {
+        msg!("[Cleanup] closed_intermediate_atas={}", closed);
+    }
+    Ok(())
+}
+
+/// 读取 token 账户余额与 Token-2022 withheld 手续费（Token 程序账户 withheld 恒为 0）
+fn read_balance_and_withheld(token_ai: &AccountInfo, program_ids: &ProgramIds) -> Result<(u64, u64)> {
+    let data = token_ai.try_borrow_data()?;
+    if token_ai.owner == &program_ids.token_2022_program {
+        let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)
+            .map_err(|_| ArbitrageError::InvalidAccount)?;
+        let withheld = state
+            .get_extension::<TransferFeeAmount>()
+            .map(|ext| u64::from(ext.withheld_amount))
+            .unwrap_or(0);
+        return Ok((state.base.amount, withheld));
+    }
+    require!(data.len() >= 72, ArbitrageError::InvalidAccount);
+    let mut amount_bytes = [0u8; 8];
+    amount_bytes.copy_from_slice(&data[64..72]);
+    Ok((u64::from_le_bytes(amount_bytes), 0))
 }
 
 /// 校验用户 SPL Token 账户是否与期望的 mint/owner 对齐，且为受支持的 token program
@@ -231,6 +825,7 @@ fn validate_user_token_account<'info>(
     expected_mint: &Pubkey,
     expected_owner: &Pubkey,
     program_ids: &ProgramIds,
+    log_level: LogLevel,
 ) -> Result<()> {
     // 校验 program（Token 或 Token-2022）
     let is_token = token_ai.owner == &program_ids.token_program;
@@ -255,7 +850,10 @@ fn validate_user_token_account<'info>(
     require!(mint_pk != Pubkey::default(), ArbitrageError::InvalidAccount);
     require!(owner_pk != Pubkey::default(), ArbitrageError::InvalidAccount);
 
-    // 观测日志：便于链上对齐问题排查
+    // 观测日志：便于链上对齐问题排查（需 verbose）
+    if !log_level.verbose() {
+        return Ok(());
+    }
     msg!(
         "[ATA] program={} len={} mint={} owner={} (expected_mint={} expected_owner={})",
         if is_token { program_ids.token_program } else { program_ids.token_2022_program },
@@ -289,3 +887,15 @@ fn log_account_fingerprint<'info>(ai: &AccountInfo<'info>, label: &str) {
     msg!("[FINGERPRINT] {} len={} head8={}", label, len, head8);
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::hash::hash;
+
+    #[test]
+    fn raw_entry_discriminator_matches_anchor_sighash() {
+        let sighash = hash(b"global:execute_arbitrage_v3");
+        assert_eq!(EXECUTE_ARBITRAGE_V3_DISCRIMINATOR, sighash.to_bytes()[..8]);
+    }
+}
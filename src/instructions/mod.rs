@@ -1,3 +1,5 @@
 pub mod execute_arbitrage;
+pub mod quote_path;
 
-pub use execute_arbitrage::*;
\ No newline at end of file
+pub use execute_arbitrage::*;
+pub use quote_path::*;
\ No newline at end of file
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::{set_return_data, MAX_RETURN_DATA};
+use crate::instructions::execute_arbitrage::{prepare_execution, with_payload_cycles, ExecuteArbitrage, ExecutionContext};
+use crate::quote::{self, PathQuote};
+use crate::errors::ArbitrageError;
+
+// ==============================================================================================
+// 只读报价入口
+// 作用：
+// - 与执行入口相同的账户、参数（版本化 payload，`0x02` 即 `execute_arbitrage` 的 `ArbitrageParams`）与全局表，
+//   经同一套参数校验/账户解析/池校验/推导代码；
+// - 按当前池状态逐环报价（`quote::quote_path`），不发起任何 CPI、不修改任何账户；
+// - 结果 `Vec<Option<PathQuote>>`（下标即 cycle_index，None = 含无法报价的腿）Borsh 编码后 `set_return_data`，
+//   供客户端在 `simulateTransaction` 中读取权威的链上报价。
+// ==============================================================================================
+
+pub fn quote_path<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ExecuteArbitrage<'info>>,
+    payload: &[u8],
+) -> Result<()> {
+    let quotes = with_payload_cycles(ctx.remaining_accounts, payload, |cycles, options| {
+        let log_level = options.log_level;
+        let ExecutionContext { program_ids, table, derived_accounts } = prepare_execution(&ctx, cycles, log_level)?;
+
+        let mut quotes: Vec<Option<PathQuote>> = Vec::with_capacity(cycles.len());
+        for (cycle_index, cycle) in cycles.iter().enumerate() {
+            let quote = quote::quote_path(
+                ctx.remaining_accounts,
+                &table,
+                &derived_accounts,
+                &program_ids,
+                cycle.input_amount,
+                &cycle.steps,
+            )?;
+            if log_level.summary() {
+                match &quote {
+                    Some(q) => msg!(
+                        "[QUOTE] cycle={} initial={} final={} min_out_met={}",
+                        cycle_index,
+                        q.initial_amount,
+                        q.final_amount,
+                        q.min_out_met
+                    ),
+                    None => msg!("[QUOTE] cycle={} not quotable", cycle_index),
+                }
+            }
+            quotes.push(quote);
+        }
+        Ok(quotes)
+    })?;
+
+    let data = quotes.try_to_vec()?;
+    require!(data.len() <= MAX_RETURN_DATA, ArbitrageError::ReturnDataTooLarge);
+    set_return_data(&data);
+    Ok(())
+}
@@ -1,56 +1,60 @@
-use anchor_lang::prelude::*;
+//! Program ID（declare_id）生成与配置指引
+//!
+//! 1) 生成 Program ID（dev/test/main 可分别生成）
+//! - 生成密钥对（示例输出到 target/deploy/）：
+//!   ```bash
+//!   solana-keygen new -o target/deploy/arbitrage_contract-devnet-keypair.json -s --no-bip39-passphrase --force
+//!   solana-keygen new -o target/deploy/arbitrage_contract-testnet-keypair.json -s --no-bip39-passphrase --force
+//!   solana-keygen new -o target/deploy/arbitrage_contract-mainnet-keypair.json -s --no-bip39-passphrase --force
+//!   ```
+//! - 查看 Program ID（公钥）：
+//!   ```bash
+//!   solana-keygen pubkey target/deploy/arbitrage_contract-devnet-keypair.json
+//!   solana-keygen pubkey target/deploy/arbitrage_contract-testnet-keypair.json
+//!   solana-keygen pubkey target/deploy/arbitrage_contract-mainnet-keypair.json
+//!   # 或使用 Anchor 汇总
+//!   anchor keys list
+//!   ```
+//!
+//! 2) 配置位置（必须三处一致）
+//! - 合约：`src/lib.rs` 的 `declare_id!("<ProgramID>")`
+//! - Anchor：`Anchor.toml` 的对应网络段
+//!   ```toml
+//!   [programs.devnet]
+//!   arbitrage_contract = "<DevnetProgramID>"
+//!
+//!   [programs.testnet]
+//!   arbitrage_contract = "<TestnetProgramID>"
+//!
+//!   [programs.mainnet]
+//!   arbitrage_contract = "<MainnetProgramID>"
+//!
+//!   [provider]
+//!   cluster = "devnet"   # 或 "testnet"/"mainnet"
+//!   wallet  = "~/.config/solana/id.json"
+//!   ```
+//! - 客户端：将 `ARBITRAGE_CONTRACT_ID`（或等价常量）设置为对应网络的 Program ID
+//!
+//! 3) 部署流程（示例）
+//! ```bash
+//! solana config set --url devnet        # 或 mainnet-beta/testnet
+//! anchor build
+//! anchor deploy                         # 使用 Anchor.toml 的 [provider]
+//! ```
+//!
+//! 4) 策略建议
+//! - 复用一套 Program ID 跨网络：省去改 `declare_id!`；各网络部署同一 ID 的程序
+//! - 每网独立 Program ID：更隔离，但切换网络前需同步修改 `declare_id!`、`Anchor.toml` 与客户端常量，并用对应 keypair 部署
+//!
+//! 5) 升级注意
+//! - 升级（`anchor upgrade`）必须使用最初部署该 Program ID 的私钥；请妥善保管 keypair
+//! - 若丢失私钥，将无法继续升级该 Program ID 下的程序
+
+// anchor 0.31 的 #[program] 在 crate 根生成 `__private::__idl`（IDL 指令处理），其中调用已弃用的
+// AccountInfo::realloc；该模块为生成的同级项，无法在单个 item 上放行，只能在 crate 级。
+#![allow(deprecated)]
 
-/// Program ID（declare_id）生成与配置指引
-///
-/// 1) 生成 Program ID（dev/test/main 可分别生成）
-/// - 生成密钥对（示例输出到 target/deploy/）：
-///   ```bash
-///   solana-keygen new -o target/deploy/arbitrage_contract-devnet-keypair.json -s --no-bip39-passphrase --force
-///   solana-keygen new -o target/deploy/arbitrage_contract-testnet-keypair.json -s --no-bip39-passphrase --force
-///   solana-keygen new -o target/deploy/arbitrage_contract-mainnet-keypair.json -s --no-bip39-passphrase --force
-///   ```
-/// - 查看 Program ID（公钥）：
-///   ```bash
-///   solana-keygen pubkey target/deploy/arbitrage_contract-devnet-keypair.json
-///   solana-keygen pubkey target/deploy/arbitrage_contract-testnet-keypair.json
-///   solana-keygen pubkey target/deploy/arbitrage_contract-mainnet-keypair.json
-///   # 或使用 Anchor 汇总
-///   anchor keys list
-///   ```
-///
-/// 2) 配置位置（必须三处一致）
-/// - 合约：`src/lib.rs` 的 `declare_id!("<ProgramID>")`
-/// - Anchor：`Anchor.toml` 的对应网络段
-///   ```toml
-///   [programs.devnet]
-///   arbitrage_contract = "<DevnetProgramID>"
-///
-///   [programs.testnet]
-///   arbitrage_contract = "<TestnetProgramID>"
-///
-///   [programs.mainnet]
-///   arbitrage_contract = "<MainnetProgramID>"
-///
-///   [provider]
-///   cluster = "devnet"   # 或 "testnet"/"mainnet"
-///   wallet  = "~/.config/solana/id.json"
-///   ```
-/// - 客户端：将 `ARBITRAGE_CONTRACT_ID`（或等价常量）设置为对应网络的 Program ID
-///
-/// 3) 部署流程（示例）
-/// ```bash
-/// solana config set --url devnet        # 或 mainnet-beta/testnet
-/// anchor build
-/// anchor deploy                         # 使用 Anchor.toml 的 [provider]
-/// ```
-///
-/// 4) 策略建议
-/// - 复用一套 Program ID 跨网络：省去改 `declare_id!`；各网络部署同一 ID 的程序
-/// - 每网独立 Program ID：更隔离，但切换网络前需同步修改 `declare_id!`、`Anchor.toml` 与客户端常量，并用对应 keypair 部署
-///
-/// 5) 升级注意
-/// - 升级（`anchor upgrade`）必须使用最初部署该 Program ID 的私钥；请妥善保管 keypair
-/// - 若丢失私钥，将无法继续升级该 Program ID 下的程序
+use anchor_lang::prelude::*;
 
 pub mod instructions;
 pub mod state;
@@ -58,13 +62,26 @@ pub mod errors;
 pub mod account_resolver;
 pub mod account_derivation;
 pub mod dex_router;
+pub mod compact;
+pub mod quote;
+pub mod events;
+pub mod external_programs;
+#[cfg(feature = "client")]
+pub mod client;
 
+// 指令处理函数与 #[program] 生成的同名入口重名（外部按 `instructions::` 路径引用）
+#[allow(ambiguous_glob_reexports)]
 pub use instructions::*;
 pub use state::*;
 pub use errors::*;
 pub use account_resolver::*;
+// 两者均含 `types` 子模块（外部按完整路径引用）
+#[allow(ambiguous_glob_reexports)]
 pub use account_derivation::*;
 pub use dex_router::*;
+pub use compact::*;
+pub use quote::*;
+pub use events::*;
 
 declare_id!("4ZqQT3aUpSMiAjmyaYj6yHjfJQH6k7v3XBSpgAhWU8uC");
 
@@ -78,4 +95,32 @@ pub mod arbitrage_contract {
     ) -> Result<()> {
         instructions::execute_arbitrage(ctx, params)
     }
+
+    /// 版本化入口（`payload` 首字节为协议版本：0x02 V2 / 0x03 V3 / 0x83 V3 紧凑编码）
+    ///
+    /// 仅为生成 IDL 而声明；链上由 `fallback` 按同一 discriminator 直接读取指令数据切片，省去 `Vec<u8>` 复制
+    #[cfg(feature = "idl-build")]
+    pub fn execute_arbitrage_v3<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteArbitrage<'info>>,
+        payload: Vec<u8>,
+    ) -> Result<()> {
+        instructions::execute_arbitrage_v3(ctx, &payload)
+    }
+
+    /// 未匹配 discriminator 的指令：受理 `execute_arbitrage_v3` 的原始入口，其余拒绝
+    pub fn fallback<'info>(
+        program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> Result<()> {
+        instructions::execute_arbitrage_v3_raw(program_id, accounts, data)
+    }
+
+    /// 只读报价（不发起 CPI）：账户与 payload 同 `execute_arbitrage_v3`，结果经 `set_return_data` 返回
+    pub fn quote_path<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteArbitrage<'info>>,
+        payload: Vec<u8>,
+    ) -> Result<()> {
+        instructions::quote_path(ctx, &payload)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,763 @@
+//! Raydium CLMM 报价数学：tick ↔ sqrt price、单区间 swap step 与跨 tick 模拟
+//!
+//! 与 raydium-clmm `libraries::{tick_math, sqrt_price_math, swap_math}` 及 `swap_internal` 逐式对应，
+//! 舍入方向一致（输入向上、产出向下取整）；256 位中间量以定长 limb 实现，乘除在 512 位上完成。
+
+use anchor_lang::prelude::*;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use crate::dex_router::types::constants::{
+    RAYDIUM_CLMM_MAX_SQRT_PRICE_X64, RAYDIUM_CLMM_MAX_TICK, RAYDIUM_CLMM_MIN_SQRT_PRICE_X64, RAYDIUM_CLMM_MIN_TICK,
+    RAYDIUM_CLMM_TICK_ARRAY_SIZE,
+};
+use crate::errors::ArbitrageError;
+use super::state::{ClmmPoolState, TickArrayState, TickState};
+
+/// CLMM trade_fee_rate 分母
+pub const CLMM_FEE_RATE_DENOMINATOR: u64 = 1_000_000;
+
+// ================================================================
+// 512 位无符号定长整数（little-endian u64 limbs），仅供 mul_div
+// ================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Wide([u64; 8]);
+
+impl Wide {
+    const ZERO: Wide = Wide([0; 8]);
+
+    fn from_u128(v: u128) -> Self {
+        let mut limbs = [0u64; 8];
+        limbs[0] = v as u64;
+        limbs[1] = (v >> 64) as u64;
+        Wide(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|&l| l == 0)
+    }
+
+    /// 高位非零时返回 None
+    fn to_u128(self) -> Option<u128> {
+        if self.0[2..].iter().any(|&l| l != 0) {
+            return None;
+        }
+        Some((self.0[0] as u128) | ((self.0[1] as u128) << 64))
+    }
+
+    fn to_u64(self) -> Option<u64> {
+        self.to_u128().and_then(|v| u64::try_from(v).ok())
+    }
+
+    /// × 2^64
+    fn shl64(self) -> Option<Self> {
+        if self.0[7] != 0 {
+            return None;
+        }
+        let mut limbs = [0u64; 8];
+        limbs[1..].copy_from_slice(&self.0[..7]);
+        Some(Wide(limbs))
+    }
+
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        let mut out = [0u64; 8];
+        let mut carry = 0u128;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let sum = self.0[i] as u128 + rhs.0[i] as u128 + carry;
+            *limb = sum as u64;
+            carry = sum >> 64;
+        }
+        (carry == 0).then_some(Wide(out))
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        if self < rhs {
+            return None;
+        }
+        let mut out = [0u64; 8];
+        let mut borrow = 0i128;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let mut diff = self.0[i] as i128 - rhs.0[i] as i128 - borrow;
+            borrow = if diff < 0 { diff += 1 << 64; 1 } else { 0 };
+            *limb = diff as u64;
+        }
+        Some(Wide(out))
+    }
+
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let mut out = [0u64; 8];
+        for i in 0..8 {
+            if self.0[i] == 0 {
+                continue;
+            }
+            let mut carry = 0u128;
+            for j in 0..8 {
+                let cur = self.0[i] as u128 * rhs.0[j] as u128 + out.get(i + j).copied().unwrap_or(0) as u128 + carry;
+                if i + j < 8 {
+                    out[i + j] = cur as u64;
+                } else if cur as u64 != 0 {
+                    return None;
+                }
+                carry = cur >> 64;
+            }
+            if carry != 0 {
+                return None;
+            }
+        }
+        Some(Wide(out))
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        (self.0[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    fn shl1(self) -> Self {
+        let mut out = [0u64; 8];
+        let mut carry = 0u64;
+        for (i, limb) in out.iter_mut().enumerate() {
+            *limb = (self.0[i] << 1) | carry;
+            carry = self.0[i] >> 63;
+        }
+        Wide(out)
+    }
+
+    /// 长除法：返回 (商, 是否有余数)；除数须非零且 < 2^511
+    fn div_rem(self, divisor: Self) -> Option<(Self, bool)> {
+        if divisor.is_zero() || divisor.bit(511) {
+            return None;
+        }
+        let mut quotient = Wide::ZERO;
+        let mut remainder = Wide::ZERO;
+        for i in (0..512).rev() {
+            remainder = remainder.shl1();
+            if self.bit(i) {
+                remainder.0[0] |= 1;
+            }
+            if remainder >= divisor {
+                remainder = remainder.checked_sub(divisor)?;
+                quotient.0[i / 64] |= 1 << (i % 64);
+            }
+        }
+        Some((quotient, !remainder.is_zero()))
+    }
+
+    fn div_floor(self, divisor: Self) -> Option<Self> {
+        self.div_rem(divisor).map(|(q, _)| q)
+    }
+
+    fn div_ceil(self, divisor: Self) -> Option<Self> {
+        let (q, rem) = self.div_rem(divisor)?;
+        if rem { q.checked_add(Wide::from_u128(1)) } else { Some(q) }
+    }
+
+    fn mul_div_floor(self, mul: Self, divisor: Self) -> Option<Self> {
+        self.checked_mul(mul)?.div_floor(divisor)
+    }
+
+    fn mul_div_ceil(self, mul: Self, divisor: Self) -> Option<Self> {
+        self.checked_mul(mul)?.div_ceil(divisor)
+    }
+}
+
+impl PartialOrd for Wide {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Wide {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+#[inline]
+fn overflow() -> Error {
+    error!(ArbitrageError::MathOverflow)
+}
+
+// ================================================================
+// tick / sqrt price
+// ================================================================
+
+/// `sqrt(1.0001^tick) * 2^64`（与 raydium-clmm `tick_math::get_sqrt_price_at_tick` 逐位一致）
+pub fn get_sqrt_price_at_tick(tick: i32) -> Result<u128> {
+    require!(
+        (RAYDIUM_CLMM_MIN_TICK..=RAYDIUM_CLMM_MAX_TICK).contains(&tick),
+        ArbitrageError::InvalidSqrtPriceLimit
+    );
+    const FACTORS: [(u32, u128); 18] = [
+        (0x2, 0xfff97272373d4000),
+        (0x4, 0xfff2e50f5f657000),
+        (0x8, 0xffe5caca7e10f000),
+        (0x10, 0xffcb9843d60f7000),
+        (0x20, 0xff973b41fa98e800),
+        (0x40, 0xff2ea16466c9b000),
+        (0x80, 0xfe5dee046a9a3800),
+        (0x100, 0xfcbe86c7900bb000),
+        (0x200, 0xf987a7253ac65800),
+        (0x400, 0xf3392b0822bb6000),
+        (0x800, 0xe7159475a2caf000),
+        (0x1000, 0xd097f3bdfd2f2000),
+        (0x2000, 0xa9f746462d9f8000),
+        (0x4000, 0x70d869a156f31c00),
+        (0x8000, 0x31be135f97ed3200),
+        (0x10000, 0x9aa508b5b85a500),
+        (0x20000, 0x5d6af8dedc582c),
+        (0x40000, 0x2216e584f5fa),
+    ];
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio: u128 = if abs_tick & 0x1 != 0 { 0xfffcb933bd6fb800 } else { 1u128 << 64 };
+    for (mask, factor) in FACTORS {
+        if abs_tick & mask != 0 {
+            ratio = (ratio * factor) >> 64;
+        }
+    }
+    if tick > 0 {
+        ratio = u128::MAX / ratio;
+    }
+    Ok(ratio)
+}
+
+/// tick 所在 tick array 的起始 index（向负无穷取整到 60 × tick_spacing 的倍数）
+pub fn tick_array_start_index(tick: i32, tick_spacing: u16) -> i32 {
+    let ticks_in_array = RAYDIUM_CLMM_TICK_ARRAY_SIZE * tick_spacing as i32;
+    tick.div_euclid(ticks_in_array) * ticks_in_array
+}
+
+// ================================================================
+// sqrt_price_math
+// ================================================================
+
+/// Δtoken0 = L × (√b − √a) / (√a × √b)；超出 u64 返回 None
+fn get_delta_amount_0_unsigned(mut a: u128, mut b: u128, liquidity: u128, round_up: bool) -> Result<Option<u64>> {
+    if a > b {
+        core::mem::swap(&mut a, &mut b);
+    }
+    require!(a > 0, ArbitrageError::MathOverflow);
+    let numerator_1 = Wide::from_u128(liquidity).shl64().ok_or_else(overflow)?;
+    let numerator_2 = Wide::from_u128(b - a);
+    let (b, a) = (Wide::from_u128(b), Wide::from_u128(a));
+    let result = if round_up {
+        numerator_1.mul_div_ceil(numerator_2, b).and_then(|v| v.div_ceil(a))
+    } else {
+        numerator_1.mul_div_floor(numerator_2, b).and_then(|v| v.div_floor(a))
+    };
+    Ok(result.ok_or_else(overflow)?.to_u64())
+}
+
+/// Δtoken1 = L × (√b − √a)；超出 u64 返回 None
+fn get_delta_amount_1_unsigned(mut a: u128, mut b: u128, liquidity: u128, round_up: bool) -> Result<Option<u64>> {
+    if a > b {
+        core::mem::swap(&mut a, &mut b);
+    }
+    let q64 = Wide::from_u128(1u128 << 64);
+    let (l, diff) = (Wide::from_u128(liquidity), Wide::from_u128(b - a));
+    let result = if round_up { l.mul_div_ceil(diff, q64) } else { l.mul_div_floor(diff, q64) };
+    Ok(result.ok_or_else(overflow)?.to_u64())
+}
+
+fn next_sqrt_price_from_amount_0_rounding_up(sqrt_price: u128, liquidity: u128, amount: u64, add: bool) -> Result<u128> {
+    if amount == 0 {
+        return Ok(sqrt_price);
+    }
+    let numerator_1 = Wide::from_u128(liquidity).shl64().ok_or_else(overflow)?;
+    let price = Wide::from_u128(sqrt_price);
+    let amount = Wide::from_u128(amount as u128);
+    let next = if add {
+        let product = amount.checked_mul(price).ok_or_else(overflow)?;
+        match numerator_1.checked_add(product) {
+            Some(denominator) => numerator_1.mul_div_ceil(price, denominator),
+            None => numerator_1
+                .div_floor(price)
+                .and_then(|q| q.checked_add(amount))
+                .and_then(|d| numerator_1.div_ceil(d)),
+        }
+    } else {
+        let product = amount.checked_mul(price).ok_or_else(overflow)?;
+        require!(numerator_1 > product, ArbitrageError::InsufficientLiquidity);
+        numerator_1.checked_sub(product).and_then(|d| numerator_1.mul_div_ceil(price, d))
+    };
+    next.and_then(Wide::to_u128).ok_or_else(overflow)
+}
+
+fn next_sqrt_price_from_amount_1_rounding_down(sqrt_price: u128, liquidity: u128, amount: u64, add: bool) -> Result<u128> {
+    let shifted = Wide::from_u128(amount as u128).shl64().ok_or_else(overflow)?;
+    let liquidity = Wide::from_u128(liquidity);
+    if add {
+        let quotient = shifted.div_floor(liquidity).and_then(Wide::to_u128).ok_or_else(overflow)?;
+        sqrt_price.checked_add(quotient).ok_or_else(overflow)
+    } else {
+        let quotient = shifted.div_ceil(liquidity).and_then(Wide::to_u128).ok_or_else(overflow)?;
+        require!(sqrt_price > quotient, ArbitrageError::InsufficientLiquidity);
+        Ok(sqrt_price - quotient)
+    }
+}
+
+fn next_sqrt_price_from_input(sqrt_price: u128, liquidity: u128, amount_in: u64, zero_for_one: bool) -> Result<u128> {
+    if zero_for_one {
+        next_sqrt_price_from_amount_0_rounding_up(sqrt_price, liquidity, amount_in, true)
+    } else {
+        next_sqrt_price_from_amount_1_rounding_down(sqrt_price, liquidity, amount_in, true)
+    }
+}
+
+fn next_sqrt_price_from_output(sqrt_price: u128, liquidity: u128, amount_out: u64, zero_for_one: bool) -> Result<u128> {
+    if zero_for_one {
+        next_sqrt_price_from_amount_1_rounding_down(sqrt_price, liquidity, amount_out, false)
+    } else {
+        next_sqrt_price_from_amount_0_rounding_up(sqrt_price, liquidity, amount_out, false)
+    }
+}
+
+// ================================================================
+// swap_math
+// ================================================================
+
+/// 单区间（不跨 tick）交换结果
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price_next_x64: u128,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+}
+
+/// 在 [current, target] 区间内按剩余量成交（raydium-clmm `swap_math::compute_swap_step`）
+pub fn compute_swap_step(
+    sqrt_price_current_x64: u128,
+    sqrt_price_target_x64: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee_rate: u32,
+    is_base_input: bool,
+    zero_for_one: bool,
+) -> Result<SwapStep> {
+    let fee_rate = fee_rate as u64;
+    require!(fee_rate < CLMM_FEE_RATE_DENOMINATOR, ArbitrageError::InvalidFeeAmount);
+    let mut step = SwapStep::default();
+    if is_base_input {
+        let amount_remaining_less_fee = u64::try_from(
+            (amount_remaining as u128) * ((CLMM_FEE_RATE_DENOMINATOR - fee_rate) as u128)
+                / CLMM_FEE_RATE_DENOMINATOR as u128,
+        )
+        .map_err(|_| overflow())?;
+        let amount_in = if zero_for_one {
+            get_delta_amount_0_unsigned(sqrt_price_target_x64, sqrt_price_current_x64, liquidity, true)?
+        } else {
+            get_delta_amount_1_unsigned(sqrt_price_current_x64, sqrt_price_target_x64, liquidity, true)?
+        };
+        if let Some(amount_in) = amount_in {
+            step.amount_in = amount_in;
+        }
+        step.sqrt_price_next_x64 = match amount_in {
+            Some(amount_in) if amount_remaining_less_fee >= amount_in => sqrt_price_target_x64,
+            _ => next_sqrt_price_from_input(sqrt_price_current_x64, liquidity, amount_remaining_less_fee, zero_for_one)?,
+        };
+    } else {
+        let amount_out = if zero_for_one {
+            get_delta_amount_1_unsigned(sqrt_price_target_x64, sqrt_price_current_x64, liquidity, false)?
+        } else {
+            get_delta_amount_0_unsigned(sqrt_price_current_x64, sqrt_price_target_x64, liquidity, false)?
+        };
+        if let Some(amount_out) = amount_out {
+            step.amount_out = amount_out;
+        }
+        step.sqrt_price_next_x64 = match amount_out {
+            Some(amount_out) if amount_remaining >= amount_out => sqrt_price_target_x64,
+            _ => next_sqrt_price_from_output(sqrt_price_current_x64, liquidity, amount_remaining, zero_for_one)?,
+        };
+    }
+
+    let reached_target = sqrt_price_target_x64 == step.sqrt_price_next_x64;
+    let next = step.sqrt_price_next_x64;
+    // 触及目标价时，指定侧沿用区间整量，仅重算另一侧
+    let recompute_in = !(reached_target && is_base_input);
+    let recompute_out = !reached_target || is_base_input;
+    if zero_for_one {
+        if recompute_in {
+            step.amount_in = get_delta_amount_0_unsigned(next, sqrt_price_current_x64, liquidity, true)?.ok_or_else(overflow)?;
+        }
+        if recompute_out {
+            step.amount_out = get_delta_amount_1_unsigned(next, sqrt_price_current_x64, liquidity, false)?.ok_or_else(overflow)?;
+        }
+    } else {
+        if recompute_in {
+            step.amount_in = get_delta_amount_1_unsigned(sqrt_price_current_x64, next, liquidity, true)?.ok_or_else(overflow)?;
+        }
+        if recompute_out {
+            step.amount_out = get_delta_amount_0_unsigned(sqrt_price_current_x64, next, liquidity, false)?.ok_or_else(overflow)?;
+        }
+    }
+    if !is_base_input && step.amount_out > amount_remaining {
+        step.amount_out = amount_remaining;
+    }
+
+    step.fee_amount = if is_base_input && step.sqrt_price_next_x64 != sqrt_price_target_x64 {
+        // 未触及目标价：剩余输入全部用尽，差额即手续费
+        amount_remaining.checked_sub(step.amount_in).ok_or_else(overflow)?
+    } else {
+        u64::try_from(
+            ((step.amount_in as u128) * (fee_rate as u128))
+                .div_ceil((CLMM_FEE_RATE_DENOMINATOR - fee_rate) as u128),
+        )
+        .map_err(|_| overflow())?
+    };
+    Ok(step)
+}
+
+// ================================================================
+// 跨 tick 模拟
+// ================================================================
+
+/// CLMM 报价结果（amount_in 含手续费）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClmmSwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+    /// 成交后价格
+    pub sqrt_price_x64: u128,
+}
+
+/// 在 tick array 内查找方向上的下一个已初始化 tick（raydium-clmm `next_initialized_tick` / `first_initialized_tick`）
+fn next_initialized_tick(
+    array: &TickArrayState,
+    current_tick: i32,
+    tick_spacing: u16,
+    zero_for_one: bool,
+    from_edge: bool,
+) -> Option<TickState> {
+    let last = RAYDIUM_CLMM_TICK_ARRAY_SIZE - 1;
+    let (lo, hi) = if from_edge {
+        (0, last)
+    } else {
+        let offset = (current_tick - array.start_tick_index) / tick_spacing as i32;
+        if zero_for_one { (0, offset) } else { (offset + 1, last) }
+    };
+    let initialized = |i: i32| array.ticks.get(i as usize).filter(|t| t.is_initialized()).copied();
+    if zero_for_one {
+        (lo..=hi).rev().find_map(initialized)
+    } else {
+        (lo..=hi).find_map(initialized)
+    }
+}
+
+/// 按池状态与方向上的 tick arrays（首个须覆盖当前 tick 或位于其前方）模拟 `swap_v2`
+///
+/// `amount_specified`：精确输入时为输入量，精确输出时为目标产出；`sqrt_price_limit_x64` 为 0 时取协议边界。
+/// 提供的 tick arrays 不足以完成成交时返回 `InsufficientLiquidity`（需追加 tick array）。
+pub fn clmm_swap(
+    pool: &ClmmPoolState,
+    trade_fee_rate: u32,
+    tick_arrays: &[TickArrayState],
+    zero_for_one: bool,
+    amount_specified: u64,
+    is_base_input: bool,
+    sqrt_price_limit_x64: u128,
+) -> Result<ClmmSwapQuote> {
+    clmm_swap_with(
+        pool,
+        trade_fee_rate,
+        |i| Ok(tick_arrays.get(i)),
+        zero_for_one,
+        amount_specified,
+        is_base_input,
+        sqrt_price_limit_x64,
+    )
+}
+
+/// 同 `clmm_swap`，tick arrays 按方向序号惰性加载（`tick_array_at(i)` 返回 `None` 表示已无更多数组）
+///
+/// 链上报价只解析实际走到的数组，避免一次性反序列化全部尾部账户占用堆。
+pub fn clmm_swap_with<A: Borrow<TickArrayState>>(
+    pool: &ClmmPoolState,
+    trade_fee_rate: u32,
+    mut tick_array_at: impl FnMut(usize) -> Result<Option<A>>,
+    zero_for_one: bool,
+    amount_specified: u64,
+    is_base_input: bool,
+    sqrt_price_limit_x64: u128,
+) -> Result<ClmmSwapQuote> {
+    require!(amount_specified > 0, ArbitrageError::InvalidAmount);
+    let sqrt_price_limit_x64 = match (sqrt_price_limit_x64, zero_for_one) {
+        (0, true) => RAYDIUM_CLMM_MIN_SQRT_PRICE_X64 + 1,
+        (0, false) => RAYDIUM_CLMM_MAX_SQRT_PRICE_X64 - 1,
+        (limit, _) => limit,
+    };
+    if zero_for_one {
+        require!(
+            sqrt_price_limit_x64 < pool.sqrt_price_x64 && sqrt_price_limit_x64 > RAYDIUM_CLMM_MIN_SQRT_PRICE_X64,
+            ArbitrageError::InvalidSqrtPriceLimit
+        );
+    } else {
+        require!(
+            sqrt_price_limit_x64 > pool.sqrt_price_x64 && sqrt_price_limit_x64 < RAYDIUM_CLMM_MAX_SQRT_PRICE_X64,
+            ArbitrageError::InvalidSqrtPriceLimit
+        );
+    }
+
+    let tick_spacing = pool.tick_spacing;
+    let mut remaining = amount_specified;
+    let mut calculated = 0u64;
+    let mut fee_total = 0u64;
+    let mut sqrt_price = pool.sqrt_price_x64;
+    let mut tick = pool.tick_current;
+    let mut liquidity = pool.liquidity;
+    let mut array_index = 0usize;
+    let mut fresh_array = true;
+    let mut loaded: Option<(usize, A)> = None;
+
+    while remaining != 0 && sqrt_price != sqrt_price_limit_x64 {
+        // 当前 tick array 内查找；越过本数组后切换到下一个（从方向边缘开始）
+        let next_tick = loop {
+            let array = match loaded.take() {
+                Some((index, array)) if index == array_index => array,
+                _ => tick_array_at(array_index)?.ok_or(ArbitrageError::InsufficientLiquidity)?,
+            };
+            let tick_array = array.borrow();
+            let in_array = tick_array_start_index(tick, tick_spacing) == tick_array.start_tick_index;
+            let found = if in_array {
+                next_initialized_tick(tick_array, tick, tick_spacing, zero_for_one, false)
+            } else if fresh_array {
+                next_initialized_tick(tick_array, tick, tick_spacing, zero_for_one, true)
+            } else {
+                None
+            };
+            loaded = Some((array_index, array));
+            fresh_array = false;
+            match found {
+                Some(t) => break t,
+                None => {
+                    array_index += 1;
+                    fresh_array = true;
+                }
+            }
+        };
+
+        let tick_next = next_tick.tick.clamp(RAYDIUM_CLMM_MIN_TICK, RAYDIUM_CLMM_MAX_TICK);
+        let sqrt_price_next = get_sqrt_price_at_tick(tick_next)?;
+        let target = if zero_for_one {
+            sqrt_price_next.max(sqrt_price_limit_x64)
+        } else {
+            sqrt_price_next.min(sqrt_price_limit_x64)
+        };
+        let step = compute_swap_step(sqrt_price, target, liquidity, remaining, trade_fee_rate, is_base_input, zero_for_one)?;
+        sqrt_price = step.sqrt_price_next_x64;
+
+        if is_base_input {
+            remaining = remaining
+                .checked_sub(step.amount_in.checked_add(step.fee_amount).ok_or_else(overflow)?)
+                .ok_or_else(overflow)?;
+            calculated = calculated.checked_add(step.amount_out).ok_or_else(overflow)?;
+        } else {
+            remaining = remaining.checked_sub(step.amount_out).ok_or_else(overflow)?;
+            calculated = calculated
+                .checked_add(step.amount_in.checked_add(step.fee_amount).ok_or_else(overflow)?)
+                .ok_or_else(overflow)?;
+        }
+        fee_total = fee_total.checked_add(step.fee_amount).ok_or_else(overflow)?;
+
+        if sqrt_price == sqrt_price_next {
+            // 穿越已初始化 tick：按方向应用 liquidity_net
+            let liquidity_net = if zero_for_one { -next_tick.liquidity_net } else { next_tick.liquidity_net };
+            liquidity = if liquidity_net < 0 {
+                liquidity.checked_sub(liquidity_net.unsigned_abs())
+            } else {
+                liquidity.checked_add(liquidity_net as u128)
+            }
+            .ok_or_else(overflow)?;
+            tick = if zero_for_one { tick_next - 1 } else { tick_next };
+        } else {
+            // 停在区间内部（输入耗尽或触及限价），循环随之结束
+            break;
+        }
+    }
+
+    let consumed = amount_specified - remaining;
+    let (amount_in, amount_out) = if is_base_input { (consumed, calculated) } else { (calculated, consumed) };
+    Ok(ClmmSwapQuote { amount_in, amount_out, fee_amount: fee_total, sqrt_price_x64: sqrt_price })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK_SPACING: u16 = 10;
+    // 基础仓位 [-590, 590) 与窄仓位 [-10, 10) 各提供 L，当前 tick 0 处流动性为 2L
+    const L: u128 = 4_000_000_000_000;
+
+    fn pool() -> ClmmPoolState {
+        ClmmPoolState {
+            amm_config: Pubkey::default(),
+            token_mint_0: Pubkey::default(),
+            token_mint_1: Pubkey::default(),
+            token_vault_0: Pubkey::default(),
+            token_vault_1: Pubkey::default(),
+            observation_key: Pubkey::default(),
+            tick_spacing: TICK_SPACING,
+            liquidity: 2 * L,
+            sqrt_price_x64: 1 << 64,
+            tick_current: 0,
+            status: 0,
+            tick_array_bitmap: [0; 16],
+        }
+    }
+
+    fn tick_array(start_tick_index: i32, initialized: &[(i32, i128)]) -> TickArrayState {
+        let mut ticks = vec![TickState::default(); RAYDIUM_CLMM_TICK_ARRAY_SIZE as usize];
+        for &(tick, liquidity_net) in initialized {
+            let offset = ((tick - start_tick_index) / TICK_SPACING as i32) as usize;
+            ticks[offset] = TickState { tick, liquidity_net, liquidity_gross: liquidity_net.unsigned_abs() };
+        }
+        TickArrayState { pool_id: Pubkey::default(), start_tick_index, ticks }
+    }
+
+    /// 按交换方向排列的 tick arrays（首个覆盖当前 tick 0）
+    fn tick_arrays(zero_for_one: bool) -> Vec<TickArrayState> {
+        let upper = tick_array(0, &[(10, -(L as i128)), (590, -(L as i128))]);
+        let lower = tick_array(-600, &[(-590, L as i128), (-10, L as i128)]);
+        if zero_for_one { vec![upper, lower] } else { vec![upper] }
+    }
+
+    fn swap(zero_for_one: bool, amount: u64, fee_rate: u32, is_base_input: bool, limit: u128) -> Result<ClmmSwapQuote> {
+        clmm_swap(&pool(), fee_rate, &tick_arrays(zero_for_one), zero_for_one, amount, is_base_input, limit)
+    }
+
+    #[test]
+    fn sqrt_price_at_tick_matches_reference() {
+        // 协议边界与 raydium-clmm MIN/MAX_SQRT_PRICE_X64 逐位一致
+        assert_eq!(get_sqrt_price_at_tick(RAYDIUM_CLMM_MIN_TICK).unwrap(), RAYDIUM_CLMM_MIN_SQRT_PRICE_X64);
+        assert_eq!(get_sqrt_price_at_tick(RAYDIUM_CLMM_MAX_TICK).unwrap(), RAYDIUM_CLMM_MAX_SQRT_PRICE_X64);
+        for tick in [RAYDIUM_CLMM_MIN_TICK - 1, RAYDIUM_CLMM_MAX_TICK + 1] {
+            assert_eq!(get_sqrt_price_at_tick(tick).unwrap_err(), ArbitrageError::InvalidSqrtPriceLimit.into());
+        }
+
+        // (tick, floor(sqrt(1.0001^tick) × 2^64))：高精度十进制求得；Q64 定点近似全域相对误差 < 2e-10
+        let cases: [(i32, u128); 9] = [
+            (-443636, 4_295_048_016),
+            (-443635, 4_295_262_763),
+            (-200000, 837_899_702_510_258),
+            (-1, 18_445_821_805_675_392_311),
+            (0, 1 << 64),
+            (1, 18_447_666_387_855_959_850),
+            (200000, 406_113_483_393_643_373_014_939),
+            (443635, 79_222_712_478_800_779_441_888_593_669),
+            (443636, 79_226_673_515_401_279_992_447_579_061),
+        ];
+        let mut previous = 0;
+        for (tick, reference) in cases {
+            let price = get_sqrt_price_at_tick(tick).unwrap();
+            assert!(price.abs_diff(reference) <= reference / 1_000_000_000, "tick {tick}: {price} vs {reference}");
+            assert!(price > previous, "not monotonic at tick {tick}");
+            previous = price;
+        }
+    }
+
+    #[test]
+    fn tick_array_start_index_rounds_toward_negative_infinity() {
+        // (tick, tick_spacing, start)
+        let cases = [
+            (0, 1, 0),
+            (59, 1, 0),
+            (60, 1, 60),
+            (-1, 1, -60),
+            (-60, 1, -60),
+            (-61, 1, -120),
+            (-600, 10, -600),
+            (-601, 10, -1200),
+            (RAYDIUM_CLMM_MIN_TICK, 1, -443_640),
+            (RAYDIUM_CLMM_MAX_TICK, 1, 443_580),
+            (RAYDIUM_CLMM_MIN_TICK, 10, -444_000),
+            (RAYDIUM_CLMM_MAX_TICK, 10, 443_400),
+        ];
+        for (tick, spacing, start) in cases {
+            assert_eq!(tick_array_start_index(tick, spacing), start, "tick {tick} spacing {spacing}");
+        }
+    }
+
+    #[test]
+    fn clmm_swap_vectors() {
+        // 期望值由独立的大整数参考实现（raydium-clmm swap_math/sqrt_price_math 公式）求得
+        // (zero_for_one, is_base_input, amount, fee_rate, limit) → (amount_in, amount_out, fee, sqrt_price)
+        let cases = [
+            // 区间内精确输入
+            ((false, true, 1_000_000, 0, 0), (1_000_000, 999_999, 0, 18_446_746_379_552_560_829)),
+            ((true, true, 1_000_000, 2500, 0), (1_000_000, 997_499, 2500, 18_446_741_773_631_436_717)),
+            // 穿越 tick 10 / -10：流动性由 2L 降为 L
+            ((false, true, 10_000_000_000, 2500, 0), (10_000_000_000, 9_958_133_169, 25_000_001, 18_483_520_424_838_418_536)),
+            ((true, true, 10_000_000_000, 500, 0), (10_000_000_000, 9_978_053_561, 5_000_001, 18_409_949_029_275_487_955)),
+            // 精确输出（区间内 / 穿越）
+            ((false, false, 1_000_000_000, 2500, 0), (1_002_631_595, 1_000_000_000, 2_506_579, 18_449_050_204_985_174_763)),
+            ((false, false, 6_000_000_000, 2500, 0), (6_020_055_247, 6_000_000_000, 15_050_139, 18_465_212_054_904_466_428)),
+            ((true, false, 6_000_000_000, 100, 0), (6_005_605_670, 6_000_000_000, 600_562, 18_428_294_563_265_387_003)),
+        ];
+        for ((zero_for_one, is_base_input, amount, fee_rate, limit), (amount_in, amount_out, fee_amount, sqrt_price_x64)) in
+            cases
+        {
+            assert_eq!(
+                swap(zero_for_one, amount, fee_rate, is_base_input, limit).unwrap(),
+                ClmmSwapQuote { amount_in, amount_out, fee_amount, sqrt_price_x64 },
+                "zero_for_one={zero_for_one} base_input={is_base_input} amount={amount}"
+            );
+        }
+    }
+
+    #[test]
+    fn clmm_swap_crossing_applies_liquidity_net() {
+        let sqrt_price_10 = get_sqrt_price_at_tick(10).unwrap();
+        let quote = swap(false, 10_000_000_000, 2500, true, 0).unwrap();
+        assert!(quote.sqrt_price_x64 > sqrt_price_10);
+
+        // 分段复算：tick 0 → 10 以 2L 成交，其余以 L 成交
+        let first = compute_swap_step(1 << 64, sqrt_price_10, 2 * L, 10_000_000_000, 2500, true, false).unwrap();
+        assert_eq!(first.sqrt_price_next_x64, sqrt_price_10);
+        let remaining = 10_000_000_000 - first.amount_in - first.fee_amount;
+        let target = get_sqrt_price_at_tick(590).unwrap();
+        let second = compute_swap_step(sqrt_price_10, target, L, remaining, 2500, true, false).unwrap();
+        assert_eq!(quote.amount_out, first.amount_out + second.amount_out);
+        assert_eq!(quote.fee_amount, first.fee_amount + second.fee_amount);
+        assert_eq!(quote.sqrt_price_x64, second.sqrt_price_next_x64);
+
+        // 若 tick 10 未初始化（不减流动性），同样输入的产出更多
+        let flat = vec![tick_array(0, &[(590, -(2 * L as i128))])];
+        let no_cross = clmm_swap(&pool(), 2500, &flat, false, 10_000_000_000, true, 0).unwrap();
+        assert!(no_cross.amount_out > quote.amount_out);
+    }
+
+    #[test]
+    fn clmm_swap_exact_in_and_exact_out_round_trip() {
+        for (zero_for_one, amount, fee_rate) in
+            [(false, 1_000_000, 2500), (true, 1_000_000, 100), (false, 10_000_000_000, 2500), (true, 10_000_000_000, 500)]
+        {
+            let exact_in = swap(zero_for_one, amount, fee_rate, true, 0).unwrap();
+            let exact_out = swap(zero_for_one, exact_in.amount_out, fee_rate, false, 0).unwrap();
+            assert_eq!(exact_out.amount_out, exact_in.amount_out);
+            // 输入向上、产出向下取整：反推的输入不超过原输入，且按其精确输入不少于目标产出
+            assert!(exact_out.amount_in <= amount, "zero_for_one={zero_for_one} amount={amount}: {exact_out:?}");
+            let again = swap(zero_for_one, exact_out.amount_in, fee_rate, true, 0).unwrap();
+            assert!(again.amount_out + 1 >= exact_in.amount_out, "{again:?} vs {exact_in:?}");
+        }
+    }
+
+    #[test]
+    fn clmm_swap_limits_and_missing_arrays() {
+        // 到价即停：部分成交，价格恰为限价
+        let limit = get_sqrt_price_at_tick(5).unwrap();
+        assert_eq!(
+            swap(false, 10_000_000_000, 2500, true, limit).unwrap(),
+            ClmmSwapQuote { amount_in: 2_005_162_911, amount_out: 1_999_650_052, fee_amount: 5_012_908, sqrt_price_x64: limit }
+        );
+        // 限价须在当前价格的交换方向一侧且在协议边界内
+        for (zero_for_one, limit) in [(false, 1u128 << 64), (true, 1u128 << 64), (true, RAYDIUM_CLMM_MIN_SQRT_PRICE_X64)] {
+            assert_eq!(swap(zero_for_one, 1_000_000, 2500, true, limit).unwrap_err(), ArbitrageError::InvalidSqrtPriceLimit.into());
+        }
+        // 只给当前数组时向下穿越需要下一个数组
+        let upper_only = tick_arrays(false);
+        assert_eq!(
+            clmm_swap(&pool(), 2500, &upper_only, true, 1_000_000, true, 0).unwrap_err(),
+            ArbitrageError::InsufficientLiquidity.into()
+        );
+        assert_eq!(swap(false, 0, 2500, true, 0).unwrap_err(), ArbitrageError::InvalidAmount.into());
+    }
+}
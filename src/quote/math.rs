@@ -0,0 +1,358 @@
+//! 纯函数报价数学（不读账户），舍入方向与各协议链上实现保持一致：
+//! 手续费向上取整、产出向下取整、精确输出所需输入向上取整，因而报价不会优于实际成交。
+//! CLMM 跨 tick 模拟见 `quote::clmm`。
+
+use anchor_lang::prelude::*;
+use crate::errors::ArbitrageError;
+
+/// 万分比分母（PumpFun/PumpSwap fee bps）
+pub const BPS_DENOMINATOR: u128 = 10_000;
+/// Raydium CPMM fee rate 分母
+pub const CPMM_FEE_RATE_DENOMINATOR: u128 = 1_000_000;
+
+#[inline]
+fn ceil_div(numerator: u128, denominator: u128) -> Result<u128> {
+    require!(denominator > 0, ArbitrageError::MathOverflow);
+    Ok(numerator.div_ceil(denominator))
+}
+
+#[inline]
+fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| ArbitrageError::MathOverflow.into())
+}
+
+/// 恒定乘积精确输入：`amount_in * reserve_out / (reserve_in + amount_in)`（向下取整）
+pub fn constant_product_out(amount_in: u64, reserve_in: u64, reserve_out: u64) -> Result<u64> {
+    let denominator = (reserve_in as u128)
+        .checked_add(amount_in as u128)
+        .ok_or(ArbitrageError::MathOverflow)?;
+    if denominator == 0 {
+        return Ok(0);
+    }
+    to_u64((amount_in as u128) * (reserve_out as u128) / denominator)
+}
+
+/// Raydium CPMM `swap_base_input`：先扣 trade fee（ceil），再按恒定乘积；返回 (amount_out, trade_fee)
+pub fn cpmm_swap_base_input(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    trade_fee_rate: u64,
+) -> Result<(u64, u64)> {
+    let trade_fee = to_u64(ceil_div(
+        (amount_in as u128) * (trade_fee_rate as u128),
+        CPMM_FEE_RATE_DENOMINATOR,
+    )?)?;
+    let amount_in_less_fee = amount_in.checked_sub(trade_fee).ok_or(ArbitrageError::MathOverflow)?;
+    let amount_out = constant_product_out(amount_in_less_fee, reserve_in, reserve_out)?;
+    Ok((amount_out, trade_fee))
+}
+
+/// Raydium CPMM `swap_base_output`：先按恒定乘积反推（ceil），再按 trade fee 反推含费输入（ceil）；返回 (amount_in, trade_fee)
+pub fn cpmm_swap_base_output(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    trade_fee_rate: u64,
+) -> Result<(u64, u64)> {
+    let amount_in_less_fee = constant_product_in(amount_out, reserve_in, reserve_out)?;
+    let amount_in = if trade_fee_rate == 0 {
+        amount_in_less_fee
+    } else {
+        let denominator = CPMM_FEE_RATE_DENOMINATOR
+            .checked_sub(trade_fee_rate as u128)
+            .ok_or(ArbitrageError::MathOverflow)?;
+        to_u64(ceil_div((amount_in_less_fee as u128) * CPMM_FEE_RATE_DENOMINATOR, denominator)?)?
+    };
+    Ok((amount_in, amount_in - amount_in_less_fee))
+}
+
+/// 恒定乘积精确输出：`ceil(amount_out * reserve_in / (reserve_out - amount_out))`；产出须小于储备
+pub fn constant_product_in(amount_out: u64, reserve_in: u64, reserve_out: u64) -> Result<u64> {
+    require!(amount_out < reserve_out, ArbitrageError::InsufficientLiquidity);
+    to_u64(ceil_div(
+        (amount_out as u128) * (reserve_in as u128),
+        (reserve_out - amount_out) as u128,
+    )?)
+}
+
+/// PumpFun 买入（SOL → token）：手续费按 bps 加收在 SOL 之上，返回 (token_out, fee)；产出受 real_token_reserves 封顶
+pub fn pumpfun_buy(
+    sol_in: u64,
+    virtual_sol_reserves: u64,
+    virtual_token_reserves: u64,
+    real_token_reserves: u64,
+    total_fee_bps: u64,
+) -> Result<(u64, u64)> {
+    let net_sol = to_u64((sol_in as u128) * BPS_DENOMINATOR / (BPS_DENOMINATOR + total_fee_bps as u128))?;
+    let fee = sol_in - net_sol;
+    let tokens = constant_product_out(net_sol, virtual_sol_reserves, virtual_token_reserves)?;
+    Ok((tokens.min(real_token_reserves), fee))
+}
+
+/// PumpFun 卖出（token → SOL）：按虚拟储备换算后扣除手续费（ceil），返回 (sol_out, fee)
+pub fn pumpfun_sell(
+    token_in: u64,
+    virtual_token_reserves: u64,
+    virtual_sol_reserves: u64,
+    total_fee_bps: u64,
+) -> Result<(u64, u64)> {
+    let gross = constant_product_out(token_in, virtual_token_reserves, virtual_sol_reserves)?;
+    let fee = to_u64(ceil_div((gross as u128) * (total_fee_bps as u128), BPS_DENOMINATOR)?)?;
+    Ok((gross.saturating_sub(fee), fee))
+}
+
+/// PumpFun 按 token 数量买入（链上 `buy(amount, max_sol_cost)`）：
+/// 成本 `amount * vsr / (vtr - amount) + 1`，手续费（ceil）加收其上；返回 (sol_in, fee)
+pub fn pumpfun_buy_exact_out(
+    token_out: u64,
+    virtual_sol_reserves: u64,
+    virtual_token_reserves: u64,
+    real_token_reserves: u64,
+    total_fee_bps: u64,
+) -> Result<(u64, u64)> {
+    require!(
+        token_out <= real_token_reserves && token_out < virtual_token_reserves,
+        ArbitrageError::InsufficientLiquidity
+    );
+    let sol_cost = to_u64(
+        (token_out as u128) * (virtual_sol_reserves as u128) / ((virtual_token_reserves - token_out) as u128) + 1,
+    )?;
+    let fee = to_u64(ceil_div((sol_cost as u128) * (total_fee_bps as u128), BPS_DENOMINATOR)?)?;
+    Ok((sol_cost.checked_add(fee).ok_or(ArbitrageError::MathOverflow)?, fee))
+}
+
+/// PumpFun 以 SOL 预算买入：链上只有按 token 数量成交的 `buy`，取预算内可买的最大 token 数量。
+/// 精确输入报价换算回 `buy` 成本时可能因 +1 与手续费取整超出预算，按超出部分收紧预算重算；返回 (token_out, sol_in)
+pub fn pumpfun_buy_within_budget(
+    sol_budget: u64,
+    virtual_sol_reserves: u64,
+    virtual_token_reserves: u64,
+    real_token_reserves: u64,
+    total_fee_bps: u64,
+) -> Result<(u64, u64)> {
+    let mut budget = sol_budget;
+    loop {
+        let (tokens, _) =
+            pumpfun_buy(budget, virtual_sol_reserves, virtual_token_reserves, real_token_reserves, total_fee_bps)?;
+        if tokens == 0 {
+            return Ok((0, 0));
+        }
+        let (cost, _) = pumpfun_buy_exact_out(
+            tokens,
+            virtual_sol_reserves,
+            virtual_token_reserves,
+            real_token_reserves,
+            total_fee_bps,
+        )?;
+        if cost <= sol_budget {
+            return Ok((tokens, cost));
+        }
+        budget = budget.saturating_sub(cost - sol_budget);
+    }
+}
+
+/// PumpSwap 买入（quote → base）：lp/protocol/creator 费按 bps 加收在 quote 之上，返回 (base_out, fee)
+pub fn pumpswap_buy(
+    quote_in: u64,
+    base_reserve: u64,
+    quote_reserve: u64,
+    total_fee_bps: u64,
+) -> Result<(u64, u64)> {
+    let net_quote = to_u64((quote_in as u128) * BPS_DENOMINATOR / (BPS_DENOMINATOR + total_fee_bps as u128))?;
+    let fee = quote_in - net_quote;
+    let base_out = constant_product_out(net_quote, quote_reserve, base_reserve)?;
+    Ok((base_out, fee))
+}
+
+/// PumpSwap 卖出（base → quote）：各项费率分别向上取整后从产出中扣除，返回 (quote_out, fee)
+pub fn pumpswap_sell(
+    base_in: u64,
+    base_reserve: u64,
+    quote_reserve: u64,
+    fee_bps: &[u64],
+) -> Result<(u64, u64)> {
+    let gross = constant_product_out(base_in, base_reserve, quote_reserve)?;
+    let mut fee = 0u64;
+    for &bps in fee_bps {
+        let part = to_u64(ceil_div((gross as u128) * (bps as u128), BPS_DENOMINATOR)?)?;
+        fee = fee.checked_add(part).ok_or(ArbitrageError::MathOverflow)?;
+    }
+    Ok((gross.saturating_sub(fee), fee))
+}
+
+/// PumpSwap 按 base 数量买入（链上 `buy(base_amount_out, max_quote_amount_in)`）：
+/// 原始成本按恒定乘积反推（ceil），各项费率分别向上取整后加收；返回 (quote_in, fee)
+pub fn pumpswap_buy_exact_out(
+    base_out: u64,
+    base_reserve: u64,
+    quote_reserve: u64,
+    fee_bps: &[u64],
+) -> Result<(u64, u64)> {
+    let quote_raw = constant_product_in(base_out, quote_reserve, base_reserve)?;
+    let mut fee = 0u64;
+    for &bps in fee_bps {
+        let part = to_u64(ceil_div((quote_raw as u128) * (bps as u128), BPS_DENOMINATOR)?)?;
+        fee = fee.checked_add(part).ok_or(ArbitrageError::MathOverflow)?;
+    }
+    Ok((quote_raw.checked_add(fee).ok_or(ArbitrageError::MathOverflow)?, fee))
+}
+
+/// PumpSwap 以 quote 预算买入：同 `pumpfun_buy_within_budget`，按 `buy` 的成本（各项费率分别取整）收紧预算；
+/// 返回 (base_out, quote_in)
+pub fn pumpswap_buy_within_budget(
+    quote_budget: u64,
+    base_reserve: u64,
+    quote_reserve: u64,
+    fee_bps: &[u64],
+) -> Result<(u64, u64)> {
+    let total_fee_bps = fee_bps.iter().sum();
+    let mut budget = quote_budget;
+    loop {
+        let (base_out, _) = pumpswap_buy(budget, base_reserve, quote_reserve, total_fee_bps)?;
+        if base_out == 0 {
+            return Ok((0, 0));
+        }
+        let (cost, _) = pumpswap_buy_exact_out(base_out, base_reserve, quote_reserve, fee_bps)?;
+        if cost <= quote_budget {
+            return Ok((base_out, cost));
+        }
+        budget = budget.saturating_sub(cost - quote_budget);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // PumpFun 初始曲线（虚拟 SOL 30、虚拟 token 1.073e9、真实 token 7.931e8，6 位精度）
+    const PUMPFUN_VSR: u64 = 30_000_000_000;
+    const PUMPFUN_VTR: u64 = 1_073_000_000_000_000;
+    const PUMPFUN_RTR: u64 = 793_100_000_000_000;
+    const PUMPSWAP_BASE: u64 = 200_000_000_000_000;
+    const PUMPSWAP_QUOTE: u64 = 85_000_000_000;
+    const PUMPSWAP_FEES: [u64; 3] = [20, 5, 5];
+
+    #[test]
+    fn cpmm_swap_base_input_vectors() {
+        // (amount_in, reserve_in, reserve_out, trade_fee_rate) → (amount_out, trade_fee)
+        let cases = [
+            ((1_000_000, 1_000_000_000, 2_000_000_000, 2500), (1_993_011, 2500)),
+            // 2.5025 → 手续费向上取整为 3
+            ((1001, 5_000_000, 7_000_000, 2500), (1396, 3)),
+            ((123_456_789, 1_000_000_000_000, 300_000_000_000, 0), (37_032_464, 0)),
+        ];
+        for ((amount_in, reserve_in, reserve_out, rate), expected) in cases {
+            assert_eq!(cpmm_swap_base_input(amount_in, reserve_in, reserve_out, rate).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn cpmm_swap_base_output_vectors_round_trip() {
+        // (amount_out, reserve_in, reserve_out, trade_fee_rate) → (amount_in, trade_fee)
+        let cases = [
+            ((1_990_000, 1_000_000_000, 2_000_000_000, 2500), (998_489, 2497)),
+            ((1000, 5_000_000, 7_000_000, 10_000), (723, 8)),
+            ((999_999, 1_000_000, 1_000_000_000, 0), (1001, 0)),
+        ];
+        for ((amount_out, reserve_in, reserve_out, rate), expected) in cases {
+            let (amount_in, fee) = cpmm_swap_base_output(amount_out, reserve_in, reserve_out, rate).unwrap();
+            assert_eq!((amount_in, fee), expected);
+            // 反推的输入按精确输入成交不少于目标产出
+            let (out, _) = cpmm_swap_base_input(amount_in, reserve_in, reserve_out, rate).unwrap();
+            assert!(out >= amount_out, "round trip {amount_out} → {amount_in} → {out}");
+        }
+        assert_eq!(
+            cpmm_swap_base_output(2_000_000_000, 1_000_000_000, 2_000_000_000, 2500).unwrap_err(),
+            ArbitrageError::InsufficientLiquidity.into()
+        );
+    }
+
+    #[test]
+    fn pumpfun_vectors() {
+        // 买入：手续费加收在 SOL 之上，产出受 real_token_reserves 封顶
+        let buys = [
+            ((1_000_000_000, PUMPFUN_RTR), (34_281_150_129_545, 9_900_991)),
+            ((100_000_000_000, 500_000_000_000_000), (500_000_000_000_000, 990_099_010)),
+            ((1_000_000_000_000_000, PUMPFUN_RTR), (PUMPFUN_RTR, 9_900_990_099_010)),
+        ];
+        for ((sol_in, real), expected) in buys {
+            assert_eq!(pumpfun_buy(sol_in, PUMPFUN_VSR, PUMPFUN_VTR, real, 100).unwrap(), expected);
+        }
+
+        let sells = [(34_612_903_225_806, (928_124_999, 9_375_000)), (1, (0, 0))];
+        for (token_in, expected) in sells {
+            assert_eq!(pumpfun_sell(token_in, PUMPFUN_VTR, PUMPFUN_VSR, 100).unwrap(), expected);
+        }
+
+        // 按 token 数量买入：成本 + 1 lamport，手续费向上取整
+        assert_eq!(
+            pumpfun_buy_exact_out(34_612_903_225_806, PUMPFUN_VSR, PUMPFUN_VTR, PUMPFUN_RTR, 100).unwrap(),
+            (1_010_000_000, 10_000_000)
+        );
+        assert_eq!(
+            pumpfun_buy_exact_out(PUMPFUN_RTR + 1, PUMPFUN_VSR, PUMPFUN_VTR, PUMPFUN_RTR, 100).unwrap_err(),
+            ArbitrageError::InsufficientLiquidity.into()
+        );
+    }
+
+    #[test]
+    fn pumpfun_exact_out_cost_covers_exact_in() {
+        for token_out in [1_000_000, 34_612_903_225_806, 500_000_000_000_000] {
+            let (sol_in, _) = pumpfun_buy_exact_out(token_out, PUMPFUN_VSR, PUMPFUN_VTR, PUMPFUN_RTR, 100).unwrap();
+            let (tokens, _) = pumpfun_buy(sol_in, PUMPFUN_VSR, PUMPFUN_VTR, PUMPFUN_RTR, 100).unwrap();
+            assert!(tokens >= token_out, "{token_out} → {sol_in} → {tokens}");
+        }
+    }
+
+    #[test]
+    fn pumpswap_vectors() {
+        assert_eq!(pumpswap_buy(1_000_000_000, PUMPSWAP_BASE, PUMPSWAP_QUOTE, 30).unwrap(), (2_318_706_161_775, 2_991_027));
+        assert_eq!(
+            pumpswap_sell(2_000_000_000_000, PUMPSWAP_BASE, PUMPSWAP_QUOTE, &PUMPSWAP_FEES).unwrap(),
+            (839_059_403, 2_524_755)
+        );
+        let (quote_in, fee) =
+            pumpswap_buy_exact_out(2_000_000_000_000, PUMPSWAP_BASE, PUMPSWAP_QUOTE, &PUMPSWAP_FEES).unwrap();
+        assert_eq!((quote_in, fee), (861_161_617, 2_575_758));
+        // 各项费率分别向上取整：不少于合并费率的一次取整
+        let (raw_in, _) = pumpswap_buy_exact_out(2_000_000_000_000, PUMPSWAP_BASE, PUMPSWAP_QUOTE, &[]).unwrap();
+        assert!(fee >= (raw_in as u128 * 30).div_ceil(BPS_DENOMINATOR) as u64);
+        assert_eq!(
+            pumpswap_buy_exact_out(PUMPSWAP_BASE, PUMPSWAP_BASE, PUMPSWAP_QUOTE, &PUMPSWAP_FEES).unwrap_err(),
+            ArbitrageError::InsufficientLiquidity.into()
+        );
+    }
+
+    #[test]
+    fn pumpfun_buy_within_budget_never_exceeds_budget() {
+        for sol_budget in [1, 1_000, 1_000_000_000, 1_010_000_000, 85_000_000_000, 1_000_000_000_000_000] {
+            let (tokens, cost) =
+                pumpfun_buy_within_budget(sol_budget, PUMPFUN_VSR, PUMPFUN_VTR, PUMPFUN_RTR, 100).unwrap();
+            assert!(cost <= sol_budget, "{sol_budget} → {tokens} costs {cost}");
+            if tokens > 0 {
+                let (exact_cost, _) =
+                    pumpfun_buy_exact_out(tokens, PUMPFUN_VSR, PUMPFUN_VTR, PUMPFUN_RTR, 100).unwrap();
+                assert_eq!(cost, exact_cost);
+            }
+            // 与精确输入报价相差至多取整误差
+            let (quoted, _) = pumpfun_buy(sol_budget, PUMPFUN_VSR, PUMPFUN_VTR, PUMPFUN_RTR, 100).unwrap();
+            assert!(tokens <= quoted);
+        }
+    }
+
+    #[test]
+    fn pumpswap_buy_within_budget_never_exceeds_budget() {
+        for quote_budget in [1, 1_000, 1_000_000_000, 861_161_617, 85_000_000_000] {
+            let (base_out, cost) =
+                pumpswap_buy_within_budget(quote_budget, PUMPSWAP_BASE, PUMPSWAP_QUOTE, &PUMPSWAP_FEES).unwrap();
+            assert!(cost <= quote_budget, "{quote_budget} → {base_out} costs {cost}");
+            let (quoted, _) = pumpswap_buy(quote_budget, PUMPSWAP_BASE, PUMPSWAP_QUOTE, 30).unwrap();
+            assert!(base_out <= quoted);
+        }
+        // 按 base 数量买入 2e12 的成本恰好足够买回 2e12
+        let (base_out, _) =
+            pumpswap_buy_within_budget(861_161_617, PUMPSWAP_BASE, PUMPSWAP_QUOTE, &PUMPSWAP_FEES).unwrap();
+        assert!(base_out >= 2_000_000_000_000);
+    }
+}
@@ -0,0 +1,287 @@
+//! 链上预报价（只读，不发起 CPI）
+//!
+//! 按当前池状态模拟路径产出：CPMM 取 vault 余额扣除未提取的 protocol/fund 费作为储备，
+//! CLMM 取池价格/流动性并沿该步尾部 tick arrays 模拟跨 tick 成交，
+//! PumpFun 取 bonding curve 虚拟储备，PumpSwap 取池两侧 vault 余额；费率读自各自的 config/global 账户。
+//! 账户解析、池归属/成员与两侧 mint 校验复用执行路径（`DexRouter::resolve_accounts` + `validate_dex_accounts`
+//! + `validate_step_mints`），报价所用的池与实际成交的池一致。
+//!
+//! 账户反序列化（`state`）与报价数学（`math`、CLMM 的 `clmm`）为纯函数，客户端与链上共用。
+//!
+//! 链上暂不支持的场景（ExactOut）返回 `None`，由调用方决定按“无法判定”处理。
+
+pub mod math;
+pub mod state;
+pub mod clmm;
+
+pub use math::*;
+pub use state::*;
+pub use clmm::*;
+
+use anchor_lang::prelude::*;
+use crate::account_derivation::types::pda_utils;
+use crate::account_derivation::{DerivedAccounts, ProgramIds};
+use crate::account_resolver::validation::expected_program;
+use crate::account_resolver::{
+    validate_dex_accounts, validate_step_mints, AccountResolver, AccountTable, PumpfunAccounts, PumpswapAccounts,
+    RaydiumClmmAccounts, RaydiumCpmmAccounts,
+};
+use crate::dex_router::swaps::read_token_amount;
+use crate::dex_router::{DexAccounts, DexRouter, SwapResult};
+use crate::errors::ArbitrageError;
+use crate::external_programs::raydium_clmm;
+use crate::state::{AmountMode, DexType, StepView, SwapMode};
+use anchor_lang::Discriminator;
+
+/// 路径报价结果：每步为各腿聚合后的 (amount_in, amount_out, fee_amount)；亦作为 `quote_path` 指令的返回数据（Borsh）
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct PathQuote {
+    /// 首步报价输入（余额模式下取当前链上余额）
+    pub initial_amount: u64,
+    /// 末步报价产出
+    pub final_amount: u64,
+    pub steps: Vec<SwapResult>,
+    /// 所有腿报价均不低于各自 minimum_amount_out（否则实际执行会在该步回滚）
+    pub min_out_met: bool,
+}
+
+impl PathQuote {
+    /// 满足利润要求所需的最终产出
+    pub fn required_amount(&self, min_profit_lamports: u64) -> u64 {
+        self.initial_amount.saturating_add(min_profit_lamports)
+    }
+
+    pub fn is_profitable(&self, min_profit_lamports: u64) -> bool {
+        self.min_out_met && self.final_amount >= self.required_amount(min_profit_lamports)
+    }
+}
+
+/// 按当前池状态报价整条路径（含拆分腿）；任一腿无法报价时返回 `None`
+///
+/// 余额模式的步：首步取输入 ATA 当前余额，后续步按“当前余额 + 上一步报价产出”估算。
+pub fn quote_path<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    table: &AccountTable<'info>,
+    derived_accounts: &DerivedAccounts,
+    program_ids: &ProgramIds,
+    input_amount: u64,
+    path: &[StepView],
+) -> Result<Option<PathQuote>> {
+    // 报价期间不打印 resolver 角色日志（与执行期重复）
+    let resolver = AccountResolver::new(remaining_accounts, Default::default());
+    let wsol = derived_accounts
+        .get_fixed_addresses()
+        .ok_or(ArbitrageError::AccountNotFound)?
+        .wrapped_sol_mint;
+
+    let mut current_amount = input_amount;
+    let mut initial_amount = input_amount;
+    let mut steps = Vec::with_capacity(path.len());
+    let mut min_out_met = true;
+    for (step_index, step) in path.iter().enumerate() {
+        if step.swap_mode != SwapMode::ExactIn {
+            return Ok(None);
+        }
+        let user_input_key = derived_accounts
+            .get_user_token_account(&step.input_mint)
+            .ok_or(ArbitrageError::MissingTokenAccount)?;
+        let balance = read_token_amount(table.get(user_input_key)?)?;
+        let amount_in = projected_amount_in(step.amount_mode, current_amount, balance, step_index == 0)?;
+        if step_index == 0 {
+            initial_amount = amount_in;
+        }
+
+        let (primary_amount, leg_amounts) = DexRouter::split_amount(amount_in, step.split_legs)?;
+        let legs = std::iter::once((
+            step.dex_type,
+            step.mapping,
+            primary_amount,
+            step.minimum_amount_out,
+            step.sqrt_price_limit_x64,
+        ))
+        .chain(step.split_legs.iter().zip(&leg_amounts).map(|(leg, &amount)| {
+            (leg.dex_type, leg.mapping.view(), amount, leg.minimum_amount_out, leg.sqrt_price_limit_x64)
+        }));
+        let mut leg_quotes = Vec::with_capacity(1 + step.split_legs.len());
+        for (dex_type, leg_mapping, leg_amount, minimum_amount_out, sqrt_price_limit_x64) in legs {
+            let dex_accounts = DexRouter::resolve_accounts(&resolver, dex_type, leg_mapping)?;
+            validate_dex_accounts(&dex_accounts, program_ids)?;
+            validate_step_mints(&dex_accounts, &step.input_mint, &step.output_mint, &wsol)?;
+            let Some(quote) = quote_leg(
+                &dex_accounts,
+                table,
+                program_ids,
+                &wsol,
+                &step.input_mint,
+                leg_amount,
+                sqrt_price_limit_x64,
+            )?
+            else {
+                return Ok(None);
+            };
+            if quote.amount_out < minimum_amount_out {
+                min_out_met = false;
+            }
+            leg_quotes.push(quote);
+        }
+        let step_quote = DexRouter::aggregate_results(&leg_quotes)?;
+        current_amount = step_quote.amount_out;
+        steps.push(step_quote);
+    }
+
+    Ok(Some(PathQuote { initial_amount, final_amount: current_amount, steps, min_out_met }))
+}
+
+/// 单腿精确输入报价
+///
+/// Pump 系列买入与执行期一致：按 `buy` 成本把预算换算为可买的最大数量（`*_buy_within_budget`），
+/// `amount_in` 记实际花费；CLMM 触及 `sqrt_price_limit_x64` 时部分成交，`amount_in` 为实际消耗量。
+pub fn quote_leg<'info>(
+    accounts: &DexAccounts<'info>,
+    table: &AccountTable<'info>,
+    program_ids: &ProgramIds,
+    wsol: &Pubkey,
+    input_mint: &Pubkey,
+    amount_in: u64,
+    sqrt_price_limit_x64: u128,
+) -> Result<Option<SwapResult>> {
+    let quote = match accounts {
+        DexAccounts::RaydiumCpmm(a) => quote_raydium_cpmm(a, input_mint, amount_in)?,
+        DexAccounts::RaydiumClmm(a) => {
+            let quote = quote_raydium_clmm(a, input_mint, amount_in, sqrt_price_limit_x64)?;
+            SwapResult { amount_in: quote.amount_in, amount_out: quote.amount_out, fee_amount: quote.fee_amount }
+        }
+        DexAccounts::Pumpfun(a) => quote_pumpfun(a, table, program_ids, input_mint == wsol, amount_in)?,
+        DexAccounts::Pumpswap(a) => {
+            let is_buy = *input_mint != a.base_mint.key();
+            quote_pumpswap(a, table, program_ids, is_buy, amount_in)?
+        }
+    };
+    Ok(Some(quote))
+}
+
+/// 报价期的 amount_in 推算（与执行期 `resolve_step_amount_in` 语义对齐）
+fn projected_amount_in(mode: AmountMode, chained_amount: u64, balance: u64, is_first: bool) -> Result<u64> {
+    let projected_balance = if is_first { balance } else { balance.saturating_add(chained_amount) };
+    let amount_in = match mode {
+        AmountMode::Chained => chained_amount,
+        AmountMode::FullBalance => projected_balance,
+        AmountMode::BalanceBps(bps) => {
+            require!(bps > 0 && bps <= 10_000, ArbitrageError::InvalidAmount);
+            let scaled = (projected_balance as u128) * (bps as u128) / 10_000u128;
+            u64::try_from(scaled).map_err(|_| ArbitrageError::MathOverflow)?
+        }
+    };
+    require!(amount_in > 0, ArbitrageError::InvalidAmount);
+    Ok(amount_in)
+}
+
+/// CPMM：储备 = vault 余额 − 未提取 protocol/fund 费；trade_fee_rate 取自 AmmConfig
+fn quote_raydium_cpmm(accounts: &RaydiumCpmmAccounts, input_mint: &Pubkey, amount_in: u64) -> Result<SwapResult> {
+    let pool = CpmmPoolState::from_account_data(&accounts.pool_state.try_borrow_data()?)?;
+    let config = CpmmAmmConfig::from_account_data(&accounts.amm_config.try_borrow_data()?)?;
+    let (reserve0, reserve1) = pool.reserves(
+        read_token_amount(accounts.token0_vault)?,
+        read_token_amount(accounts.token1_vault)?,
+    );
+    let (reserve_in, reserve_out) =
+        if *input_mint == pool.token_0_mint { (reserve0, reserve1) } else { (reserve1, reserve0) };
+    let (amount_out, fee_amount) = math::cpmm_swap_base_input(amount_in, reserve_in, reserve_out, config.trade_fee_rate)?;
+    Ok(SwapResult { amount_in, amount_out, fee_amount })
+}
+
+/// CLMM：池价格/流动性 + 该步尾部 tick arrays（跳过居首的 bitmap extension，按传入顺序惰性解析）
+///
+/// 尾部数组已在 `validate_dex_accounts` 中核对归属本池；走出所给数组仍未成交完时返回 `InsufficientLiquidity`，
+/// 与实际 `swap_v2` 缺少 tick array 时失败一致。
+fn quote_raydium_clmm(
+    accounts: &RaydiumClmmAccounts,
+    input_mint: &Pubkey,
+    amount_in: u64,
+    sqrt_price_limit_x64: u128,
+) -> Result<ClmmSwapQuote> {
+    let pool = ClmmPoolState::from_account_data(&accounts.pool_state.try_borrow_data()?)?;
+    let config = ClmmAmmConfig::from_account_data(&accounts.amm_config.try_borrow_data()?)?;
+    let zero_for_one = *input_mint == pool.token_mint_0;
+    let skip = match accounts.dynamic_accounts.first() {
+        Some(first) => usize::from(
+            first.try_borrow_data()?.starts_with(raydium_clmm::accounts::TickArrayBitmapExtension::DISCRIMINATOR),
+        ),
+        None => 0,
+    };
+    let tick_arrays = &accounts.dynamic_accounts[skip..];
+    clmm_swap_with(
+        &pool,
+        config.trade_fee_rate,
+        |i| {
+            tick_arrays
+                .get(i)
+                .map(|info| TickArrayState::from_account_data(&info.try_borrow_data()?))
+                .transpose()
+        },
+        zero_for_one,
+        amount_in,
+        true,
+        sqrt_price_limit_x64,
+    )
+}
+
+/// PumpFun：虚拟储备报价；曲线已完成（迁移）时报价为 0
+fn quote_pumpfun<'info>(
+    accounts: &PumpfunAccounts<'info>,
+    table: &AccountTable<'info>,
+    program_ids: &ProgramIds,
+    is_buy: bool,
+    amount_in: u64,
+) -> Result<SwapResult> {
+    let curve = PumpFunBondingCurve::from_account_data(&accounts.bonding_curve.try_borrow_data()?)?;
+    if curve.complete {
+        return Ok(SwapResult { amount_in, amount_out: 0, fee_amount: 0 });
+    }
+    let (virtual_token, virtual_sol, real_token) =
+        (curve.virtual_token_reserves, curve.virtual_sol_reserves, curve.real_token_reserves);
+    let program = expected_program(program_ids, DexType::PumpFunBondingCurve, accounts.bonding_curve.owner);
+    let global_key = pda_utils::derive_pumpfun_global(accounts.bumps.global, &program)?;
+    let total_fee_bps = PumpFunGlobal::from_account_data(&table.get(&global_key)?.try_borrow_data()?)?.total_fee_bps();
+    if !is_buy {
+        let (amount_out, fee_amount) = math::pumpfun_sell(amount_in, virtual_token, virtual_sol, total_fee_bps)?;
+        return Ok(SwapResult { amount_in, amount_out, fee_amount });
+    }
+    let (tokens, cost) =
+        math::pumpfun_buy_within_budget(amount_in, virtual_sol, virtual_token, real_token, total_fee_bps)?;
+    let fee_amount = if tokens == 0 {
+        0
+    } else {
+        math::pumpfun_buy_exact_out(tokens, virtual_sol, virtual_token, real_token, total_fee_bps)?.1
+    };
+    Ok(SwapResult { amount_in: cost, amount_out: tokens, fee_amount })
+}
+
+/// PumpSwap：储备取 Pool 记录的两侧 vault 余额；lp/protocol/coin_creator 费率取自 GlobalConfig
+fn quote_pumpswap<'info>(
+    accounts: &PumpswapAccounts<'info>,
+    table: &AccountTable<'info>,
+    program_ids: &ProgramIds,
+    is_buy: bool,
+    amount_in: u64,
+) -> Result<SwapResult> {
+    let pool = PumpSwapPool::from_account_data(&accounts.pool_state.try_borrow_data()?)?;
+    let base_reserve = read_token_amount(table.get(&pool.pool_base_token_account)?)?;
+    let quote_reserve = read_token_amount(table.get(&pool.pool_quote_token_account)?)?;
+
+    let amm_program = expected_program(program_ids, DexType::PumpSwap, accounts.pool_state.owner);
+    let global_key = pda_utils::derive_pumpswap_global_config(accounts.bumps.global, &amm_program)?;
+    let fee_bps = PumpSwapGlobalConfig::from_account_data(&table.get(&global_key)?.try_borrow_data()?)?
+        .fee_bps(&accounts.coin_creator.key());
+    if !is_buy {
+        let (amount_out, fee_amount) = math::pumpswap_sell(amount_in, base_reserve, quote_reserve, &fee_bps)?;
+        return Ok(SwapResult { amount_in, amount_out, fee_amount });
+    }
+    let (base_out, cost) = math::pumpswap_buy_within_budget(amount_in, base_reserve, quote_reserve, &fee_bps)?;
+    let fee_amount = if base_out == 0 {
+        0
+    } else {
+        math::pumpswap_buy_exact_out(base_out, base_reserve, quote_reserve, &fee_bps)?.1
+    };
+    Ok(SwapResult { amount_in: cost, amount_out: base_out, fee_amount })
+}
@@ -0,0 +1,373 @@
+//! 外部 DEX 账户的只读反序列化（按 `pool_layouts` 字节偏移，链上与客户端共用）
+//!
+//! 只解析报价所需字段；输入为完整账户数据（含 8 字节 discriminator），长度不足返回 `InvalidPoolData`。
+//! 有 IDL 的账户（CLMM / PumpFun / PumpSwap）同时校验 discriminator 与 `external_programs` 生成的账户类型一致。
+
+use anchor_lang::prelude::*;
+use crate::account_derivation::types::pool_layouts as layout;
+use crate::account_resolver::validation::pubkey_at;
+use crate::dex_router::types::constants::RAYDIUM_CLMM_TICK_ARRAY_SIZE;
+use crate::errors::ArbitrageError;
+use crate::external_programs::{pumpfun, pumpswap, raydium_clmm};
+use anchor_lang::Discriminator;
+
+#[inline]
+fn bytes_at<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    data.get(offset..offset + N)
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| error!(ArbitrageError::InvalidPoolData))
+}
+
+/// 按偏移读取 u64（小端）
+#[inline]
+pub(crate) fn u64_at(data: &[u8], offset: usize) -> Result<u64> {
+    Ok(u64::from_le_bytes(bytes_at(data, offset)?))
+}
+
+#[inline]
+fn u16_at(data: &[u8], offset: usize) -> Result<u16> {
+    Ok(u16::from_le_bytes(bytes_at(data, offset)?))
+}
+
+#[inline]
+fn u32_at(data: &[u8], offset: usize) -> Result<u32> {
+    Ok(u32::from_le_bytes(bytes_at(data, offset)?))
+}
+
+#[inline]
+fn i32_at(data: &[u8], offset: usize) -> Result<i32> {
+    Ok(i32::from_le_bytes(bytes_at(data, offset)?))
+}
+
+#[inline]
+fn u128_at(data: &[u8], offset: usize) -> Result<u128> {
+    Ok(u128::from_le_bytes(bytes_at(data, offset)?))
+}
+
+#[inline]
+fn i128_at(data: &[u8], offset: usize) -> Result<i128> {
+    Ok(i128::from_le_bytes(bytes_at(data, offset)?))
+}
+
+#[inline]
+fn require_len(data: &[u8], min_len: usize) -> Result<()> {
+    require!(data.len() >= min_len, ArbitrageError::InvalidPoolData);
+    Ok(())
+}
+
+#[inline]
+fn require_discriminator(data: &[u8], discriminator: &[u8]) -> Result<()> {
+    require!(data.starts_with(discriminator), ArbitrageError::InvalidPoolData);
+    Ok(())
+}
+
+/// Raydium CPMM `PoolState`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpmmPoolState {
+    pub amm_config: Pubkey,
+    pub token_0_vault: Pubkey,
+    pub token_1_vault: Pubkey,
+    pub token_0_mint: Pubkey,
+    pub token_1_mint: Pubkey,
+    pub observation_key: Pubkey,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+}
+
+impl CpmmPoolState {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require_len(data, layout::CPMM_POOL_QUOTE_MIN_LEN)?;
+        Ok(Self {
+            amm_config: pubkey_at(data, layout::CPMM_POOL_AMM_CONFIG)?,
+            token_0_vault: pubkey_at(data, layout::CPMM_POOL_TOKEN_0_VAULT)?,
+            token_1_vault: pubkey_at(data, layout::CPMM_POOL_TOKEN_1_VAULT)?,
+            token_0_mint: pubkey_at(data, layout::CPMM_POOL_TOKEN_0_MINT)?,
+            token_1_mint: pubkey_at(data, layout::CPMM_POOL_TOKEN_1_MINT)?,
+            observation_key: pubkey_at(data, layout::CPMM_POOL_OBSERVATION_KEY)?,
+            protocol_fees_token_0: u64_at(data, layout::CPMM_POOL_PROTOCOL_FEES_TOKEN_0)?,
+            protocol_fees_token_1: u64_at(data, layout::CPMM_POOL_PROTOCOL_FEES_TOKEN_1)?,
+            fund_fees_token_0: u64_at(data, layout::CPMM_POOL_FUND_FEES_TOKEN_0)?,
+            fund_fees_token_1: u64_at(data, layout::CPMM_POOL_FUND_FEES_TOKEN_1)?,
+        })
+    }
+
+    /// 储备 = vault 余额 − 未提取 protocol/fund 费（与 cp-swap `vault_amount_without_fee` 一致）
+    pub fn reserves(&self, vault_0_amount: u64, vault_1_amount: u64) -> (u64, u64) {
+        (
+            vault_0_amount
+                .saturating_sub(self.protocol_fees_token_0)
+                .saturating_sub(self.fund_fees_token_0),
+            vault_1_amount
+                .saturating_sub(self.protocol_fees_token_1)
+                .saturating_sub(self.fund_fees_token_1),
+        )
+    }
+}
+
+/// Raydium CPMM `AmmConfig`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpmmAmmConfig {
+    /// 1e6 分母
+    pub trade_fee_rate: u64,
+}
+
+impl CpmmAmmConfig {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require_len(data, layout::CPMM_CONFIG_MIN_LEN)?;
+        Ok(Self { trade_fee_rate: u64_at(data, layout::CPMM_CONFIG_TRADE_FEE_RATE)? })
+    }
+}
+
+/// Raydium CLMM `PoolState`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClmmPoolState {
+    pub amm_config: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub observation_key: Pubkey,
+    pub tick_spacing: u16,
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub status: u8,
+    /// 池内 tick array 初始化位图（覆盖 start index ∈ [-512, 512) × 60 × tick_spacing；其外由 TickArrayBitmapExtension 记录）
+    pub tick_array_bitmap: [u64; 16],
+}
+
+impl ClmmPoolState {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require_len(data, layout::CLMM_POOL_QUOTE_MIN_LEN)?;
+        require_discriminator(data, raydium_clmm::accounts::PoolState::DISCRIMINATOR)?;
+        let mut tick_array_bitmap = [0u64; 16];
+        for (i, word) in tick_array_bitmap.iter_mut().enumerate() {
+            *word = u64_at(data, layout::CLMM_POOL_TICK_ARRAY_BITMAP + i * 8)?;
+        }
+        Ok(Self {
+            amm_config: pubkey_at(data, layout::CLMM_POOL_AMM_CONFIG)?,
+            token_mint_0: pubkey_at(data, layout::CLMM_POOL_TOKEN_MINT_0)?,
+            token_mint_1: pubkey_at(data, layout::CLMM_POOL_TOKEN_MINT_1)?,
+            token_vault_0: pubkey_at(data, layout::CLMM_POOL_TOKEN_VAULT_0)?,
+            token_vault_1: pubkey_at(data, layout::CLMM_POOL_TOKEN_VAULT_1)?,
+            observation_key: pubkey_at(data, layout::CLMM_POOL_OBSERVATION_KEY)?,
+            tick_spacing: u16_at(data, layout::CLMM_POOL_TICK_SPACING)?,
+            liquidity: u128_at(data, layout::CLMM_POOL_LIQUIDITY)?,
+            sqrt_price_x64: u128_at(data, layout::CLMM_POOL_SQRT_PRICE_X64)?,
+            tick_current: i32_at(data, layout::CLMM_POOL_TICK_CURRENT)?,
+            status: data[layout::CLMM_POOL_STATUS],
+            tick_array_bitmap,
+        })
+    }
+}
+
+/// Raydium CLMM `AmmConfig`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClmmAmmConfig {
+    /// 1e6 分母
+    pub trade_fee_rate: u32,
+    pub tick_spacing: u16,
+}
+
+impl ClmmAmmConfig {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require_len(data, layout::CLMM_CONFIG_MIN_LEN)?;
+        require_discriminator(data, raydium_clmm::accounts::AmmConfig::DISCRIMINATOR)?;
+        Ok(Self {
+            trade_fee_rate: u32_at(data, layout::CLMM_CONFIG_TRADE_FEE_RATE)?,
+            tick_spacing: u16_at(data, layout::CLMM_CONFIG_TICK_SPACING)?,
+        })
+    }
+}
+
+/// CLMM 单个 tick（报价只需净/总流动性）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickState {
+    pub tick: i32,
+    pub liquidity_net: i128,
+    pub liquidity_gross: u128,
+}
+
+impl TickState {
+    #[inline]
+    pub fn is_initialized(&self) -> bool {
+        self.liquidity_gross != 0
+    }
+}
+
+/// Raydium CLMM `TickArrayState`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TickArrayState {
+    pub pool_id: Pubkey,
+    pub start_tick_index: i32,
+    pub ticks: Vec<TickState>,
+}
+
+impl TickArrayState {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require_len(data, layout::CLMM_TICK_ARRAY_MIN_LEN)?;
+        require_discriminator(data, raydium_clmm::accounts::TickArrayState::DISCRIMINATOR)?;
+        let ticks = (0..RAYDIUM_CLMM_TICK_ARRAY_SIZE as usize)
+            .map(|i| {
+                let base = layout::CLMM_TICK_ARRAY_TICKS + i * layout::CLMM_TICK_STATE_LEN;
+                Ok(TickState {
+                    tick: i32_at(data, base)?,
+                    liquidity_net: i128_at(data, base + layout::CLMM_TICK_STATE_LIQUIDITY_NET)?,
+                    liquidity_gross: u128_at(data, base + layout::CLMM_TICK_STATE_LIQUIDITY_GROSS)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            pool_id: pubkey_at(data, layout::CLMM_TICK_ARRAY_POOL_ID)?,
+            start_tick_index: i32_at(data, layout::CLMM_TICK_ARRAY_START_TICK_INDEX)?,
+            ticks,
+        })
+    }
+}
+
+/// 扩展位图中单个子位图（512 个 tick array）的 u64 字数
+pub const CLMM_BITMAP_WORDS: usize = 8;
+/// 扩展位图正/负方向各自的子位图数
+pub const CLMM_EXTENSION_BITMAP_COUNT: usize = 14;
+
+/// Raydium CLMM `TickArrayBitmapExtension`（默认位图覆盖范围之外的 tick array 初始化位图）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TickArrayBitmapExtension {
+    pub pool_id: Pubkey,
+    pub positive_tick_array_bitmap: [[u64; CLMM_BITMAP_WORDS]; CLMM_EXTENSION_BITMAP_COUNT],
+    pub negative_tick_array_bitmap: [[u64; CLMM_BITMAP_WORDS]; CLMM_EXTENSION_BITMAP_COUNT],
+}
+
+impl TickArrayBitmapExtension {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require_len(data, layout::CLMM_BITMAP_EXTENSION_MIN_LEN)?;
+        require_discriminator(data, raydium_clmm::accounts::TickArrayBitmapExtension::DISCRIMINATOR)?;
+        let read = |base: usize| -> Result<[[u64; CLMM_BITMAP_WORDS]; CLMM_EXTENSION_BITMAP_COUNT]> {
+            let mut bitmaps = [[0u64; CLMM_BITMAP_WORDS]; CLMM_EXTENSION_BITMAP_COUNT];
+            for (i, bitmap) in bitmaps.iter_mut().enumerate() {
+                for (j, word) in bitmap.iter_mut().enumerate() {
+                    *word = u64_at(data, base + (i * CLMM_BITMAP_WORDS + j) * 8)?;
+                }
+            }
+            Ok(bitmaps)
+        };
+        Ok(Self {
+            pool_id: pubkey_at(data, layout::CLMM_BITMAP_EXTENSION_POOL_ID)?,
+            positive_tick_array_bitmap: read(layout::CLMM_BITMAP_EXTENSION_POSITIVE)?,
+            negative_tick_array_bitmap: read(layout::CLMM_BITMAP_EXTENSION_NEGATIVE)?,
+        })
+    }
+}
+
+/// PumpFun `BondingCurve`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PumpFunBondingCurve {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub token_total_supply: u64,
+    /// 已完成（迁移至 PumpSwap），不再可交易
+    pub complete: bool,
+    pub creator: Pubkey,
+}
+
+impl PumpFunBondingCurve {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require_len(data, layout::PUMPFUN_BONDING_CURVE_MIN_LEN)?;
+        require_discriminator(data, pumpfun::accounts::BondingCurve::DISCRIMINATOR)?;
+        Ok(Self {
+            virtual_token_reserves: u64_at(data, layout::PUMPFUN_BONDING_CURVE_VIRTUAL_TOKEN_RESERVES)?,
+            virtual_sol_reserves: u64_at(data, layout::PUMPFUN_BONDING_CURVE_VIRTUAL_SOL_RESERVES)?,
+            real_token_reserves: u64_at(data, layout::PUMPFUN_BONDING_CURVE_REAL_TOKEN_RESERVES)?,
+            real_sol_reserves: u64_at(data, layout::PUMPFUN_BONDING_CURVE_REAL_SOL_RESERVES)?,
+            token_total_supply: u64_at(data, layout::PUMPFUN_BONDING_CURVE_TOKEN_TOTAL_SUPPLY)?,
+            complete: data[layout::PUMPFUN_BONDING_CURVE_COMPLETE] != 0,
+            creator: pubkey_at(data, layout::PUMPFUN_BONDING_CURVE_CREATOR)?,
+        })
+    }
+}
+
+/// PumpFun `Global`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PumpFunGlobal {
+    pub fee_recipient: Pubkey,
+    pub fee_basis_points: u64,
+    /// 旧版本账户无此字段时为 0
+    pub creator_fee_basis_points: u64,
+}
+
+impl PumpFunGlobal {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require_len(data, layout::PUMPFUN_GLOBAL_MIN_LEN)?;
+        require_discriminator(data, pumpfun::accounts::Global::DISCRIMINATOR)?;
+        Ok(Self {
+            fee_recipient: pubkey_at(data, layout::PUMPFUN_GLOBAL_FEE_RECIPIENT)?,
+            fee_basis_points: u64_at(data, layout::PUMPFUN_GLOBAL_FEE_BASIS_POINTS)?,
+            creator_fee_basis_points: u64_at(data, layout::PUMPFUN_GLOBAL_CREATOR_FEE_BASIS_POINTS).unwrap_or(0),
+        })
+    }
+
+    /// 协议费 + 创作者费
+    pub fn total_fee_bps(&self) -> u64 {
+        self.fee_basis_points.saturating_add(self.creator_fee_basis_points)
+    }
+}
+
+/// PumpSwap `Pool`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PumpSwapPool {
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub pool_base_token_account: Pubkey,
+    pub pool_quote_token_account: Pubkey,
+    pub lp_supply: u64,
+    pub coin_creator: Pubkey,
+}
+
+impl PumpSwapPool {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require_len(data, layout::PUMPSWAP_POOL_MIN_LEN)?;
+        require_discriminator(data, pumpswap::accounts::Pool::DISCRIMINATOR)?;
+        Ok(Self {
+            base_mint: pubkey_at(data, layout::PUMPSWAP_POOL_BASE_MINT)?,
+            quote_mint: pubkey_at(data, layout::PUMPSWAP_POOL_QUOTE_MINT)?,
+            lp_mint: pubkey_at(data, layout::PUMPSWAP_POOL_LP_MINT)?,
+            pool_base_token_account: pubkey_at(data, layout::PUMPSWAP_POOL_BASE_TOKEN_ACCOUNT)?,
+            pool_quote_token_account: pubkey_at(data, layout::PUMPSWAP_POOL_QUOTE_TOKEN_ACCOUNT)?,
+            lp_supply: u64_at(data, layout::PUMPSWAP_POOL_LP_SUPPLY)?,
+            coin_creator: pubkey_at(data, layout::PUMPSWAP_POOL_COIN_CREATOR)?,
+        })
+    }
+}
+
+/// PumpSwap `GlobalConfig`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PumpSwapGlobalConfig {
+    pub lp_fee_basis_points: u64,
+    pub protocol_fee_basis_points: u64,
+    /// 旧版本账户无此字段时为 0
+    pub coin_creator_fee_basis_points: u64,
+}
+
+impl PumpSwapGlobalConfig {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require_len(data, layout::PUMPSWAP_GLOBAL_MIN_LEN)?;
+        require_discriminator(data, pumpswap::accounts::GlobalConfig::DISCRIMINATOR)?;
+        Ok(Self {
+            lp_fee_basis_points: u64_at(data, layout::PUMPSWAP_GLOBAL_LP_FEE_BASIS_POINTS)?,
+            protocol_fee_basis_points: u64_at(data, layout::PUMPSWAP_GLOBAL_PROTOCOL_FEE_BASIS_POINTS)?,
+            coin_creator_fee_basis_points: u64_at(data, layout::PUMPSWAP_GLOBAL_COIN_CREATOR_FEE_BASIS_POINTS)
+                .unwrap_or(0),
+        })
+    }
+
+    /// [lp, protocol, coin_creator] 费率；coin_creator 未设置的池不收创作者费
+    pub fn fee_bps(&self, coin_creator: &Pubkey) -> [u64; 3] {
+        let creator_fee = if *coin_creator == Pubkey::default() { 0 } else { self.coin_creator_fee_basis_points };
+        [self.lp_fee_basis_points, self.protocol_fee_basis_points, creator_fee]
+    }
+}
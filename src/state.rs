@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use crate::dex_router::SwapResult;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, AnchorSerialize, AnchorDeserialize)]
 pub enum DexType {
@@ -16,6 +17,77 @@ pub enum ContractType {
     PumpSwap = 3,
 }
 
+/// 指令参数协议版本（`execute_arbitrage_v3` 的 payload 首字节即标签）
+/// - V2（`0x02`）：Borsh `ArbitrageParams`，与 `execute_arbitrage` 同一布局（CPI_VERSION 1 起未变），新增选项取默认；
+/// - V3（`0x03`）：Borsh `ArbitrageParamsV3`，承载逐步金额/成交模式、PDA bump 与整笔选项；
+/// - V3 紧凑（`0x83`）：与 V3 语义一致的位打包编码（见 `compact.rs`），高位置位表示紧凑。
+///
+/// 客户端与程序可独立升级：新版本以新标签发布，旧标签保持可用，未知标签以 `InvalidInstructionData` 拒绝。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    V2 = 0x02,
+    V3 = 0x03,
+    V3Compact = 0x83,
+}
+
+impl ProtocolVersion {
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0x02 => Some(ProtocolVersion::V2),
+            0x03 => Some(ProtocolVersion::V3),
+            0x83 => Some(ProtocolVersion::V3Compact),
+            _ => None,
+        }
+    }
+}
+
+/// 每步 amount_in 的来源
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub enum AmountMode {
+    /// 默认：首步取 `input_amount`，其后取上一步真实产出（余额差）
+    Chained,
+    /// 取输入 ATA 当前全部余额（顺带清扫历史残留 dust）；仅限首步
+    FullBalance,
+    /// 取输入 ATA 当前余额的万分比（1..=10000），客户端无需发送前读取余额；仅限首步
+    BalanceBps(u16),
+}
+
+/// 运行时日志级别（CU 敏感：生产默认仅保留错误与摘要）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, AnchorSerialize, AnchorDeserialize)]
+pub enum LogLevel {
+    /// 仅错误日志
+    Error,
+    /// 错误 + 每步/终局摘要
+    #[default]
+    Summary,
+    /// 附加诊断日志；未启用 `verbose-logs` 特性时等同 Summary
+    Verbose,
+}
+
+impl LogLevel {
+    /// 是否输出每步/终局摘要
+    #[inline]
+    pub fn summary(self) -> bool {
+        self >= LogLevel::Summary
+    }
+
+    /// 是否输出诊断日志：编译期特性与运行时级别同时满足
+    #[inline]
+    pub fn verbose(self) -> bool {
+        cfg!(feature = "verbose-logs") && self >= LogLevel::Verbose
+    }
+}
+
+/// 每步成交模式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub enum SwapMode {
+    /// 精确输入：amount_in 全部卖出，`minimum_amount_out` 为产出下限
+    ExactIn,
+    /// 精确输出：`minimum_amount_out` 为目标产出，amount_in 为可花费上限
+    /// （CPMM swap_base_output / CLMM is_base_input=0 / Pump 系列按 token 数量买入）
+    ExactOut,
+}
+
 #[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
 pub struct PathStep {
     pub pool_id: Option<Pubkey>,
@@ -25,12 +97,12 @@ pub struct PathStep {
     pub minimum_amount_out: u64,
 }
 
-
 /// 协议与参数（V2 indices 协议）
 /// 用途：
 /// - `PathStep` 描述每步的池/DEX与最小产出；
 /// - `PathAccountMappingV2.indices` 为每步指向全局 remaining_accounts 的索引；
 /// - `ArbitrageParams` 汇总整条路径的输入、阈值与映射，供合约入口解析执行。
+///
 /// V2（indices 协议）：指向全局 remaining_accounts 的索引
 #[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
 pub struct PathAccountMappingV2 {
@@ -46,4 +118,291 @@ pub struct ArbitrageParams {
     pub max_slippage_bps: u16,
     pub path_steps: Vec<PathStep>,
     pub account_mappings_v2: Vec<PathAccountMappingV2>,
-}
\ No newline at end of file
+}
+
+/// V3 单步：V2 `PathStep` 字段 + 逐步金额来源、成交模式与 CLMM 价格限制
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub struct PathStepV3 {
+    pub pool_id: Option<Pubkey>,
+    pub dex_type: DexType,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    /// 主腿（本步自身的池）产出下限；无拆分腿时即本步下限。
+    /// 有拆分腿时不约束各腿合计：各拆分腿由各自 `SplitLeg.minimum_amount_out` 约束，整步下限为各腿下限之和
+    pub minimum_amount_out: u64,
+    pub amount_mode: AmountMode,
+    pub swap_mode: SwapMode,
+    /// CLMM 价格上/下限（Q64.64）；0 表示按方向取协议边界（MIN+1 / MAX-1），其余 DEX 忽略
+    pub sqrt_price_limit_x64: u128,
+    /// 拆分腿：按万分比从本步 amount_in 分出给同交易对的其它池，主腿（本步自身的池/映射）取剩余；
+    /// 各腿产出求和后作为下一步输入。为空即单池步骤。
+    pub split_legs: Vec<SplitLeg>,
+}
+
+/// 同一步内的并行拆分腿（input/output mint 与所属步一致，仅支持 ExactIn）
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub struct SplitLeg {
+    pub pool_id: Option<Pubkey>,
+    pub dex_type: DexType,
+    /// 占本步 amount_in 的万分比（1..10000）；各腿合计须 < 10000，主腿至少保留 1bp
+    pub share_bps: u16,
+    /// 本腿产出下限
+    pub minimum_amount_out: u64,
+    /// 同 `PathStepV3.sqrt_price_limit_x64`
+    pub sqrt_price_limit_x64: u128,
+    /// 本腿账户映射（indices 指向同一全局表）
+    pub mapping: PathAccountMappingV3,
+}
+
+/// V3 账户映射：V2 indices + 客户端预计算的 PDA bump
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub struct PathAccountMappingV3 {
+    pub dex_type: DexType,
+    pub contract_type: ContractType,
+    pub indices: Vec<u8>,
+    /// 提供时链上用 `create_program_address` 复核，缺省回退 `find_program_address`
+    pub bumps: PdaBumps,
+}
+
+/// 每步 PDA bump（各字段按 DEX 取义，非该 DEX 的字段忽略）
+///
+/// 链上只确认 bump 能推出合法地址，不校验是否 canonical；非 canonical bump 推出的地址
+/// 与全局表/目标程序期望的账户不同，会在账户定位或 CPI 时失败。客户端应传 canonical bump。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub struct PdaBumps {
+    /// PumpFun `global` / PumpSwap `global_config`
+    pub global: Option<u8>,
+    /// `__event_authority`
+    pub event_authority: Option<u8>,
+    /// PumpFun `bonding-curve`
+    pub pool: Option<u8>,
+    /// PumpFun `creator-vault` / PumpSwap `creator_vault`（authority）
+    pub creator_vault: Option<u8>,
+    /// PumpFun `global_volume_accumulator`（买入）
+    pub global_volume_accumulator: Option<u8>,
+    /// PumpFun `user_volume_accumulator`（买入）
+    pub user_volume_accumulator: Option<u8>,
+}
+
+/// V3 参数（`execute_arbitrage_v3` 的 Borsh 形态，payload 标签 `0x03`）
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub struct ArbitrageParamsV3 {
+    pub input_amount: u64,
+    pub min_profit_lamports: u64,
+    pub max_slippage_bps: u16,
+    pub path_steps: Vec<PathStepV3>,
+    pub account_mappings: Vec<PathAccountMappingV3>,
+    /// 执行成功后关闭余额为 0 的中间 mint 用户 ATA，租金退回 `user`（起止 mint 的 ATA 不动）
+    pub close_intermediate_atas: bool,
+    /// 每腿 CPI 前（主腿与各拆分腿逐一检查）要求的最少剩余 CU；不足则提前以 `ComputeBudgetExhausted` 失败，而非在 CPI 中途耗尽
+    pub min_cu_reserve: Option<u64>,
+    /// 日志级别；None 取 `LogLevel::Summary`
+    pub log_level: Option<LogLevel>,
+    /// 同一指令内的其它独立套利环（共享全局账户表），在主环（上面的顶层字段）之后依次执行
+    pub extra_cycles: Vec<ArbitrageCycle>,
+    /// 先报价后执行：发起任何 CPI 前按当前池状态模拟主环，报价不满足利润要求或报价出错时不交易、返回 Ok 并发 `Skipped` 事件
+    /// （语义同 `ArbitrageCycle.skip_if_unprofitable`，作用于主环）
+    pub quote_before_execute: bool,
+}
+
+/// 独立套利环：各自的投入、利润要求与路径；按环校验利润
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub struct ArbitrageCycle {
+    pub input_amount: u64,
+    pub min_profit_lamports: u64,
+    pub path_steps: Vec<PathStepV3>,
+    pub account_mappings: Vec<PathAccountMappingV3>,
+    /// 执行前按当前池状态预报价，不满足利润要求或报价出错时跳过本环（发 `Skipped` 事件）而非整笔回滚；
+    /// 路径含无法报价的腿（ExactOut）时照常执行
+    pub skip_if_unprofitable: bool,
+}
+
+/// 执行入口的返回数据（Borsh，经 `set_return_data`）：调用方经 CPI 或模拟读取结果，无需解析日志
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub struct ArbitrageOutcome {
+    /// 按 cycle_index 顺序（主环为 0）
+    pub cycles: Vec<CycleOutcome>,
+    /// 整条指令消耗的 CU（入口至写返回数据前）
+    pub compute_units_used: u64,
+    /// 逐步明细超出返回数据上限（1024 字节）而被省略，仅保留各环汇总
+    pub steps_truncated: bool,
+}
+
+/// 单个套利环的执行结果
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub struct CycleOutcome {
+    pub cycle_index: u8,
+    /// 因预报价不足或报价出错而跳过（未交易：final_amount/profit/compute_units_used 为 0、steps 为空；
+    /// initial_amount 为报价输入，报价出错时为本环 `input_amount`）
+    pub skipped: bool,
+    pub initial_amount: u64,
+    pub final_amount: u64,
+    pub profit: u64,
+    pub compute_units_used: u64,
+    /// 每步各腿聚合后的 (amount_in, amount_out, fee_amount)
+    pub steps: Vec<SwapResult>,
+}
+
+impl CycleOutcome {
+    pub fn skipped(cycle_index: u8, initial_amount: u64) -> Self {
+        Self {
+            cycle_index,
+            skipped: true,
+            initial_amount,
+            final_amount: 0,
+            profit: 0,
+            compute_units_used: 0,
+            steps: Vec::new(),
+        }
+    }
+}
+
+/// 单腿账户映射的借用视图（V2/V3 映射与 V3 紧凑编码共用，indices 不复制）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MappingView<'a> {
+    pub dex_type: DexType,
+    pub contract_type: ContractType,
+    pub indices: &'a [u8],
+    pub bumps: PdaBumps,
+}
+
+/// 单步执行视图：执行与推导只读此结构，各协议版本各自零拷贝构造
+#[derive(Clone, Copy, Debug)]
+pub struct StepView<'a> {
+    pub dex_type: DexType,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub minimum_amount_out: u64,
+    pub amount_mode: AmountMode,
+    pub swap_mode: SwapMode,
+    pub sqrt_price_limit_x64: u128,
+    pub mapping: MappingView<'a>,
+    /// V2 与紧凑编码不承载拆分腿，恒为空
+    pub split_legs: &'a [SplitLeg],
+}
+
+impl PathStep {
+    /// V2 步不含 V3 新增字段：按 Chained/ExactIn、协议边界限价、无 bump 执行（与 CPI_VERSION 1 行为一致）
+    pub fn view<'a>(&self, mapping: &'a PathAccountMappingV2) -> StepView<'a> {
+        StepView {
+            dex_type: self.dex_type,
+            input_mint: self.input_mint,
+            output_mint: self.output_mint,
+            minimum_amount_out: self.minimum_amount_out,
+            amount_mode: AmountMode::Chained,
+            swap_mode: SwapMode::ExactIn,
+            sqrt_price_limit_x64: 0,
+            mapping: MappingView {
+                dex_type: mapping.dex_type,
+                contract_type: mapping.contract_type,
+                indices: &mapping.indices,
+                bumps: PdaBumps::default(),
+            },
+            split_legs: &[],
+        }
+    }
+}
+
+impl PathStepV3 {
+    pub fn view<'a>(&'a self, mapping: &'a PathAccountMappingV3) -> StepView<'a> {
+        StepView {
+            dex_type: self.dex_type,
+            input_mint: self.input_mint,
+            output_mint: self.output_mint,
+            minimum_amount_out: self.minimum_amount_out,
+            amount_mode: self.amount_mode,
+            swap_mode: self.swap_mode,
+            sqrt_price_limit_x64: self.sqrt_price_limit_x64,
+            mapping: mapping.view(),
+            split_legs: &self.split_legs,
+        }
+    }
+}
+
+impl PathAccountMappingV3 {
+    pub fn view(&self) -> MappingView<'_> {
+        MappingView {
+            dex_type: self.dex_type,
+            contract_type: self.contract_type,
+            indices: &self.indices,
+            bumps: self.bumps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// V2 参数体须与 CPI_VERSION 1 客户端逐字节一致（Borsh 无可选尾部，任何新增字段都会破坏旧客户端）
+    #[test]
+    fn v2_params_layout_is_frozen() {
+        let (pool, input_mint, output_mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let params = ArbitrageParams {
+            input_amount: 1_000,
+            min_profit_lamports: 7,
+            max_slippage_bps: 50,
+            path_steps: vec![PathStep {
+                pool_id: Some(pool),
+                dex_type: DexType::PumpSwap,
+                input_mint,
+                output_mint,
+                minimum_amount_out: 9,
+            }],
+            account_mappings_v2: vec![PathAccountMappingV2 {
+                dex_type: DexType::PumpSwap,
+                contract_type: ContractType::PumpSwap,
+                indices: vec![4, 5],
+            }],
+        };
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&1_000u64.to_le_bytes());
+        expected.extend_from_slice(&7u64.to_le_bytes());
+        expected.extend_from_slice(&50u16.to_le_bytes());
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        expected.push(1);
+        expected.extend_from_slice(pool.as_ref());
+        expected.push(3);
+        expected.extend_from_slice(input_mint.as_ref());
+        expected.extend_from_slice(output_mint.as_ref());
+        expected.extend_from_slice(&9u64.to_le_bytes());
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        expected.extend_from_slice(&[3, 3]);
+        expected.extend_from_slice(&2u32.to_le_bytes());
+        expected.extend_from_slice(&[4, 5]);
+
+        assert_eq!(params.try_to_vec().unwrap(), expected);
+        let decoded = ArbitrageParams::try_from_slice(&expected).unwrap();
+        assert_eq!(decoded.path_steps[0].output_mint, output_mint);
+        assert_eq!(decoded.account_mappings_v2[0].indices, vec![4, 5]);
+    }
+
+    #[test]
+    fn v2_steps_view_with_cpi_version_1_defaults() {
+        let step = PathStep {
+            pool_id: None,
+            dex_type: DexType::RaydiumCpmm,
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            minimum_amount_out: 1,
+        };
+        let mapping = PathAccountMappingV2 { dex_type: DexType::RaydiumCpmm, contract_type: ContractType::CPMM, indices: vec![1, 2, 3] };
+        let view = step.view(&mapping);
+        assert_eq!(view.amount_mode, AmountMode::Chained);
+        assert_eq!(view.swap_mode, SwapMode::ExactIn);
+        assert_eq!(view.sqrt_price_limit_x64, 0);
+        assert_eq!(view.mapping.bumps, PdaBumps::default());
+        assert_eq!(view.mapping.indices, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn protocol_tags_round_trip_and_reject_unknown() {
+        for version in [ProtocolVersion::V2, ProtocolVersion::V3, ProtocolVersion::V3Compact] {
+            assert_eq!(ProtocolVersion::from_tag(version as u8), Some(version));
+        }
+        for tag in [0x00, 0x01, 0x04, 0x82, 0x84, 0xff] {
+            assert_eq!(ProtocolVersion::from_tag(tag), None);
+        }
+    }
+}